@@ -11,10 +11,52 @@
 //!   perf record -g ./target/release/examples/bench
 //!   perf report
 
+use rsmarisa::base::{build_flags, CacheLevel};
+use rsmarisa::grimoire::vector::bit_vector::BitVector;
+use rsmarisa::grimoire::vector::pop_count::{popcount_unit, popcount_units, Unit};
 use rsmarisa::{Agent, Keyset, Trie};
+use std::alloc::{GlobalAlloc, Layout, System};
 use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::time::Instant;
 
+/// System allocator wrapper that counts allocations, for `bench_keyset_reserve`.
+///
+/// Counting is off unless `COUNTING` is set, so the other benchmarks only
+/// pay for one relaxed load per allocation.
+struct CountingAlloc;
+
+static COUNTING: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+impl CountingAlloc {
+    #[inline]
+    fn count() {
+        if COUNTING.load(Ordering::Relaxed) {
+            ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        Self::count();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        Self::count();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
 /// Number of iterations for each benchmark operation.
 const ITERATIONS: usize = 1000;
 
@@ -224,6 +266,285 @@ fn bench_reverse_lookup(trie: &Trie, num_keys: usize) {
     );
 }
 
+/// Reverse lookups of long keys, whose suffixes are restored through many
+/// tail and next-trie links.
+fn bench_reverse_lookup_long() {
+    let mut x = 0x9E37_79B9_7F4A_7C15u64;
+    let keys: Vec<String> = (0..20_000)
+        .map(|_| {
+            let mut key = String::from("https://example.com");
+            for _ in 0..40 {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                key.push('/');
+                key.push_str(SYLLABLES[(x % 8) as usize]);
+                key.push_str(&format!("{:x}", x >> 52));
+            }
+            key
+        })
+        .collect();
+    let mut keyset = Keyset::new();
+    for key in &keys {
+        keyset.push_back_str(key).unwrap();
+    }
+    let mut trie = Trie::new();
+    trie.build(&mut keyset, 0);
+
+    let mut agent = Agent::new();
+    let start = Instant::now();
+    let mut total_bytes = 0;
+    for _ in 0..20 {
+        for id in 0..trie.num_keys() {
+            agent.set_query_id(id);
+            trie.reverse_lookup(&mut agent);
+            total_bytes += black_box(agent.key().as_bytes()).len();
+        }
+    }
+    let elapsed = start.elapsed();
+    eprintln!(
+        "reverse_lookup(long):   {:>8.2} ms  ({} ids, {} bytes)",
+        elapsed.as_secs_f64() * 1000.0,
+        20 * trie.num_keys(),
+        total_bytes,
+    );
+}
+
+/// Size of the bit data used by `bench_popcount` (100MB).
+const POPCOUNT_BYTES: usize = 100 * 1024 * 1024;
+
+/// Compare scalar and SIMD per-unit popcount, the inner loop of
+/// `BitVector::build`, over 100MB of pseudo-random units.
+fn bench_popcount() {
+    let mut x = 0x9E37_79B9_7F4A_7C15u64;
+    let units: Vec<Unit> = (0..POPCOUNT_BYTES / 8)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        })
+        .collect();
+
+    let start = Instant::now();
+    let scalar_1s: usize = units.iter().map(|&u| popcount_unit(black_box(u))).sum();
+    let scalar_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let mut counts = vec![0u8; units.len()];
+    popcount_units(black_box(&units), &mut counts);
+    let simd_1s: usize = counts.iter().map(|&c| c as usize).sum();
+    let simd_elapsed = start.elapsed();
+
+    assert_eq!(scalar_1s, simd_1s);
+    eprintln!(
+        "popcount (scalar):      {:>8.2} ms  ({} units, {} ones)",
+        scalar_elapsed.as_secs_f64() * 1000.0,
+        units.len(),
+        scalar_1s,
+    );
+    eprintln!(
+        "popcount (units):       {:>8.2} ms  ({} units, {} ones)",
+        simd_elapsed.as_secs_f64() * 1000.0,
+        units.len(),
+        simd_1s,
+    );
+}
+
+/// Compare allocation counts of filling a keyset with and without
+/// `Keyset::reserve`.
+fn bench_keyset_reserve(keys: &[String]) {
+    let total_bytes: usize = keys.iter().map(|key| key.len()).sum();
+
+    for reserve in [false, true] {
+        ALLOCATIONS.store(0, Ordering::Relaxed);
+        COUNTING.store(true, Ordering::Relaxed);
+        let start = Instant::now();
+        let mut keyset = Keyset::new();
+        if reserve {
+            keyset.reserve(keys.len(), total_bytes);
+        }
+        for key in keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let elapsed = start.elapsed();
+        COUNTING.store(false, Ordering::Relaxed);
+        let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+        black_box(&keyset);
+
+        eprintln!(
+            "keyset fill ({}): {:>8.2} ms  ({} keys, {} allocations)",
+            if reserve { "reserve" } else { "grow   " },
+            elapsed.as_secs_f64() * 1000.0,
+            keys.len(),
+            allocations,
+        );
+    }
+}
+
+/// Number of bits in the vector used by `bench_select` (64M).
+const SELECT_BITS: usize = 64 * 1024 * 1024;
+
+/// Compare `select0`/`select1` on a bit vector built eagerly with one
+/// built by `BitVector::build_lazy`, once its indices exist.
+fn bench_select() {
+    for lazy in [false, true] {
+        let mut x = 0x2545_F491_4F6C_DD1Du64;
+        let mut bv = BitVector::new();
+        for _ in 0..SELECT_BITS {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            bv.push_back(x & 1 != 0);
+        }
+        let num_1s = bv.num_1s();
+        let num_0s = SELECT_BITS - num_1s;
+        if lazy {
+            bv.build_lazy(true, true);
+        } else {
+            bv.build(true, true);
+        }
+        // Build any deferred index before timing
+        black_box(bv.select1(0) + bv.select0(0));
+
+        let start = Instant::now();
+        let mut sum = 0usize;
+        for i in 0..ITERATIONS * 1000 {
+            sum = sum.wrapping_add(bv.select1(black_box(i * 7919 % num_1s)));
+            sum = sum.wrapping_add(bv.select0(black_box(i * 7927 % num_0s)));
+        }
+        let elapsed = start.elapsed();
+        black_box(sum);
+
+        eprintln!(
+            "select ({}):          {:>8.2} ms  ({} selects)",
+            if lazy { "lazy " } else { "eager" },
+            elapsed.as_secs_f64() * 1000.0,
+            2 * ITERATIONS * 1000,
+        );
+    }
+}
+
+/// Compare tail builds with and without suffix merging on keys whose
+/// suffixes are random and therefore rarely shared.
+fn bench_tail_merge() {
+    let mut x = 0x2545_F491_4F6C_DD1Du64;
+    let keys: Vec<String> = (0..200_000)
+        .map(|i| {
+            let mut key = format!("{:05}", i % 50_000);
+            for _ in 0..12 {
+                x ^= x << 13;
+                x ^= x >> 7;
+                x ^= x << 17;
+                key.push((b'a' + (x % 26) as u8) as char);
+            }
+            key
+        })
+        .collect();
+
+    for (label, flags) in [("merge", 0), ("no-merge", build_flags::NO_TAIL_MERGE)] {
+        let mut keyset = Keyset::new();
+        for key in &keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let start = Instant::now();
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, flags);
+        let elapsed = start.elapsed();
+        eprintln!(
+            "build(tail {:<8}): {:>8.2} ms  ({} keys, {} bytes)",
+            label,
+            elapsed.as_secs_f64() * 1000.0,
+            trie.num_keys(),
+            trie.io_size(),
+        );
+    }
+}
+
+/// Compare lookup throughput on a 1M-key trie across cache levels.
+fn bench_cache_levels() {
+    let keys: Vec<String> = (0..1_000_000u64)
+        .map(|i| format!("{:x}/{}", i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 40, i))
+        .collect();
+    let queries: Vec<&str> = keys.iter().step_by(97).map(String::as_str).collect();
+
+    for level in [
+        CacheLevel::Tiny,
+        CacheLevel::Small,
+        CacheLevel::Normal,
+        CacheLevel::Large,
+        CacheLevel::Huge,
+    ] {
+        let mut keyset = Keyset::new();
+        for key in &keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, level as i32);
+
+        let mut agent = Agent::new();
+        let start = Instant::now();
+        for _ in 0..10 {
+            for query in &queries {
+                agent.set_query_str(query);
+                black_box(trie.lookup(&mut agent));
+            }
+        }
+        let elapsed = start.elapsed();
+        eprintln!(
+            "lookup(cache {:<6}):   {:>8.2} ms  ({} lookups, {} cache bytes)",
+            format!("{:?}", level),
+            elapsed.as_secs_f64() * 1000.0,
+            10 * queries.len(),
+            trie.stats().cache,
+        );
+    }
+}
+
+fn bench_lookup_filter() {
+    let keys: Vec<String> = (0..100_000u64)
+        .map(|i| format!("k{:x}", i.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 36))
+        .collect();
+    // Mostly misses: wrong first byte, too long, or absent
+    let queries: Vec<String> = (0..200_000u64)
+        .map(|i| match i % 4 {
+            0 => format!("x{:x}", i),
+            1 => format!("k{:x}-not-a-key-at-all", i),
+            2 => format!("k{:x}", i.wrapping_mul(0xD6E8_FEB8_6659_FD93) >> 36),
+            _ => keys[(i as usize) % keys.len()].clone(),
+        })
+        .collect();
+
+    for (label, flags) in [("off", 0), ("on", build_flags::LOOKUP_FILTER)] {
+        let mut keyset = Keyset::new();
+        for key in &keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, flags);
+
+        let mut agent = Agent::new();
+        let start = Instant::now();
+        let mut hits = 0;
+        for _ in 0..5 {
+            for query in &queries {
+                agent.set_query_str(query);
+                if black_box(trie.lookup(&mut agent)) {
+                    hits += 1;
+                }
+            }
+        }
+        let elapsed = start.elapsed();
+        eprintln!(
+            "lookup(filter {:<3}):    {:>8.2} ms  ({} lookups, {} hits)",
+            label,
+            elapsed.as_secs_f64() * 1000.0,
+            5 * queries.len(),
+            hits,
+        );
+    }
+}
+
 fn main() {
     eprintln!("=== rsmarisa bench (for perf profiling) ===\n");
 
@@ -262,6 +583,13 @@ fn main() {
     bench_common_prefix_search(&kana_trie, &common_prefix_queries);
     bench_lookup(&dict_trie, &dict_keys);
     bench_reverse_lookup(&dict_trie, num_dict_keys);
+    bench_reverse_lookup_long();
+    bench_popcount();
+    bench_select();
+    bench_keyset_reserve(&dict_keys);
+    bench_tail_merge();
+    bench_cache_levels();
+    bench_lookup_filter();
 
     eprintln!("\nDone.");
 }
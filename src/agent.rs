@@ -4,10 +4,11 @@
 //! - include/marisa/agent.h
 //! - lib/marisa/agent.cc
 
+use crate::grimoire::io;
 use crate::grimoire::trie::state::{State, StatusCode};
 use crate::key::Key;
 use crate::query::Query;
-use std::io;
+use alloc::boxed::Box;
 
 /// Agent encapsulates query, key, and state for trie operations.
 ///
@@ -51,12 +52,20 @@ impl Clone for Agent {
                         cloned.key.set_bytes(key_buf);
                     }
                     _ => {
-                        // Key is null or points to query - no update needed
+                        // Key is null or points to query - handled below
                     }
                 }
             }
         }
 
+        // The query owns its bytes, so a key pointing into the original
+        // query must point into the copy instead
+        if cloned.key.ptr().is_some() && cloned.key.ptr() == self.query.ptr() {
+            let length = cloned.key.length();
+            let query_bytes = cloned.query.as_bytes();
+            cloned.key.set_bytes(&query_bytes[..length]);
+        }
+
         cloned
     }
 }
@@ -77,7 +86,11 @@ impl Agent {
     }
 
     /// Returns a mutable reference to the query.
+    ///
+    /// Changing the query may move its bytes, so the key is cleared first
+    /// rather than left pointing into the old buffer.
     pub fn query_mut(&mut self) -> &mut Query {
+        self.key.set_bytes(&[]);
         &mut self.query
     }
 
@@ -91,27 +104,77 @@ impl Agent {
         &mut self.key
     }
 
-    /// Sets the query from a string slice.
-    pub fn set_query_str(&mut self, s: &str) {
+    /// Returns the bytes of the last key found, borrowed from the agent.
+    ///
+    /// Nothing is copied. After a lookup or common prefix search the bytes
+    /// are (a prefix of) the query buffer. After a predictive search or
+    /// reverse lookup they are the agent's key buffer. Setting a new query
+    /// clears the key, so this is empty until the next successful search.
+    /// Same as `self.key().as_bytes()`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("apple").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("apple");
+    /// if trie.lookup(&mut agent) {
+    ///     assert_eq!(agent.key_bytes(), b"apple");
+    /// }
+    ///
+    /// agent.set_query_str("banana");
+    /// assert!(agent.key_bytes().is_empty());
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn key_bytes(&self) -> &[u8] {
+        self.key.as_bytes()
+    }
+
+    /// Prepares for a new query.
+    ///
+    /// Resets the search state and clears the key, which may point into
+    /// the query buffer that is about to be overwritten or reallocated.
+    fn reset_for_query(&mut self) {
         if let Some(ref mut state) = self.state {
             state.reset();
         }
+        self.key.set_bytes(&[]);
+    }
+
+    /// Sets the query from a string slice.
+    ///
+    /// The string is copied into the agent's query buffer. The key of the
+    /// previous search is cleared.
+    pub fn set_query_str(&mut self, s: &str) {
+        self.reset_for_query();
         self.query.set_str(s);
     }
 
     /// Sets the query from a byte slice.
+    ///
+    /// Use this for binary keys that are not valid UTF-8. The bytes are
+    /// copied into the agent's query buffer, so the agent owns them. The
+    /// key of the previous search is cleared.
     pub fn set_query_bytes(&mut self, bytes: &[u8]) {
-        if let Some(ref mut state) = self.state {
-            state.reset();
-        }
+        self.reset_for_query();
         self.query.set_bytes(bytes);
     }
 
     /// Sets the query from a key ID for reverse lookup.
+    ///
+    /// Valid IDs are `0..trie.num_keys()`. The ID is not checked here, since
+    /// the agent does not know which trie it will be used with;
+    /// `Trie::reverse_lookup` panics on an out-of-range ID and reports both
+    /// the ID and the trie size. Use `Trie::restore` to get `None` instead.
     pub fn set_query_id(&mut self, key_id: usize) {
-        if let Some(ref mut state) = self.state {
-            state.reset();
-        }
+        self.reset_for_query();
         self.query.set_id(key_id);
     }
 
@@ -125,6 +188,36 @@ impl Agent {
         self.state.as_deref_mut()
     }
 
+    /// Returns how far into the query the last search advanced.
+    ///
+    /// After a failed lookup this is the number of query bytes that matched
+    /// a path in the trie before the search stopped, which lets callers
+    /// implement maximal-munch scanning. Returns 0 if the agent has no
+    /// state yet.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("apple").unwrap();
+    /// keyset.push_back_str("banana").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("bandana");
+    /// assert!(!trie.lookup(&mut agent));
+    /// // "ban" matched before the search stopped at 'd'
+    /// assert_eq!(agent.query_pos(), 3);
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn query_pos(&self) -> usize {
+        self.state().map_or(0, State::query_pos)
+    }
+
     /// Returns the query bytes alongside a mutable reference to the state.
     ///
     /// This is a borrow-splitting helper for inner loops in tail/trie matching
@@ -226,9 +319,45 @@ impl Agent {
         *self = Agent::new();
     }
 
+    /// Empties the agent for reuse, keeping its allocations.
+    ///
+    /// Unlike [`clear`](Self::clear), which replaces the agent with a new
+    /// one, this empties the query and key and resets the search state in
+    /// place. The query buffer and the state's key buffer and history keep
+    /// their capacity, so a pooled agent that serves many predictive
+    /// searches stops allocating once it has seen its deepest one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("apple").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("app");
+    /// assert!(trie.predictive_search(&mut agent));
+    ///
+    /// agent.reset();
+    /// assert!(agent.query().is_empty());
+    /// assert!(agent.has_state());
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn reset(&mut self) {
+        self.query.clear();
+        self.key = Key::new();
+        if let Some(ref mut state) = self.state {
+            state.clear();
+        }
+    }
+
     /// Swaps with another agent.
     pub fn swap(&mut self, other: &mut Agent) {
-        std::mem::swap(self, other);
+        core::mem::swap(self, other);
     }
 }
 
@@ -398,6 +527,104 @@ mod tests {
         assert!(cloned.has_state());
     }
 
+    #[test]
+    fn test_agent_clone_repoints_key_to_own_query() {
+        // Rust-specific: a key taken from the query follows the cloned
+        // query buffer rather than the original agent's
+        let mut agent = Agent::new();
+        agent.set_query_str("lookup-result");
+        agent.set_key_from_query();
+        agent.set_key_id(7);
+
+        let cloned = agent.clone();
+        drop(agent);
+
+        assert_eq!(cloned.key().as_bytes(), b"lookup-result");
+        assert_eq!(cloned.key().ptr(), cloned.query().ptr());
+        assert_eq!(cloned.key().id(), 7);
+    }
+
+    #[test]
+    fn test_agent_key_bytes_borrows() {
+        // Rust-specific: a lookup result borrows the query buffer instead
+        // of copying it; a reverse lookup result borrows the key buffer
+        use crate::keyset::Keyset;
+        use crate::trie::Trie;
+
+        let mut keyset = Keyset::new();
+        for key in ["app", "apple", "banana"] {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut agent = Agent::new();
+        agent.set_query_str("apple");
+        assert!(trie.lookup(&mut agent));
+        assert_eq!(agent.key_bytes(), b"apple");
+        assert_eq!(
+            agent.key_bytes().as_ptr(),
+            agent.query().as_bytes().as_ptr()
+        );
+
+        agent.set_query_str("apples");
+        assert!(trie.common_prefix_search(&mut agent));
+        assert_eq!(agent.key_bytes(), b"app");
+        assert_eq!(
+            agent.key_bytes().as_ptr(),
+            agent.query().as_bytes().as_ptr()
+        );
+
+        let id = trie.lookup_bytes(b"banana").unwrap();
+        agent.set_query_id(id);
+        trie.reverse_lookup(&mut agent);
+        assert_eq!(agent.key_bytes(), b"banana");
+        let key_buf = agent.state().unwrap().key_buf();
+        assert_eq!(agent.key_bytes().as_ptr(), key_buf.as_ptr());
+    }
+
+    #[test]
+    fn test_agent_reset_keeps_history_capacity() {
+        // Rust-specific: reset empties the agent but keeps the state's
+        // buffers, so a second predictive search reuses the history that
+        // the first one grew instead of reallocating it
+        use crate::{Keyset, Trie};
+
+        let mut keyset = Keyset::new();
+        for i in 0..500 {
+            let key: String = format!("{:b}", i * 37).chars().rev().collect();
+            keyset.push_back_str(&key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 1);
+
+        let mut agent = Agent::new();
+        let mut history_ptrs = Vec::new();
+        for _ in 0..2 {
+            agent.set_query_str("");
+            let mut count = 0;
+            while trie.predictive_search(&mut agent) {
+                count += 1;
+            }
+            assert_eq!(count, 500);
+            let history = agent.state_mut().unwrap().history_mut();
+            assert!(history.capacity() > 0);
+            history_ptrs.push((history.as_ptr(), history.capacity()));
+
+            agent.reset();
+            let state = agent.state().unwrap();
+            assert!(state.history().is_empty());
+            assert!(state.key_buf().is_empty());
+            assert_eq!(state.status_code(), StatusCode::ReadyToAll);
+            assert!(agent.query().is_empty());
+            assert!(agent.key().as_bytes().is_empty());
+        }
+        assert_eq!(history_ptrs[0], history_ptrs[1]);
+
+        agent.clear();
+        assert!(!agent.has_state());
+    }
+
     #[test]
     fn test_agent_query_mut() {
         let mut agent = Agent::new();
@@ -406,6 +633,43 @@ mod tests {
         assert_eq!(agent.query().as_str(), "mutable");
     }
 
+    #[test]
+    fn test_agent_new_query_clears_key() {
+        // Rust-specific: a lookup key points into the query buffer, which a
+        // longer query reallocates, so replacing the query must clear it
+        use crate::{Keyset, Trie};
+
+        let mut keyset = Keyset::new();
+        keyset.push_back_str("apple").unwrap();
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut agent = Agent::new();
+        agent.set_query_str("apple");
+        assert!(trie.lookup(&mut agent));
+        assert_eq!(agent.key_bytes(), b"apple");
+        agent.set_query_str(&"z".repeat(4096));
+        assert_eq!(agent.key_bytes(), b"");
+
+        agent.set_query_str("apple");
+        assert!(trie.lookup(&mut agent));
+        agent.set_query_bytes(&[b'y'; 4096]);
+        assert_eq!(agent.key_bytes(), b"");
+
+        agent.set_query_str("apple");
+        assert!(trie.lookup(&mut agent));
+        for _ in 0..4096 {
+            agent.query_mut().push(b'x');
+        }
+        assert_eq!(agent.key_bytes(), b"");
+
+        agent.set_query_id(0);
+        trie.reverse_lookup(&mut agent);
+        assert_eq!(agent.key_bytes(), b"apple");
+        agent.set_query_id(0);
+        assert_eq!(agent.key_bytes(), b"");
+    }
+
     #[test]
     fn test_agent_key_mut() {
         let mut agent = Agent::new();
@@ -413,4 +677,33 @@ mod tests {
 
         assert_eq!(agent.key().id(), 99);
     }
+
+    #[test]
+    fn test_agent_query_pos_after_failed_lookup() {
+        // Rust-specific: query_pos reports where a failed lookup stopped
+        use crate::keyset::Keyset;
+        use crate::trie::Trie;
+
+        let mut keyset = Keyset::new();
+        for key in ["app", "apple", "application", "banana"] {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut agent = Agent::new();
+        assert_eq!(agent.query_pos(), 0);
+
+        agent.set_query_str("applesauce");
+        assert!(!trie.lookup(&mut agent));
+        assert_eq!(agent.query_pos(), 5);
+
+        agent.set_query_str("apx");
+        assert!(!trie.lookup(&mut agent));
+        assert_eq!(agent.query_pos(), 2);
+
+        agent.set_query_str("apple");
+        assert!(trie.lookup(&mut agent));
+        assert_eq!(agent.query_pos(), 5);
+    }
 }
@@ -5,7 +5,7 @@
 //! This module defines fundamental types and constants used throughout the library,
 //! including error codes, configuration flags, and invalid ID constants.
 
-use std::fmt;
+use core::fmt;
 
 /// Word size in bits used by the trie's bit vectors.
 ///
@@ -22,6 +22,12 @@ pub const INVALID_LINK_ID: u32 = u32::MAX;
 /// Invalid key ID constant.
 pub const INVALID_KEY_ID: u32 = u32::MAX;
 
+/// Maximum number of bits a bit vector can hold.
+///
+/// Select indices store bit positions as `u32` (as in the on-disk format),
+/// so a bit vector is capped at `u32::MAX` bits.
+pub const MAX_BIT_VECTOR_SIZE: usize = u32::MAX as usize;
+
 /// Tail mode for suffix storage.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u32)]
@@ -39,6 +45,36 @@ pub enum TailMode {
     /// Uses a bit vector to detect end of sequences instead of NULL.
     /// Requires more space if average suffix length > 8 bytes.
     BinaryTail = 0x02000,
+
+    /// Chooses the mode from the keys at build time: text mode unless a
+    /// suffix contains a NULL byte, binary mode otherwise.
+    ///
+    /// `TextTail` already falls back to binary mode the same way, as in
+    /// C++; `Auto` makes that choice explicit. The mode is resolved during
+    /// build, so [`Trie::tail_mode`](crate::Trie::tail_mode) and saved
+    /// dictionaries only ever report `TextTail` or `BinaryTail`.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    Auto = 0x04000,
+}
+
+/// Returns the version of this crate, the `CARGO_PKG_VERSION` it was built
+/// with.
+///
+/// Rust-specific: Not present in C++ version.
+pub fn version() -> &'static str {
+    env!("CARGO_PKG_VERSION")
+}
+
+/// Returns the version of the on-disk trie format this crate reads and
+/// writes.
+///
+/// Use [`Trie::file_format_version`](crate::Trie::file_format_version) to
+/// check the version of a file.
+///
+/// Rust-specific: Not present in C++ version.
+pub fn format_version() -> u32 {
+    crate::grimoire::trie::header::FORMAT_VERSION
 }
 
 /// Invalid extra value constant (UINT32_MAX >> 8).
@@ -101,6 +137,7 @@ impl fmt::Display for ErrorCode {
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for ErrorCode {}
 
 /// Flags for memory mapping.
@@ -114,6 +151,22 @@ impl MapFlags {
     pub const POPULATE: MapFlags = MapFlags(1 << 0);
 }
 
+/// Expected access pattern of a memory-mapped trie, passed to `madvise`.
+///
+/// Rust-specific: Not present in C++ version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Advice {
+    /// No particular pattern; the OS default readahead.
+    #[default]
+    Normal,
+    /// Scattered accesses, such as lookups; readahead is wasted.
+    Random,
+    /// A front-to-back pass, such as iterating every key.
+    Sequential,
+    /// The whole map will be needed soon, so the OS may start paging it in.
+    WillNeed,
+}
+
 /// Number of tries in a dictionary.
 ///
 /// A dictionary consists of 3 tries by default. More tries generally make
@@ -171,6 +224,41 @@ pub enum NodeOrder {
     /// Generally better choice as it enables faster matching.
     #[default]
     Weight = 0x20000,
+
+    /// Arranges sibling nodes in keyset insertion order.
+    ///
+    /// Siblings are ordered by the earliest-inserted key below them, so key
+    /// IDs (and reverse lookup) follow the order keys were pushed to the
+    /// `Keyset` as closely as the trie shape allows. This only applies
+    /// fully to the first trie; later tries order their keys internally.
+    ///
+    /// Rust-specific: Not present in C++ version. C++ marisa rejects
+    /// dictionaries built with this order as having an undefined node order.
+    None = 0x40000,
+}
+
+/// Phase of a trie build, reported by
+/// [`Trie::build_with_progress`](crate::Trie::build_with_progress).
+///
+/// Each trie level reports `BuildingTrie` and then `Sorting`. The last
+/// level reports `BuildingTail` when it has suffixes left over. Each level
+/// reports `FillingCache` once the levels below it are done, so the
+/// deepest level reports first.
+///
+/// Rust-specific: Not present in C++ version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildPhase {
+    /// Sorting the keys of the current trie level.
+    Sorting,
+    /// Starting trie level `trie_id`, counting from 1.
+    BuildingTrie {
+        /// Level being built; 1 is the top-level trie.
+        trie_id: usize,
+    },
+    /// Building the tail that stores the remaining suffixes.
+    BuildingTail,
+    /// Filling the search cache of a finished trie level.
+    FillingCache,
 }
 
 /// Configuration masks for extracting specific config bits.
@@ -187,6 +275,59 @@ pub mod config_mask {
     pub const NODE_ORDER: u32 = 0xF0000;
     /// Mask for all configuration bits.
     pub const CONFIG: u32 = 0xFFFFF;
+    /// Mask for Rust-specific build flags (bits 20-25).
+    ///
+    /// See [`build_flags`](super::build_flags).
+    pub const BUILD_FLAGS: u32 = 0x3F00000;
+}
+
+/// Rust-specific build options.
+///
+/// These bits sit above [`config_mask::CONFIG`]. They are accepted by
+/// `Trie::build` alongside the regular configuration flags but only affect
+/// how the trie is built in memory; they are never written to the
+/// dictionary file.
+pub mod build_flags {
+    /// Defers select index construction until the first query that needs it.
+    ///
+    /// Lowers build time and memory for tries that are built and written
+    /// without being searched.
+    pub const LAZY_SELECT: i32 = 0x100000;
+
+    /// Builds the tail without sharing common suffixes between entries.
+    ///
+    /// Skips the sort and suffix-merge pass, which speeds up builds when
+    /// keys have little suffix overlap, at the cost of a larger tail. The
+    /// resulting file is still readable by C++ marisa.
+    pub const NO_TAIL_MERGE: i32 = 0x200000;
+
+    /// Sorts keys with a stable sort during build.
+    ///
+    /// Keys that compare equal keep their keyset order, so ties are
+    /// resolved the same way regardless of the sort's pivot choices. The
+    /// resulting trie is otherwise unchanged.
+    pub const STABLE_SORT: i32 = 0x400000;
+
+    /// Builds a small filter that rejects impossible lookups up front.
+    ///
+    /// The filter records the shortest and longest key and which bytes
+    /// start a key, and `lookup` consults it before walking the trie.
+    /// This speeds up miss-heavy workloads at the cost of about 48 bytes.
+    /// The filter is not part of the file format, so a trie that is read
+    /// or mapped back has none.
+    pub const LOOKUP_FILTER: i32 = 0x800000;
+
+    /// Makes the node order independent of how weights are listed.
+    ///
+    /// Sibling nodes are ordered by the sum of their keys' weights, and
+    /// floating-point sums round differently depending on the order of
+    /// the terms, which follows the keyset order for duplicate keys. With
+    /// this flag each sum is taken over the weights in sorted order, and
+    /// equal sums are ordered by label, so the same keys and weights give
+    /// the same key IDs whatever order they were pushed in. The sums also
+    /// pick which nodes the cache keeps, so other node orders get a
+    /// reproducible cache.
+    pub const DETERMINISTIC_WEIGHTS: i32 = 0x1000000;
 }
 
 #[cfg(test)]
@@ -0,0 +1,171 @@
+//! Agent bound to a trie.
+//!
+//! Rust-specific: Not present in C++ version.
+//!
+//! This module provides [`BoundAgent`], which pairs a [`Trie`] with an
+//! agent sized for it, so an interactive session can run query after query
+//! without passing both around.
+
+use crate::agent::Agent;
+use crate::trie::Trie;
+use alloc::vec::Vec;
+
+/// An [`Agent`] bound to the [`Trie`] it searches.
+///
+/// Created by [`Trie::bound_agent`]. The agent is set up once with
+/// [`Trie::reserve_agent`] and reused by every query, so its key buffer
+/// and search history keep their capacity between calls.
+///
+/// Rust-specific: Not present in C++ version.
+pub struct BoundAgent<'a> {
+    trie: &'a Trie,
+    agent: Agent,
+}
+
+impl<'a> BoundAgent<'a> {
+    /// Creates an agent bound to `trie`.
+    pub(crate) fn new(trie: &'a Trie) -> Self {
+        BoundAgent {
+            trie,
+            agent: trie.reserve_agent(),
+        }
+    }
+
+    /// Returns the trie this agent searches.
+    pub fn trie(&self) -> &'a Trie {
+        self.trie
+    }
+
+    /// Looks up a key.
+    ///
+    /// # Returns
+    ///
+    /// The key ID, or `None` if `key` is not in the trie
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("apple").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = trie.bound_agent();
+    /// assert_eq!(agent.lookup("apple"), Some(0));
+    /// assert_eq!(agent.lookup("app"), None);
+    /// ```
+    pub fn lookup(&mut self, key: &str) -> Option<usize> {
+        self.agent.set_query_str(key);
+        self.trie
+            .lookup(&mut self.agent)
+            .then(|| self.agent.key().id())
+    }
+
+    /// Restores the key with the given ID.
+    ///
+    /// # Returns
+    ///
+    /// The key bytes, borrowed until the next query, or `None` if
+    /// `key_id` is out of range
+    pub fn reverse_lookup(&mut self, key_id: usize) -> Option<&[u8]> {
+        if key_id >= self.trie.num_keys() {
+            return None;
+        }
+        self.agent.set_query_id(key_id);
+        self.trie.reverse_lookup(&mut self.agent);
+        Some(self.agent.key().as_bytes())
+    }
+
+    /// Finds the keys that are prefixes of `text`.
+    ///
+    /// # Returns
+    ///
+    /// `(key ID, key length)` pairs, shortest key first
+    pub fn common_prefix(&mut self, text: &str) -> Vec<(usize, usize)> {
+        self.agent.set_query_str(text);
+        let mut results = Vec::new();
+        while self.trie.common_prefix_search(&mut self.agent) {
+            results.push((self.agent.key().id(), self.agent.key().length()));
+        }
+        results
+    }
+
+    /// Finds the keys that start with `prefix`.
+    ///
+    /// # Returns
+    ///
+    /// `(key ID, key bytes)` pairs in predictive search order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// for key in ["app", "apple", "banana"] {
+    ///     keyset.push_back_str(key).unwrap();
+    /// }
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = trie.bound_agent();
+    /// assert_eq!(agent.predictive("app").len(), 2);
+    /// assert_eq!(agent.predictive("b")[0].1, b"banana");
+    /// ```
+    pub fn predictive(&mut self, prefix: &str) -> Vec<(usize, Vec<u8>)> {
+        self.agent.set_query_str(prefix);
+        let mut results = Vec::new();
+        while self.trie.predictive_search(&mut self.agent) {
+            results.push((self.agent.key().id(), self.agent.key().as_bytes().to_vec()));
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Keyset, Trie};
+
+    #[test]
+    fn test_bound_agent_query_sequence() {
+        // Rust-specific: one bound agent answers mixed queries in sequence
+        // with the same results as the trie's own one-off helpers
+        let mut keyset = Keyset::new();
+        for key in ["a", "app", "apple", "application", "banana", "band"] {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut agent = trie.bound_agent();
+        assert!(core::ptr::eq(agent.trie(), &trie));
+
+        let apple = agent.lookup("apple").unwrap();
+        assert_eq!(Some(apple), trie.lookup_bytes(b"apple"));
+        assert_eq!(agent.reverse_lookup(apple), Some(&b"apple"[..]));
+        assert_eq!(agent.lookup("appl"), None);
+
+        assert_eq!(agent.predictive("app"), trie.predictive_search_n("app", 10));
+        let ids: Vec<usize> = ["a", "app", "apple"]
+            .iter()
+            .map(|key| trie.lookup_bytes(key.as_bytes()).unwrap())
+            .collect();
+        assert_eq!(
+            agent.common_prefix("apples"),
+            [(ids[0], 1), (ids[1], 3), (ids[2], 5)]
+        );
+
+        // Searches of different kinds can follow each other in any order
+        assert_eq!(agent.predictive("ban").len(), 2);
+        assert_eq!(agent.lookup("band"), trie.lookup_bytes(b"band"));
+        assert_eq!(agent.predictive("c"), []);
+        assert_eq!(agent.common_prefix("xyz"), []);
+        assert_eq!(agent.reverse_lookup(trie.num_keys()), None);
+        for id in 0..trie.num_keys() {
+            let key = trie.restore(id).unwrap();
+            assert_eq!(agent.reverse_lookup(id), Some(&key[..]));
+        }
+    }
+}
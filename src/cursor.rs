@@ -0,0 +1,246 @@
+//! Streaming lookup cursor.
+//!
+//! Rust-specific: Not present in C++ version.
+//!
+//! This module provides [`TrieCursor`], which matches a key one byte at a
+//! time for input that arrives incrementally, such as a tokenizer reading
+//! from a stream.
+
+use crate::agent::Agent;
+use crate::grimoire::trie::louds_trie::LoudsTrie;
+
+/// Outcome of feeding one byte to a [`TrieCursor`].
+///
+/// Rust-specific: Not present in C++ version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedResult {
+    /// The bytes fed so far form the key with this ID.
+    Match(usize),
+    /// The bytes fed so far are a proper prefix of at least one key.
+    Continue,
+    /// No key starts with the bytes fed so far.
+    ///
+    /// The cursor stays dead until [`TrieCursor::reset`] is called.
+    Dead,
+}
+
+/// Byte-at-a-time lookup over a [`Trie`](crate::Trie).
+///
+/// Created by [`Trie::cursor`](crate::Trie::cursor). Each call to
+/// [`feed`](Self::feed) extends the current input by one byte and reports
+/// whether it is a key, a prefix of a key, or neither. Whole trie edges
+/// are followed as soon as their bytes have arrived; an edge spanning
+/// several bytes (a tail or next-trie link) is matched again from its
+/// start each time a byte of it arrives.
+///
+/// Rust-specific: Not present in C++ version.
+pub struct TrieCursor<'a> {
+    trie: &'a LoudsTrie,
+    /// Holds the bytes fed so far as its query.
+    agent: Agent,
+    /// Last node fully reached.
+    node_id: usize,
+    /// Number of fed bytes consumed reaching `node_id`.
+    query_pos: usize,
+    dead: bool,
+}
+
+impl<'a> TrieCursor<'a> {
+    /// Creates a cursor positioned at the root of `trie`.
+    pub(crate) fn new(trie: &'a LoudsTrie) -> Self {
+        let mut agent = Agent::new();
+        agent
+            .init_state()
+            .expect("Failed to initialize agent state");
+        TrieCursor {
+            trie,
+            agent,
+            node_id: 0,
+            query_pos: 0,
+            dead: false,
+        }
+    }
+
+    /// Feeds the next input byte.
+    ///
+    /// # Arguments
+    ///
+    /// * `byte` - The byte following those already fed
+    ///
+    /// # Returns
+    ///
+    /// [`FeedResult::Match`] if the input so far is a key,
+    /// [`FeedResult::Continue`] if it is only a prefix of some key, and
+    /// [`FeedResult::Dead`] once no key can match.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{FeedResult, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("app").unwrap();
+    /// keyset.push_back_str("apple").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut cursor = trie.cursor();
+    /// assert_eq!(cursor.feed(b'a'), FeedResult::Continue);
+    /// assert_eq!(cursor.feed(b'p'), FeedResult::Continue);
+    /// assert!(matches!(cursor.feed(b'p'), FeedResult::Match(_)));
+    /// assert_eq!(cursor.feed(b's'), FeedResult::Dead);
+    /// ```
+    pub fn feed(&mut self, byte: u8) -> FeedResult {
+        if self.dead {
+            return FeedResult::Dead;
+        }
+        self.agent.query_mut().push(byte);
+
+        match self
+            .trie
+            .advance_cursor(&mut self.agent, self.node_id, self.query_pos)
+        {
+            Some((node_id, query_pos)) => {
+                self.node_id = node_id;
+                self.query_pos = query_pos;
+                if query_pos < self.agent.query().length() {
+                    return FeedResult::Continue;
+                }
+                match self.trie.terminal_key_id(node_id) {
+                    Some(key_id) => FeedResult::Match(key_id),
+                    None => FeedResult::Continue,
+                }
+            }
+            None => {
+                self.dead = true;
+                FeedResult::Dead
+            }
+        }
+    }
+
+    /// Returns the bytes fed since the cursor was created or reset.
+    ///
+    /// After [`FeedResult::Dead`], this includes the byte that killed the
+    /// cursor but none fed afterwards.
+    pub fn fed(&self) -> &[u8] {
+        self.agent.query().as_bytes()
+    }
+
+    /// Moves the cursor back to the root, forgetting all fed bytes.
+    pub fn reset(&mut self) {
+        self.agent.set_query_bytes(&[]);
+        self.node_id = 0;
+        self.query_pos = 0;
+        self.dead = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Keyset, Trie};
+
+    fn build(keys: &[&str], flags: i32) -> Trie {
+        let mut keyset = Keyset::new();
+        for key in keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, flags);
+        trie
+    }
+
+    fn lookup_id(trie: &Trie, key: &str) -> usize {
+        let mut agent = Agent::new();
+        agent.set_query_str(key);
+        assert!(trie.lookup(&mut agent));
+        agent.key().id()
+    }
+
+    #[test]
+    fn test_cursor_feed_apple() {
+        // Rust-specific: matches are reported at the byte ending each key
+        let trie = build(&["app", "apple"], 0);
+        let app = lookup_id(&trie, "app");
+        let apple = lookup_id(&trie, "apple");
+
+        let mut cursor = trie.cursor();
+        let results: Vec<FeedResult> = b"apple".iter().map(|&b| cursor.feed(b)).collect();
+        assert_eq!(
+            results,
+            [
+                FeedResult::Continue,
+                FeedResult::Continue,
+                FeedResult::Match(app),
+                FeedResult::Continue,
+                FeedResult::Match(apple),
+            ]
+        );
+        assert_eq!(cursor.fed(), b"apple");
+        assert_eq!(cursor.feed(b's'), FeedResult::Dead);
+        assert_eq!(cursor.feed(b'x'), FeedResult::Dead);
+        assert_eq!(cursor.fed(), b"apples");
+
+        cursor.reset();
+        assert_eq!(cursor.fed(), b"");
+        assert_eq!(cursor.feed(b'a'), FeedResult::Continue);
+    }
+
+    #[test]
+    fn test_cursor_dead_inside_edge() {
+        // Rust-specific: a mismatch part-way through a tail kills the cursor
+        let trie = build(&["application", "banana"], 0);
+        let mut cursor = trie.cursor();
+        for &b in b"applic" {
+            assert_eq!(cursor.feed(b), FeedResult::Continue);
+        }
+        assert_eq!(cursor.feed(b'x'), FeedResult::Dead);
+
+        let mut cursor = trie.cursor();
+        assert_eq!(cursor.feed(b'c'), FeedResult::Dead);
+    }
+
+    #[test]
+    fn test_cursor_matches_lookup() {
+        // Rust-specific: feeding each key agrees with lookup for every
+        // prefix, across tail modes and multi-level tries
+        let keys = [
+            "a",
+            "app",
+            "apple",
+            "application",
+            "apply",
+            "banana",
+            "band",
+            "bandana",
+            "can",
+            "cat",
+            "catalog",
+            "catalogue",
+        ];
+        for flags in [0, 1, 3, 0x02000 | 2, 0x10000 | 4] {
+            let trie = build(&keys, flags);
+            for key in keys.iter().chain(&["ap", "bandanas", "catx", "zzz"]) {
+                let mut cursor = trie.cursor();
+                let mut dead = false;
+                for end in 1..=key.len() {
+                    let prefix = &key[..end];
+                    let result = cursor.feed(key.as_bytes()[end - 1]);
+                    let mut agent = Agent::new();
+                    agent.set_query_str(prefix);
+                    let expected = if dead {
+                        FeedResult::Dead
+                    } else if trie.lookup(&mut agent) {
+                        FeedResult::Match(agent.key().id())
+                    } else if trie.num_keys_with_prefix(prefix) > 0 {
+                        FeedResult::Continue
+                    } else {
+                        FeedResult::Dead
+                    };
+                    assert_eq!(result, expected, "flags {:#x} prefix {:?}", flags, prefix);
+                    dead = result == FeedResult::Dead;
+                }
+            }
+        }
+    }
+}
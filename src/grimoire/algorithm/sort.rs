@@ -108,18 +108,75 @@ fn insertion_sort<T: Sortable>(data: &mut [T], depth: usize) -> usize {
     count
 }
 
+/// Returns the introsort partitioning budget for `len` elements,
+/// `2 * floor(log2(len))`.
+#[inline]
+fn depth_limit(len: usize) -> usize {
+    2 * (usize::BITS - 1 - len.max(1).leading_zeros()) as usize
+}
+
+/// Restores the max-heap property below `root` in `data[..end]`.
+fn sift_down<T: Sortable>(data: &mut [T], mut root: usize, end: usize, depth: usize) {
+    loop {
+        let mut child = 2 * root + 1;
+        if child >= end {
+            break;
+        }
+        if child + 1 < end && compare(&data[child], &data[child + 1], depth) < 0 {
+            child += 1;
+        }
+        if compare(&data[root], &data[child], depth) >= 0 {
+            break;
+        }
+        data.swap(root, child);
+        root = child;
+    }
+}
+
+/// Heapsort fallback for ranges whose partitioning budget ran out.
+///
+/// Elements are compared from `depth` onward, so all of them must share
+/// their first `depth` bytes. Guarantees O(n log n) comparisons.
+///
+/// Returns the count of unique strings in the range.
+fn heap_sort<T: Sortable>(data: &mut [T], depth: usize) -> usize {
+    let len = data.len();
+    for root in (0..len / 2).rev() {
+        sift_down(data, root, len, depth);
+    }
+    for end in (1..len).rev() {
+        data.swap(0, end);
+        sift_down(data, 0, end, depth);
+    }
+
+    if len == 0 {
+        return 0;
+    }
+    1 + (1..len)
+        .filter(|&i| compare(&data[i - 1], &data[i], depth) != 0)
+        .count()
+}
+
 /// Depth-based quicksort implementation.
 ///
 /// This is a three-way quicksort optimized for string sorting, using
 /// the depth parameter to compare strings character by character.
 ///
+/// `limit` is the number of partitioning steps left at the current depth
+/// before the range falls back to heapsort, as in introsort.
+///
 /// Returns the count of unique string prefixes.
-fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
+fn sort_impl<T: Sortable>(data: &mut [T], mut depth: usize, mut limit: usize) -> usize {
     let mut count = 0;
     let mut l = 0;
     let mut r = data.len();
 
     while (r - l) > INSERTION_SORT_THRESHOLD {
+        if limit == 0 {
+            return count + heap_sort(&mut data[l..r], depth);
+        }
+        limit -= 1;
+
         let mut pl = l;
         let mut pr = r;
         let mut pivot_l = l;
@@ -182,7 +239,7 @@ fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
                 if pivot == -1 {
                     count += 1;
                 } else {
-                    count += sort_impl(&mut data[pl..pr], depth + 1);
+                    count += sort_impl(&mut data[pl..pr], depth + 1, depth_limit(pr - pl));
                 }
             }
 
@@ -191,14 +248,14 @@ fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
                 if pl - l == 1 {
                     count += 1;
                 } else if pl - l > 1 {
-                    count += sort_impl(&mut data[l..pl], depth);
+                    count += sort_impl(&mut data[l..pl], depth, limit);
                 }
                 l = pr;
             } else {
                 if r - pr == 1 {
                     count += 1;
                 } else if r - pr > 1 {
-                    count += sort_impl(&mut data[pr..r], depth);
+                    count += sort_impl(&mut data[pr..r], depth, limit);
                 }
                 r = pl;
             }
@@ -207,14 +264,14 @@ fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
             if pl - l == 1 {
                 count += 1;
             } else if pl - l > 1 {
-                count += sort_impl(&mut data[l..pl], depth);
+                count += sort_impl(&mut data[l..pl], depth, limit);
             }
 
             // Recurse on right partition
             if r - pr == 1 {
                 count += 1;
             } else if r - pr > 1 {
-                count += sort_impl(&mut data[pr..r], depth);
+                count += sort_impl(&mut data[pr..r], depth, limit);
             }
 
             // Continue with middle partition
@@ -227,10 +284,11 @@ fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
                     l = r;
                     count += 1;
                 } else {
-                    // Continue loop with increased depth
-                    let mid_count = sort_impl(&mut data[l..r], depth + 1);
-                    count += mid_count;
-                    break;
+                    // Continue with the next byte, in a loop rather than by
+                    // recursion so that long shared prefixes cannot
+                    // exhaust the stack
+                    depth += 1;
+                    limit = depth_limit(r - l);
                 }
             }
         }
@@ -258,7 +316,60 @@ fn sort_impl<T: Sortable>(data: &mut [T], depth: usize) -> usize {
 ///
 /// The count of unique string prefixes
 pub fn sort<T: Sortable>(data: &mut [T]) -> usize {
-    sort_impl(data, 0)
+    sort_impl(data, 0, depth_limit(data.len()))
+}
+
+/// Sorts elements in byte order, keeping equal elements in input order.
+///
+/// [`sort`] is a three-way quicksort and may reorder equal elements. This
+/// variant uses a stable merge sort instead, so ties keep their original
+/// relative order. It is somewhat slower and needs a temporary buffer of
+/// `data.len() / 2` elements.
+///
+/// # Arguments
+///
+/// * `data` - Mutable slice of elements to sort
+///
+/// # Returns
+///
+/// The count of unique elements, as returned by [`sort`]
+///
+/// Rust-specific: Not present in C++ version.
+pub fn sort_stable<T: Sortable>(data: &mut [T]) -> usize {
+    data.sort_by(|lhs, rhs| compare(lhs, rhs, 0).cmp(&0));
+    count_sorted(data)
+}
+
+/// Counts unique elements in an already sorted slice.
+///
+/// This is the linear counterpart of [`sort`] for input the caller knows
+/// to be in byte order: it returns the same count without moving anything.
+/// Sortedness is checked with a debug assertion only, so release builds
+/// trust the caller.
+///
+/// # Arguments
+///
+/// * `data` - Slice of elements in ascending byte order
+///
+/// # Returns
+///
+/// The count of unique elements
+///
+/// Rust-specific: Not present in C++ version.
+pub fn count_sorted<T: Sortable>(data: &[T]) -> usize {
+    if data.is_empty() {
+        return 0;
+    }
+
+    let mut count = 1;
+    for i in 1..data.len() {
+        let result = compare(&data[i - 1], &data[i], 0);
+        debug_assert!(result <= 0, "input is not sorted at index {}", i);
+        if result != 0 {
+            count += 1;
+        }
+    }
+    count
 }
 
 #[cfg(test)]
@@ -503,4 +614,128 @@ mod tests {
         // Should return count of unique prefixes
         assert!(count > 0);
     }
+
+    #[test]
+    fn test_count_sorted_matches_sort() {
+        // Rust-specific: counting a presorted slice agrees with sorting it
+        let words = ["a", "ab", "ab", "b", "", "abc", "b", "ba", "a", "zz"];
+        let mut data: Vec<TestString> = words.iter().map(|w| TestString::new(w)).collect();
+        let sorted_count = sort(&mut data);
+
+        assert_eq!(count_sorted(&data), sorted_count);
+        assert_eq!(count_sorted(&data), 7);
+        assert_eq!(count_sorted::<TestString>(&[]), 0);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "not sorted")]
+    fn test_count_sorted_rejects_unsorted_in_debug() {
+        // Rust-specific: sortedness is verified in debug builds
+        let data = vec![TestString::new("b"), TestString::new("a")];
+        count_sorted(&data);
+    }
+
+    #[test]
+    fn test_sort_stable_keeps_tie_order() {
+        // Rust-specific: equal keys keep their input order
+        struct Tagged {
+            data: &'static [u8],
+            tag: usize,
+        }
+
+        impl Sortable for Tagged {
+            fn get(&self, index: usize) -> Option<u8> {
+                self.data.get(index).copied()
+            }
+
+            fn length(&self) -> usize {
+                self.data.len()
+            }
+        }
+
+        let words: [&'static [u8]; 40] = [
+            b"ab", b"a", b"ab", b"b", b"abc", b"a", b"ab", b"b", b"", b"abc", b"a", b"ab", b"b",
+            b"", b"abc", b"a", b"ab", b"b", b"abc", b"a", b"ab", b"b", b"", b"abc", b"a", b"ab",
+            b"b", b"abc", b"a", b"ab", b"b", b"", b"abc", b"a", b"ab", b"b", b"abc", b"a", b"ab",
+            b"b",
+        ];
+        let mut data: Vec<Tagged> = words
+            .iter()
+            .enumerate()
+            .map(|(tag, data)| Tagged { data, tag })
+            .collect();
+
+        assert_eq!(sort_stable(&mut data), 5);
+        for pair in data.windows(2) {
+            assert!(pair[0].data <= pair[1].data);
+            if pair[0].data == pair[1].data {
+                assert!(pair[0].tag < pair[1].tag);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sort_long_shared_prefix_does_not_overflow_stack() {
+        // Rust-specific: each shared byte used to add a recursion level,
+        // overflowing the stack on keys with a very long common prefix
+        let mut data: Vec<TestString> = (0..32u8)
+            .rev()
+            .map(|i| {
+                let mut bytes = vec![b'x'; 100_000];
+                bytes.push(i);
+                TestString { data: bytes }
+            })
+            .collect();
+
+        assert_eq!(sort(&mut data), 32);
+        for (i, item) in data.iter().enumerate() {
+            assert_eq!(item.data[100_000], i as u8);
+        }
+    }
+
+    #[test]
+    fn test_sort_heapsort_fallback() {
+        // Rust-specific: an exhausted partitioning budget falls back to
+        // heapsort with the same order and unique count
+        let words: Vec<String> = (0..500)
+            .map(|i| format!("{}-{}", (i * 7919) % 211, i % 17))
+            .collect();
+        let mut expected: Vec<TestString> = words.iter().map(|w| TestString::new(w)).collect();
+        let expected_count = sort(&mut expected);
+
+        let mut data: Vec<TestString> = words.iter().map(|w| TestString::new(w)).collect();
+        assert_eq!(sort_impl(&mut data, 0, 0), expected_count);
+        assert_eq!(data, expected);
+
+        let mut data: Vec<TestString> = words.iter().map(|w| TestString::new(w)).collect();
+        assert_eq!(sort_impl(&mut data, 0, 1), expected_count);
+        assert_eq!(data, expected);
+
+        assert_eq!(depth_limit(1), 0);
+        assert_eq!(depth_limit(16), 8);
+        assert_eq!(depth_limit(1000), 18);
+    }
+
+    #[test]
+    fn test_sort_adversarial_labels() {
+        // Rust-specific: keys whose first bytes make median-of-three pick a
+        // poor pivot at every step still sort correctly
+        let mut data: Vec<TestString> = Vec::new();
+        for round in 0..64u8 {
+            for label in (0..=255u8).step_by(3) {
+                data.push(TestString {
+                    data: vec![label, round, label ^ round],
+                });
+            }
+        }
+        let mut expected: Vec<Vec<u8>> = data.iter().map(|t| t.data.clone()).collect();
+        expected.sort();
+        expected.dedup();
+
+        assert_eq!(sort(&mut data), expected.len());
+        for pair in data.windows(2) {
+            assert!(pair[0].data <= pair[1].data);
+        }
+    }
 }
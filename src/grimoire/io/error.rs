@@ -0,0 +1,94 @@
+//! I/O error type for `no_std` builds.
+//!
+//! Rust-specific: Not present in C++ version.
+//!
+//! With the `std` feature, [`Error`], [`ErrorKind`] and [`Result`] are
+//! re-exported from `std::io` and this module is not compiled. Without it,
+//! these minimal stand-ins keep the same names and the subset of
+//! `std::io::Error` that the crate uses, so every fallible API has the same
+//! shape in both builds.
+
+use alloc::string::String;
+use core::fmt;
+
+/// A specialized `Result` type for operations that can fail with [`Error`].
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// The category of an [`Error`], mirroring `std::io::ErrorKind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An entity already exists.
+    AlreadyExists,
+    /// A parameter was incorrect.
+    InvalidInput,
+    /// Data not valid for the operation were encountered.
+    InvalidData,
+    /// The operation needs an open source that was not provided.
+    NotConnected,
+    /// The data ended before everything expected was read.
+    UnexpectedEof,
+    /// The operation is not supported in this build or for this data.
+    Unsupported,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            ErrorKind::AlreadyExists => "entity already exists",
+            ErrorKind::InvalidInput => "invalid input parameter",
+            ErrorKind::InvalidData => "invalid data",
+            ErrorKind::NotConnected => "not connected",
+            ErrorKind::UnexpectedEof => "unexpected end of file",
+            ErrorKind::Unsupported => "unsupported",
+            ErrorKind::Other => "other error",
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error with a kind and a message, mirroring `std::io::Error`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl Error {
+    /// Creates an error of the given kind with a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The error category
+    /// * `message` - Description of what went wrong
+    pub fn new<M: Into<String>>(kind: ErrorKind, message: M) -> Self {
+        Error {
+            kind,
+            message: message.into(),
+        }
+    }
+
+    /// Returns the category of this error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error::new(kind, kind.as_str())
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
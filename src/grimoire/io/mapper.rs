@@ -5,7 +5,8 @@
 //! - lib/marisa/grimoire/io/mapper.cc
 //!
 //! Mapper provides read-only access to data through memory mapping.
-//! This implementation supports both file-backed memory mapping and borrowed memory.
+//! This implementation supports file-backed memory mapping, shared `Arc`
+//! buffers and borrowed memory.
 //!
 //! When the `mmap` feature is disabled (e.g., for WASM), only borrowed memory
 //! mode is available. File-backed memory mapping requires the `mmap` feature.
@@ -24,41 +25,59 @@
 //!
 //! See also: [WAMR host data sharing](https://bytecodealliance.github.io/wamr.dev/blog/the-wasm-host-sharing-data-basics/)
 
+use crate::base::Advice;
+use crate::grimoire::io;
+use alloc::sync::Arc;
 #[cfg(feature = "mmap")]
 use memmap2::Mmap;
-use std::io;
 
 /// Mapper for memory-mapped data access.
 ///
 /// Mapper provides read-only access to data, primarily used for
 /// deserializing trie structures from memory or files.
 ///
-/// The mapper can work in two modes:
+/// The mapper can work in three modes:
 /// - File-backed memory mapping using `memmap2::Mmap` (requires `mmap` feature)
+/// - A shared `Arc<[u8]>` buffer that the mapper keeps alive
 /// - Borrowed memory slices (for testing or in-memory data)
-pub struct Mapper {
+///
+/// The lifetime `'a` is that of a borrowed slice. Structures mapped from
+/// `'static` slices point into them without copying. Files and `Arc`
+/// buffers are released when the mapper is dropped, so structures mapped
+/// from them copy their elements, except inside tries that keep the mapper
+/// alive for as long as they point into it.
+pub struct Mapper<'a> {
     /// File-backed memory map (only available with `mmap` feature).
     #[cfg(feature = "mmap")]
     mmap: Option<Mmap>,
-    /// Borrowed memory reference (static lifetime for safety).
-    borrowed: Option<&'static [u8]>,
+    /// Shared buffer owned by the mapper.
+    arc: Option<Arc<[u8]>>,
+    /// Borrowed memory reference.
+    borrowed: Option<&'a [u8]>,
+    /// Whether mapped structures may point into the data without copying.
+    ///
+    /// Set for `'static` slices, and by [`share`](Self::share) for sources
+    /// the mapper owns.
+    shared: bool,
     /// Current read position.
     position: usize,
 }
 
-impl Default for Mapper {
+impl Default for Mapper<'_> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Mapper {
+impl<'a> Mapper<'a> {
     /// Creates a new empty mapper.
     pub fn new() -> Self {
         Mapper {
             #[cfg(feature = "mmap")]
             mmap: None,
+            arc: None,
             borrowed: None,
+            shared: false,
             position: 0,
         }
     }
@@ -89,7 +108,9 @@ impl Mapper {
         let mmap = unsafe { Mmap::map(&file)? };
         Ok(Mapper {
             mmap: Some(mmap),
+            arc: None,
             borrowed: None,
+            shared: false,
             position: 0,
         })
     }
@@ -121,7 +142,52 @@ impl Mapper {
         Mapper {
             #[cfg(feature = "mmap")]
             mmap: None,
+            arc: None,
             borrowed: Some(data),
+            shared: true,
+            position: 0,
+        }
+    }
+
+    /// Opens a mapper over a shared buffer, keeping it alive.
+    ///
+    /// Rust-specific. The mapper holds a reference to the buffer, so it
+    /// stays alive for as long as the mapper (or whatever it is moved into)
+    /// does, even after every other `Arc` handle has been dropped. A trie
+    /// mapped through [`Trie::map_arc`](crate::Trie::map_arc) keeps the
+    /// mapper and points into the buffer without copying.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Shared buffer to map
+    pub fn open_arc(data: Arc<[u8]>) -> Mapper<'static> {
+        Mapper {
+            #[cfg(feature = "mmap")]
+            mmap: None,
+            arc: Some(data),
+            borrowed: None,
+            shared: false,
+            position: 0,
+        }
+    }
+
+    /// Opens a mapper over a borrowed byte slice of any lifetime.
+    ///
+    /// Rust-specific. Because structures mapped from this mapper may
+    /// outlive `data`, mapped vectors always copy their elements. Use
+    /// [`open_memory`](Self::open_memory) for `'static` data to avoid the
+    /// copy.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - Byte slice to map
+    pub fn open_borrowed(data: &'a [u8]) -> Self {
+        Mapper {
+            #[cfg(feature = "mmap")]
+            mmap: None,
+            arc: None,
+            borrowed: Some(data),
+            shared: false,
             position: 0,
         }
     }
@@ -148,6 +214,9 @@ impl Mapper {
         if let Some(ref m) = self.mmap {
             return &m[..];
         }
+        if let Some(ref arc) = self.arc {
+            return arc;
+        }
         self.borrowed.unwrap_or(&[])
     }
 
@@ -163,9 +232,60 @@ impl Mapper {
     ///
     /// # Safety
     ///
-    /// This function reads raw bytes into the memory representation of T.
-    /// The caller must ensure T is safe to initialize from arbitrary bytes.
+    /// This function reads raw bytes into the memory representation of T,
+    /// in native byte order. It is only sound for plain integer types (and
+    /// `#[repr(C)]` structs of them) for which every bit pattern is valid;
+    /// prefer [`map_u32`](Self::map_u32) and [`map_u64`](Self::map_u64)
+    /// for scalar fields.
     pub fn map<T: Copy>(&mut self, value: &mut T) -> io::Result<()> {
+        let size = core::mem::size_of::<T>();
+        let slice = self.take(size)?;
+        unsafe {
+            core::ptr::copy_nonoverlapping(slice.as_ptr(), value as *mut T as *mut u8, size);
+        }
+        Ok(())
+    }
+
+    /// Maps a little-endian `u32` from the current position.
+    ///
+    /// Rust-specific: bounds-checked and independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper is not open or if there's insufficient data.
+    pub fn map_u32(&mut self) -> io::Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Maps a little-endian `u64` from the current position.
+    ///
+    /// Rust-specific: bounds-checked and independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper is not open or if there's insufficient data.
+    pub fn map_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(u64::from_le_bytes(bytes))
+    }
+
+    /// Returns the next `len` bytes and advances past them.
+    ///
+    /// Rust-specific: the returned slice borrows the mapped data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper is not open or if there's insufficient data.
+    pub fn map_u8_slice(&mut self, len: usize) -> io::Result<&[u8]> {
+        self.take(len)
+    }
+
+    /// Bounds-checks `size` bytes at the current position, advances past
+    /// them and returns them.
+    fn take(&mut self, size: usize) -> io::Result<&[u8]> {
+        let position = self.position;
         let data = self.data();
         if data.is_empty() {
             return Err(io::Error::new(
@@ -174,21 +294,15 @@ impl Mapper {
             ));
         }
 
-        let size = std::mem::size_of::<T>();
-        if self.position + size > data.len() {
-            return Err(io::Error::new(
-                io::ErrorKind::UnexpectedEof,
-                "Insufficient data to map",
-            ));
-        }
-
-        let slice = &data[self.position..self.position + size];
-        unsafe {
-            std::ptr::copy_nonoverlapping(slice.as_ptr(), value as *mut T as *mut u8, size);
-        }
+        let end = position
+            .checked_add(size)
+            .filter(|&end| end <= data.len())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "Insufficient data to map")
+            })?;
 
-        self.position += size;
-        Ok(())
+        self.position = end;
+        Ok(&self.data()[position..end])
     }
 
     /// Maps and returns a single value of type T from the current position.
@@ -236,7 +350,7 @@ impl Mapper {
             ));
         }
 
-        let size = std::mem::size_of_val(values);
+        let size = core::mem::size_of_val(values);
         if self.position + size > data.len() {
             return Err(io::Error::new(
                 io::ErrorKind::UnexpectedEof,
@@ -246,13 +360,67 @@ impl Mapper {
 
         let slice = &data[self.position..self.position + size];
         unsafe {
-            std::ptr::copy_nonoverlapping(slice.as_ptr(), values.as_mut_ptr() as *mut u8, size);
+            core::ptr::copy_nonoverlapping(slice.as_ptr(), values.as_mut_ptr() as *mut u8, size);
         }
 
         self.position += size;
         Ok(())
     }
 
+    /// Borrows `len` values of type `T` at the current position without copying.
+    ///
+    /// On success the position advances past the values and a pointer into
+    /// the mapped data is returned. Returns `Ok(None)` without advancing if
+    /// the data is not aligned for `T`, or if the mapper was opened with
+    /// [`open_borrowed`](Self::open_borrowed); callers then fall back to
+    /// [`map_slice`](Self::map_slice).
+    ///
+    /// Only `'static` slices and mappers marked with [`share`](Self::share)
+    /// hand out pointers. The pointer stays valid as long as the mapped
+    /// source does: for the lifetime of the program with `open_memory`,
+    /// and until this mapper (or whatever it is moved into) is dropped
+    /// otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the mapper is not open or if there's insufficient data.
+    pub(crate) fn map_ptr<T: Copy>(&mut self, len: usize) -> io::Result<Option<*const T>> {
+        let data = self.data();
+        if data.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Mapper not open",
+            ));
+        }
+
+        let size = core::mem::size_of::<T>()
+            .checked_mul(len)
+            .filter(|&size| self.position + size <= data.len())
+            .ok_or_else(|| {
+                io::Error::new(io::ErrorKind::UnexpectedEof, "Insufficient data to map")
+            })?;
+
+        let ptr = data[self.position..].as_ptr();
+        if !self.shared || (ptr as usize) % core::mem::align_of::<T>() != 0 {
+            return Ok(None);
+        }
+
+        self.position += size;
+        Ok(Some(ptr as *const T))
+    }
+
+    /// Lets structures mapped from here point into the data without
+    /// copying.
+    ///
+    /// # Safety
+    ///
+    /// The caller must keep this mapper alive until everything mapped from
+    /// it after this call has been dropped, as `LoudsTrie` does by storing
+    /// the mapper next to its mapped components.
+    pub(crate) unsafe fn share(&mut self) {
+        self.shared = true;
+    }
+
     /// Seeks forward by the specified number of bytes.
     ///
     /// # Arguments
@@ -282,13 +450,42 @@ impl Mapper {
         Ok(())
     }
 
+    /// Tells the OS how the mapped file will be accessed.
+    ///
+    /// Forwards `hint` to `madvise` for a file-backed map on Unix. For
+    /// memory-backed mappers, and on platforms without `madvise`, this is
+    /// a no-op that returns `Ok(())`.
+    ///
+    /// # Arguments
+    ///
+    /// * `hint` - Expected access pattern
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the hint.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn advise(&self, hint: Advice) -> io::Result<()> {
+        #[cfg(all(feature = "mmap", unix))]
+        if let Some(ref mmap) = self.mmap {
+            return mmap.advise(match hint {
+                Advice::Normal => memmap2::Advice::Normal,
+                Advice::Random => memmap2::Advice::Random,
+                Advice::Sequential => memmap2::Advice::Sequential,
+                Advice::WillNeed => memmap2::Advice::WillNeed,
+            });
+        }
+        let _ = hint;
+        Ok(())
+    }
+
     /// Checks if the mapper is open.
     pub fn is_open(&self) -> bool {
         #[cfg(feature = "mmap")]
         if self.mmap.is_some() {
             return true;
         }
-        self.borrowed.is_some()
+        self.arc.is_some() || self.borrowed.is_some()
     }
 
     /// Returns the current position.
@@ -307,23 +504,29 @@ impl Mapper {
         {
             self.mmap = None;
         }
+        self.arc = None;
         self.borrowed = None;
+        self.shared = false;
         self.position = 0;
     }
 
     /// Swaps with another mapper.
-    pub fn swap(&mut self, other: &mut Mapper) {
+    pub fn swap(&mut self, other: &mut Mapper<'a>) {
         #[cfg(feature = "mmap")]
-        std::mem::swap(&mut self.mmap, &mut other.mmap);
-        std::mem::swap(&mut self.borrowed, &mut other.borrowed);
-        std::mem::swap(&mut self.position, &mut other.position);
+        core::mem::swap(&mut self.mmap, &mut other.mmap);
+        core::mem::swap(&mut self.arc, &mut other.arc);
+        core::mem::swap(&mut self.borrowed, &mut other.borrowed);
+        core::mem::swap(&mut self.shared, &mut other.shared);
+        core::mem::swap(&mut self.position, &mut other.position);
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "mmap")]
     use std::io::Write;
+    #[cfg(feature = "mmap")]
     use tempfile::NamedTempFile;
 
     #[test]
@@ -549,4 +752,128 @@ mod tests {
         mapper.map_slice(&mut values).unwrap();
         assert_eq!(values, [5, 6, 7, 8]);
     }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapper_map_ptr_file() {
+        // Rust-specific: map_ptr points into the mapping without copying
+        let mut temp_file = NamedTempFile::new().unwrap();
+        let words = [0x1111_2222u32, 0x3333_4444];
+        for word in &words {
+            temp_file.write_all(&word.to_le_bytes()).unwrap();
+        }
+        temp_file.flush().unwrap();
+
+        let path = temp_file.path().to_str().unwrap();
+        let mut mapper = Mapper::open_file(path).unwrap();
+        assert!(mapper.map_ptr::<u32>(2).unwrap().is_none());
+        // SAFETY: nothing mapped from here outlives the mapper
+        unsafe { mapper.share() };
+
+        let ptr = mapper.map_ptr::<u32>(2).unwrap().unwrap();
+        assert_eq!(ptr as *const u8, mapper.data().as_ptr());
+        let mapped = unsafe { std::slice::from_raw_parts(ptr, 2) };
+        assert_eq!(u32::from_le(mapped[0]), words[0]);
+        assert_eq!(u32::from_le(mapped[1]), words[1]);
+        assert_eq!(mapper.position(), 8);
+
+        let err = mapper.map_ptr::<u32>(1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_mapper_map_ptr_unaligned() {
+        // Rust-specific: misaligned data is reported, not borrowed
+        static DATA: [u64; 2] = [0, 0];
+        let bytes: &'static [u8] =
+            unsafe { std::slice::from_raw_parts(DATA.as_ptr() as *const u8, 16) };
+        let mut mapper = Mapper::open_memory(bytes);
+
+        mapper.seek(1).unwrap();
+        assert!(mapper.map_ptr::<u32>(1).unwrap().is_none());
+        assert_eq!(mapper.position(), 1);
+
+        mapper.seek(3).unwrap();
+        assert!(mapper.map_ptr::<u32>(1).unwrap().is_some());
+        assert_eq!(mapper.position(), 8);
+    }
+
+    #[test]
+    fn test_mapper_open_borrowed() {
+        // Rust-specific: non-static data is mapped by copying, never borrowed
+        let data: Vec<u8> = vec![7, 0, 0, 0, 9, 0, 0, 0];
+        let mut mapper = Mapper::open_borrowed(&data);
+        assert!(mapper.is_open());
+        assert_eq!(mapper.size(), 8);
+
+        assert!(mapper.map_ptr::<u8>(4).unwrap().is_none());
+        assert_eq!(mapper.position(), 0);
+
+        let mut values = [0u32; 2];
+        mapper.map_slice(&mut values).unwrap();
+        assert_eq!(values.map(u32::from_le), [7, 9]);
+    }
+
+    #[test]
+    fn test_mapper_open_arc() {
+        // Rust-specific: an Arc buffer is mapped in place and kept alive
+        let data: Arc<[u8]> = vec![7, 0, 0, 0, 9, 0, 0, 0].into();
+        let weak = Arc::downgrade(&data);
+        let mut mapper = Mapper::open_arc(data);
+        assert!(mapper.is_open());
+        assert_eq!(mapper.size(), 8);
+
+        assert!(mapper.map_ptr::<u8>(4).unwrap().is_none());
+        // SAFETY: the pointer is only read while the mapper is alive
+        unsafe { mapper.share() };
+        let ptr = mapper.map_ptr::<u8>(4).unwrap().expect("shared");
+        assert_eq!(unsafe { *ptr }, 7);
+        assert_eq!(mapper.map_u32().unwrap(), 9);
+
+        mapper.clear();
+        assert!(!mapper.is_open());
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_mapper_typed_reads_match_generic() {
+        // Rust-specific: explicit little-endian reads agree with map_value on integer data
+        static DATA: [u8; 16] = [
+            0x78, 0x56, 0x34, 0x12, 0xEF, 0xCD, 0xAB, 0x89, 0x67, 0x45, 0x23, 0x01, 0xAA, 0xBB,
+            0xCC, 0xDD,
+        ];
+        let mut typed = Mapper::open_memory(&DATA);
+        let mut generic = Mapper::open_memory(&DATA);
+
+        assert_eq!(typed.map_u32().unwrap(), 0x1234_5678);
+        assert_eq!(typed.map_u64().unwrap(), 0x0123_4567_89AB_CDEF);
+        assert_eq!(
+            u32::from_le(generic.map_value::<u32>().unwrap()),
+            0x1234_5678
+        );
+        assert_eq!(
+            u64::from_le(generic.map_value::<u64>().unwrap()),
+            0x0123_4567_89AB_CDEF
+        );
+        assert_eq!(typed.position(), generic.position());
+
+        let err = typed.map_u64().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(typed.position(), 12);
+    }
+
+    #[test]
+    fn test_mapper_map_u8_slice() {
+        // Rust-specific: bounds-checked byte view
+        static DATA: [u8; 5] = [1, 2, 3, 4, 5];
+        let mut mapper = Mapper::open_memory(&DATA);
+        assert_eq!(mapper.map_u8_slice(2).unwrap(), &[1, 2]);
+        assert_eq!(mapper.map_u8_slice(0).unwrap(), &[] as &[u8]);
+        assert!(mapper.map_u8_slice(4).is_err());
+        assert_eq!(mapper.map_u8_slice(3).unwrap(), &[3, 4, 5]);
+
+        let mut closed = Mapper::new();
+        let err = closed.map_u32().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotConnected);
+    }
 }
@@ -6,11 +6,28 @@
 //! - Reader: for reading trie data from files or memory
 //! - Writer: for writing trie data to files or memory
 //! - Mapper: for memory-mapped file access
+//! - CRC-32: checksum for the optional integrity trailer
+//! - Error, ErrorKind, Result: `std::io`'s, or minimal stand-ins without
+//!   the `std` feature
+//!
+//! Reader and Writer wrap `std::io` streams and require the `std` feature;
+//! Mapper works over byte slices in every build.
 
+pub mod crc32;
+#[cfg(not(feature = "std"))]
+pub mod error;
 pub mod mapper;
+#[cfg(feature = "std")]
 pub mod reader;
+#[cfg(feature = "std")]
 pub mod writer;
 
+#[cfg(not(feature = "std"))]
+pub use error::{Error, ErrorKind, Result};
 pub use mapper::Mapper;
+#[cfg(feature = "std")]
 pub use reader::Reader;
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result};
+#[cfg(feature = "std")]
 pub use writer::Writer;
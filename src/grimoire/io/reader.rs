@@ -7,6 +7,7 @@
 //! Reader provides methods to read binary data from various sources
 //! including files, byte slices, and any type implementing std::io::Read.
 
+use super::crc32::Crc32;
 use std::fs::File;
 use std::io::{self, Read as IoRead};
 use std::path::Path;
@@ -18,12 +19,23 @@ use std::path::Path;
 pub struct Reader<'a> {
     /// The underlying reader, boxed for trait object support.
     reader: Option<Box<dyn IoRead + 'a>>,
+    /// Running checksum of consumed bytes, while one is being tracked.
+    crc: Option<Crc32>,
+    /// Bytes left in the source, when its length is known up front.
+    remaining: Option<usize>,
 }
 
 impl<'a> Reader<'a> {
     /// Creates a new empty reader.
+    ///
+    /// The reader is not open; use [`from_reader`](Self::from_reader) to
+    /// read from an arbitrary `std::io::Read` source.
     pub fn new() -> Self {
-        Reader { reader: None }
+        Reader {
+            reader: None,
+            crc: None,
+            remaining: None,
+        }
     }
 
     /// Opens a file for reading.
@@ -37,19 +49,51 @@ impl<'a> Reader<'a> {
     /// Returns an error if the file cannot be opened.
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Reader<'static>> {
         let file = File::open(path)?;
-        Ok(Reader {
-            reader: Some(Box::new(file)),
-        })
+        let len = usize::try_from(file.metadata()?.len()).ok();
+        let mut reader = Reader::from_reader(file);
+        reader.remaining = len;
+        Ok(reader)
     }
 
     /// Creates a reader from any type implementing std::io::Read.
     ///
+    /// This is the generic constructor: decompressors, sockets, or any other
+    /// `Read` source work. The source does not need to be seekable, since
+    /// [`seek`](Self::seek) only skips forward by reading and discarding.
+    ///
     /// # Arguments
     ///
     /// * `reader` - Any type implementing Read
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::grimoire::io::{Reader, Writer};
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    /// use std::io::Cursor;
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("hello").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    /// let mut writer = Writer::from_vec(Vec::new());
+    /// trie.write(&mut writer).unwrap();
+    /// let bytes = writer.into_inner().unwrap();
+    ///
+    /// let mut loaded = Trie::new();
+    /// loaded
+    ///     .read(&mut Reader::from_reader(Cursor::new(bytes)))
+    ///     .unwrap();
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("hello");
+    /// assert!(loaded.lookup(&mut agent));
+    /// ```
     pub fn from_reader<R: IoRead + 'a>(reader: R) -> Self {
         Reader {
             reader: Some(Box::new(reader)),
+            crc: None,
+            remaining: None,
         }
     }
 
@@ -61,6 +105,8 @@ impl<'a> Reader<'a> {
     pub fn from_bytes(bytes: &[u8]) -> Reader<'static> {
         Reader {
             reader: Some(Box::new(io::Cursor::new(bytes.to_vec()))),
+            crc: None,
+            remaining: Some(bytes.len()),
         }
     }
 
@@ -72,25 +118,48 @@ impl<'a> Reader<'a> {
     ///
     /// # Safety
     ///
-    /// This function reads raw bytes into the memory representation of T.
-    /// It's safe for types like u32, u64, but the caller must ensure T
-    /// is safe to initialize from arbitrary bytes (e.g., Copy types with
-    /// no invalid bit patterns).
+    /// This function reads raw bytes into the memory representation of T,
+    /// in native byte order. It's safe for types like u32, u64, but the
+    /// caller must ensure T is safe to initialize from arbitrary bytes
+    /// (e.g., Copy types with no invalid bit patterns); prefer
+    /// [`read_u32`](Self::read_u32) and [`read_u64`](Self::read_u64) for
+    /// scalar fields.
     pub fn read<T: Copy>(&mut self) -> io::Result<T> {
-        let reader = self
-            .reader
-            .as_mut()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
-
         let size = std::mem::size_of::<T>();
         let mut value = unsafe { std::mem::zeroed::<T>() };
         let slice =
             unsafe { std::slice::from_raw_parts_mut(&mut value as *mut T as *mut u8, size) };
 
-        reader.read_exact(slice)?;
+        self.read_bytes(slice)?;
         Ok(value)
     }
 
+    /// Reads a little-endian `u32`.
+    ///
+    /// Rust-specific: independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reader is not open or if reading fails.
+    pub fn read_u32(&mut self) -> io::Result<u32> {
+        let mut bytes = [0u8; 4];
+        self.read_bytes(&mut bytes)?;
+        Ok(u32::from_le_bytes(bytes))
+    }
+
+    /// Reads a little-endian `u64`.
+    ///
+    /// Rust-specific: independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the reader is not open or if reading fails.
+    pub fn read_u64(&mut self) -> io::Result<u64> {
+        let mut bytes = [0u8; 8];
+        self.read_bytes(&mut bytes)?;
+        Ok(u64::from_le_bytes(bytes))
+    }
+
     /// Reads a single value of type T into a mutable reference.
     ///
     /// # Arguments
@@ -107,16 +176,10 @@ impl<'a> Reader<'a> {
     /// It's safe for types like u32, u64, but the caller must ensure T
     /// is safe to initialize from arbitrary bytes.
     pub fn read_into<T>(&mut self, value: &mut T) -> io::Result<()> {
-        let reader = self
-            .reader
-            .as_mut()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
-
         let size = std::mem::size_of::<T>();
         let slice = unsafe { std::slice::from_raw_parts_mut(value as *mut T as *mut u8, size) };
 
-        reader.read_exact(slice)?;
-        Ok(())
+        self.read_bytes(slice)
     }
 
     /// Reads multiple values into a slice.
@@ -138,16 +201,10 @@ impl<'a> Reader<'a> {
             return Ok(());
         }
 
-        let reader = self
-            .reader
-            .as_mut()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
-
         let size = std::mem::size_of_val(values);
         let slice = unsafe { std::slice::from_raw_parts_mut(values.as_mut_ptr() as *mut u8, size) };
 
-        reader.read_exact(slice)?;
-        Ok(())
+        self.read_bytes(slice)
     }
 
     /// Seeks forward by the specified number of bytes.
@@ -168,26 +225,122 @@ impl<'a> Reader<'a> {
             return Ok(());
         }
 
-        let reader = self
-            .reader
-            .as_mut()
-            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
-
         if size <= 16 {
             let mut buf = [0u8; 16];
-            reader.read_exact(&mut buf[..size])?;
+            self.read_bytes(&mut buf[..size])?;
         } else {
             let mut buf = [0u8; 1024];
             let mut remaining = size;
             while remaining > 0 {
                 let count = remaining.min(buf.len());
-                reader.read_exact(&mut buf[..count])?;
+                self.read_bytes(&mut buf[..count])?;
                 remaining -= count;
             }
         }
         Ok(())
     }
 
+    /// Returns the number of bytes left to read, if the source length is
+    /// known.
+    ///
+    /// Readers from [`from_bytes`](Self::from_bytes) and
+    /// [`open`](Self::open) know their length; readers from
+    /// [`from_reader`](Self::from_reader) and closed readers return `None`.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn remaining(&self) -> Option<usize> {
+        self.reader.as_ref().and(self.remaining)
+    }
+
+    /// Fills `buf` from the underlying reader, updating the checksum.
+    ///
+    /// Running out of input is reported as `UnexpectedEof` with the number
+    /// of bytes requested and, when known, the number available.
+    fn read_bytes(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
+
+        if let Some(remaining) = self.remaining {
+            if buf.len() > remaining {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "Unexpected end of input: requested {} bytes, {} available",
+                        buf.len(),
+                        remaining
+                    ),
+                ));
+            }
+        }
+        reader.read_exact(buf).map_err(|e| {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "Unexpected end of input: requested {} bytes, input ended early",
+                        buf.len()
+                    ),
+                )
+            } else {
+                e
+            }
+        })?;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= buf.len();
+        }
+        if let Some(crc) = &mut self.crc {
+            crc.update(buf);
+        }
+        Ok(())
+    }
+
+    /// Fills `buf` unless the reader is already at end of input.
+    ///
+    /// Returns `Ok(false)` if no bytes were left, `Ok(true)` if `buf` was
+    /// filled, and an `UnexpectedEof` error if input ended part-way.
+    /// Bytes read here are not added to the checksum.
+    pub(crate) fn read_slice_or_eof(&mut self, buf: &mut [u8]) -> io::Result<bool> {
+        let reader = self
+            .reader
+            .as_mut()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Reader not open"))?;
+
+        let mut filled = 0;
+        while filled < buf.len() {
+            match reader.read(&mut buf[filled..]) {
+                Ok(0) if filled == 0 => return Ok(false),
+                Ok(0) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "failed to fill whole buffer",
+                    ))
+                }
+                Ok(count) => {
+                    filled += count;
+                    if let Some(remaining) = &mut self.remaining {
+                        *remaining = remaining.saturating_sub(count);
+                    }
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(true)
+    }
+
+    /// Starts computing a CRC-32 over every byte read from now on.
+    pub(crate) fn begin_crc32(&mut self) {
+        self.crc = Some(Crc32::new());
+    }
+
+    /// Stops checksum tracking and returns the CRC-32 of the bytes read
+    /// since [`begin_crc32`](Self::begin_crc32).
+    pub(crate) fn end_crc32(&mut self) -> Option<u32> {
+        self.crc.take().map(|crc| crc.finish())
+    }
+
     /// Checks if the reader is open.
     pub fn is_open(&self) -> bool {
         self.reader.is_some()
@@ -196,6 +349,8 @@ impl<'a> Reader<'a> {
     /// Closes the reader and releases resources.
     pub fn clear(&mut self) {
         self.reader = None;
+        self.crc = None;
+        self.remaining = None;
     }
 }
 
@@ -346,9 +501,100 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_reader_remaining() {
+        // Rust-specific: in-memory readers track how many bytes are left
+        let data = vec![0u8; 12];
+        let mut reader = Reader::from_bytes(&data);
+        assert_eq!(reader.remaining(), Some(12));
+
+        let _: u32 = reader.read().unwrap();
+        assert_eq!(reader.remaining(), Some(8));
+        reader.seek(8).unwrap();
+        assert_eq!(reader.remaining(), Some(0));
+
+        reader.clear();
+        assert_eq!(reader.remaining(), None);
+        assert_eq!(Reader::new().remaining(), None);
+        assert_eq!(Reader::from_reader(io::Cursor::new(data)).remaining(), None);
+    }
+
+    #[test]
+    fn test_reader_read_past_end_diagnostic() {
+        // Rust-specific: reading past the end reports requested vs available
+        let data = vec![1u8, 2, 3, 4, 5, 6];
+        let mut reader = Reader::from_bytes(&data);
+        let _: u32 = reader.read().unwrap();
+
+        let err = reader.read::<u64>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            err.to_string(),
+            "Unexpected end of input: requested 8 bytes, 2 available"
+        );
+        // The failed read consumes nothing.
+        assert_eq!(reader.remaining(), Some(2));
+
+        let mut values = [0u32; 2];
+        let err = reader.read_slice(&mut values).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected end of input: requested 8 bytes, 2 available"
+        );
+
+        // Sources of unknown length still report the request size.
+        let mut reader = Reader::from_reader(io::Cursor::new(vec![1u8, 2]));
+        let err = reader.read::<u32>().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            err.to_string(),
+            "Unexpected end of input: requested 4 bytes, input ended early"
+        );
+    }
+
     #[test]
     fn test_reader_default() {
         let reader = Reader::default();
         assert!(!reader.is_open());
     }
+
+    #[test]
+    fn test_reader_from_reader_trie_unseekable() {
+        // Rust-specific: a trie streams in from a source that hands out a few
+        // bytes at a time and cannot seek
+        use crate::grimoire::io::Writer;
+        use crate::{Agent, Keyset, Trie};
+
+        struct Trickle(io::Cursor<Vec<u8>>);
+
+        impl IoRead for Trickle {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                let len = buf.len().min(3);
+                self.0.read(&mut buf[..len])
+            }
+        }
+
+        let keys = ["apple", "application", "banana", "band"];
+        let mut keyset = Keyset::new();
+        for key in keys {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+        let mut writer = Writer::from_vec(Vec::new());
+        trie.write(&mut writer).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        let mut reader = Reader::from_reader(Trickle(io::Cursor::new(bytes)));
+        let mut loaded = Trie::new();
+        loaded.read(&mut reader).unwrap();
+
+        let mut agent = Agent::new();
+        for key in keys {
+            agent.set_query_str(key);
+            assert!(loaded.lookup(&mut agent), "lookup {}", key);
+        }
+        agent.set_query_str("ban");
+        assert!(!loaded.lookup(&mut agent));
+    }
 }
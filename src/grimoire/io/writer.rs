@@ -8,31 +8,94 @@
 //! including files, byte vectors, and any type implementing std::io::Write.
 
 use std::fs::File;
-use std::io::{self, Write as IoWrite};
+use std::io::{self, Seek, SeekFrom, Write as IoWrite};
 use std::path::Path;
 
+/// A boxed output sink that may or may not support seeking.
+///
+/// Rust-specific: lets one trait object cover both plain `Write` sinks and
+/// `Write + Seek` ones, since the two cannot be told apart at run time.
+trait Sink: IoWrite {
+    /// Moves the write position by `delta` bytes.
+    fn seek_by(&mut self, delta: i64) -> io::Result<()>;
+}
+
+/// A sink that only supports appending.
+struct Stream<W>(W);
+
+impl<W: IoWrite> IoWrite for Stream<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: IoWrite> Sink for Stream<W> {
+    fn seek_by(&mut self, _delta: i64) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Writer sink is not seekable",
+        ))
+    }
+}
+
+/// A sink that can also move its write position.
+struct Seekable<W>(W);
+
+impl<W: IoWrite> IoWrite for Seekable<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: IoWrite + Seek> Sink for Seekable<W> {
+    fn seek_by(&mut self, delta: i64) -> io::Result<()> {
+        self.0.seek(SeekFrom::Current(delta)).map(|_| ())
+    }
+}
+
 /// Writer for writing binary data to various destinations.
 ///
 /// Writer wraps a std::io::Write implementation and provides convenient
 /// methods for writing typed data and seeking forward with zero padding.
+/// Seekable sinks can also move back with [`seek_to`](Self::seek_to) to
+/// patch bytes already written, such as a header.
 pub struct Writer<'a> {
     /// The underlying writer, boxed for trait object support.
-    writer: Option<Box<dyn IoWrite + 'a>>,
+    writer: Option<Box<dyn Sink + 'a>>,
     /// Optional buffer for in-memory writing (for testing).
     buffer: Option<Vec<u8>>,
+    /// Write position, in bytes from where this writer started.
+    pos: usize,
+    /// Number of bytes written, i.e. the furthest position reached.
+    len: usize,
 }
 
 impl<'a> Writer<'a> {
     /// Creates a new empty writer.
+    ///
+    /// The writer is not open; use [`from_writer`](Self::from_writer) to
+    /// write to an arbitrary `std::io::Write` sink.
     pub fn new() -> Self {
         Writer {
             writer: None,
             buffer: None,
+            pos: 0,
+            len: 0,
         }
     }
 
     /// Opens a file for writing.
     ///
+    /// The file is seekable, so [`seek_to`](Self::seek_to) works on it.
+    ///
     /// # Arguments
     ///
     /// * `path` - Path to the file to create/overwrite
@@ -42,29 +105,107 @@ impl<'a> Writer<'a> {
     /// Returns an error if the file cannot be created.
     pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Writer<'static>> {
         let file = File::create(path)?;
-        Ok(Writer {
-            writer: Some(Box::new(file)),
-            buffer: None,
-        })
+        Ok(Writer::from_seekable(file))
     }
 
     /// Creates a writer from any type implementing std::io::Write.
     ///
+    /// This is the generic constructor: sockets, compressing wrappers,
+    /// `Cursor`s, or `&mut` references to any of these all work. The sink
+    /// is boxed, and `write`, `write_slice` and `seek` all go through it.
+    /// It is not seekable; use [`from_seekable`](Self::from_seekable) when
+    /// [`seek_to`](Self::seek_to) is needed.
+    ///
     /// # Arguments
     ///
     /// * `writer` - Any type implementing Write
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::grimoire::io::Writer;
+    /// use rsmarisa::{Keyset, Trie};
+    /// use std::io::Cursor;
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("hello").unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut cursor = Cursor::new(Vec::new());
+    /// trie.write(&mut Writer::from_writer(&mut cursor)).unwrap();
+    /// assert_eq!(cursor.into_inner().len(), trie.io_size());
+    /// ```
     pub fn from_writer<W: IoWrite + 'a>(writer: W) -> Self {
         Writer {
-            writer: Some(Box::new(writer)),
+            writer: Some(Box::new(Stream(writer))),
             buffer: None,
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Creates a writer from a sink that also implements `std::io::Seek`.
+    ///
+    /// Like [`from_writer`](Self::from_writer), but the writer can move
+    /// back over what it wrote with [`seek_to`](Self::seek_to). Positions
+    /// are relative to where the sink was when the writer was created.
+    ///
+    /// # Arguments
+    ///
+    /// * `writer` - Any type implementing Write and Seek
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn from_seekable<W: IoWrite + Seek + 'a>(writer: W) -> Self {
+        Writer {
+            writer: Some(Box::new(Seekable(writer))),
+            buffer: None,
+            pos: 0,
+            len: 0,
         }
     }
 
     /// Creates a writer that writes to a `Vec<u8>`.
+    ///
+    /// Bytes are appended after any the vector already holds. The writer
+    /// is seekable over the bytes it appended.
     pub fn from_vec(vec: Vec<u8>) -> Writer<'static> {
         Writer {
             writer: None,
             buffer: Some(vec),
+            pos: 0,
+            len: 0,
+        }
+    }
+
+    /// Writes raw bytes at the current position without flushing.
+    ///
+    /// Bytes before the end of what was written are overwritten, the rest
+    /// are appended.
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        if let Some(buffer) = &mut self.buffer {
+            let at = buffer.len() - (self.len - self.pos);
+            let overlap = bytes.len().min(buffer.len() - at);
+            buffer[at..at + overlap].copy_from_slice(&bytes[..overlap]);
+            buffer.extend_from_slice(&bytes[overlap..]);
+        } else if let Some(writer) = &mut self.writer {
+            writer.write_all(bytes)?;
+        } else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotConnected,
+                "Writer not open",
+            ));
+        }
+        self.pos += bytes.len();
+        self.len = self.len.max(self.pos);
+        Ok(())
+    }
+
+    /// Flushes the underlying writer, if any.
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.writer {
+            Some(writer) => writer.flush(),
+            None => Ok(()),
         }
     }
 
@@ -80,26 +221,39 @@ impl<'a> Writer<'a> {
     ///
     /// # Safety
     ///
-    /// This function writes the raw bytes of T's memory representation.
-    /// It's safe for types like u32, u64, but the caller must ensure T
-    /// has a stable binary representation.
+    /// This function writes the raw bytes of T's memory representation, in
+    /// native byte order. It's safe for types like u32, u64, but the caller
+    /// must ensure T has a stable binary representation; prefer
+    /// [`write_u32`](Self::write_u32) and [`write_u64`](Self::write_u64)
+    /// for scalar fields.
     pub fn write<T>(&mut self, value: &T) -> io::Result<()> {
         let size = std::mem::size_of::<T>();
         let slice = unsafe { std::slice::from_raw_parts(value as *const T as *const u8, size) };
 
-        if let Some(buffer) = &mut self.buffer {
-            buffer.extend_from_slice(slice);
-            Ok(())
-        } else if let Some(writer) = &mut self.writer {
-            writer.write_all(slice)?;
-            writer.flush()?;
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotConnected,
-                "Writer not open",
-            ))
-        }
+        self.write_bytes(slice)?;
+        self.flush()
+    }
+
+    /// Writes a `u32` in little-endian byte order.
+    ///
+    /// Rust-specific: independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer is not open or if writing fails.
+    pub fn write_u32(&mut self, value: u32) -> io::Result<()> {
+        self.write_slice(&value.to_le_bytes())
+    }
+
+    /// Writes a `u64` in little-endian byte order.
+    ///
+    /// Rust-specific: independent of host byte order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer is not open or if writing fails.
+    pub fn write_u64(&mut self, value: u64) -> io::Result<()> {
+        self.write_slice(&value.to_le_bytes())
     }
 
     /// Writes multiple values from a slice.
@@ -124,19 +278,8 @@ impl<'a> Writer<'a> {
         let size = std::mem::size_of_val(values);
         let slice = unsafe { std::slice::from_raw_parts(values.as_ptr() as *const u8, size) };
 
-        if let Some(buffer) = &mut self.buffer {
-            buffer.extend_from_slice(slice);
-            Ok(())
-        } else if let Some(writer) = &mut self.writer {
-            writer.write_all(slice)?;
-            writer.flush()?;
-            Ok(())
-        } else {
-            Err(io::Error::new(
-                io::ErrorKind::NotConnected,
-                "Writer not open",
-            ))
-        }
+        self.write_bytes(slice)?;
+        self.flush()
     }
 
     /// Seeks forward by writing the specified number of zero bytes.
@@ -144,6 +287,11 @@ impl<'a> Writer<'a> {
     /// This method writes zero padding. For small sizes (<= 16 bytes),
     /// it uses a stack buffer. For larger sizes, it uses a 1024-byte buffer.
     ///
+    /// The bytes are always written, never skipped over, so the output
+    /// grows by exactly `size` bytes even for non-seekable sinks.
+    /// `Vector::write` relies on this for its 8-byte alignment padding,
+    /// which keeps the serialized length equal to `io_size()`.
+    ///
     /// # Arguments
     ///
     /// * `size` - Number of zero bytes to write
@@ -156,30 +304,85 @@ impl<'a> Writer<'a> {
             return Ok(());
         }
 
-        if let Some(buffer) = &mut self.buffer {
-            buffer.resize(buffer.len() + size, 0);
-            Ok(())
-        } else if let Some(writer) = &mut self.writer {
-            if size <= 16 {
-                let buf = [0u8; 16];
-                writer.write_all(&buf[..size])?;
-            } else {
-                let buf = [0u8; 1024];
-                let mut remaining = size;
-                while remaining > 0 {
-                    let count = remaining.min(buf.len());
-                    writer.write_all(&buf[..count])?;
-                    remaining -= count;
-                }
-            }
-            writer.flush()?;
-            Ok(())
+        if size <= 16 {
+            let buf = [0u8; 16];
+            self.write_bytes(&buf[..size])?;
         } else {
-            Err(io::Error::new(
+            let buf = [0u8; 1024];
+            let mut remaining = size;
+            while remaining > 0 {
+                let count = remaining.min(buf.len());
+                self.write_bytes(&buf[..count])?;
+                remaining -= count;
+            }
+        }
+        self.flush()
+    }
+
+    /// Returns the write position.
+    ///
+    /// This is the number of bytes from where the writer started to where
+    /// the next write goes; it equals the bytes written unless
+    /// [`seek_to`](Self::seek_to) moved back.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// Moves the write position to `pos` for back-patching.
+    ///
+    /// The next write overwrites bytes from `pos` on instead of appending.
+    /// Seek back to the end, the largest position reached so far, to
+    /// resume appending. Unlike [`seek`](Self::seek), this never writes
+    /// padding.
+    ///
+    /// # Arguments
+    ///
+    /// * `pos` - Position in bytes from where the writer started
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the writer is not open, if `pos` is past the
+    /// bytes written so far, or, with `ErrorKind::Unsupported`, if the sink
+    /// was given to [`from_writer`](Self::from_writer) and is not seekable.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::grimoire::io::Writer;
+    ///
+    /// let mut writer = Writer::from_vec(Vec::new());
+    /// writer.write_u32(0).unwrap(); // placeholder
+    /// writer.write_slice(b"body").unwrap();
+    /// let end = writer.position();
+    ///
+    /// writer.seek_to(0).unwrap();
+    /// writer.write_u32(4).unwrap();
+    /// writer.seek_to(end).unwrap();
+    ///
+    /// assert_eq!(writer.into_inner().unwrap(), b"\x04\0\0\0body");
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn seek_to(&mut self, pos: usize) -> io::Result<()> {
+        if !self.is_open() {
+            return Err(io::Error::new(
                 io::ErrorKind::NotConnected,
                 "Writer not open",
-            ))
+            ));
         }
+        if pos > self.len {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Cannot seek to {} past the {} bytes written", pos, self.len),
+            ));
+        }
+        if let Some(writer) = &mut self.writer {
+            writer.seek_by(pos as i64 - self.pos as i64)?;
+        }
+        self.pos = pos;
+        Ok(())
     }
 
     /// Checks if the writer is open.
@@ -191,6 +394,8 @@ impl<'a> Writer<'a> {
     pub fn clear(&mut self) {
         self.writer = None;
         self.buffer = None;
+        self.pos = 0;
+        self.len = 0;
     }
 
     /// Extracts the inner `Vec<u8>` if the writer was created with from_vec.
@@ -238,6 +443,37 @@ mod tests {
         assert_eq!(data, vec![0x01, 0x02, 0x03, 0x04]);
     }
 
+    #[test]
+    fn test_writer_write_le_integers() {
+        // Rust-specific: typed writes are little-endian on every host and
+        // read back through the typed reads
+        use crate::grimoire::io::Reader;
+
+        let mut writer = Writer::from_vec(Vec::new());
+        writer.write_u32(0x0403_0201).unwrap();
+        writer.write_u64(0x0807_0605_0403_0201).unwrap();
+        writer.write_u32(u32::MAX - 1).unwrap();
+        let data = writer.into_inner().unwrap();
+        assert_eq!(
+            data,
+            [
+                0x01, 0x02, 0x03, 0x04, // u32
+                0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, // u64
+                0xFE, 0xFF, 0xFF, 0xFF, // u32
+            ]
+        );
+
+        let mut reader = Reader::from_bytes(&data);
+        assert_eq!(reader.read_u32().unwrap(), 0x0403_0201);
+        assert_eq!(reader.read_u64().unwrap(), 0x0807_0605_0403_0201);
+        assert_eq!(reader.read_u32().unwrap(), u32::MAX - 1);
+        assert_eq!(reader.remaining(), Some(0));
+        assert_eq!(
+            reader.read_u32().unwrap_err().kind(),
+            std::io::ErrorKind::UnexpectedEof
+        );
+    }
+
     #[test]
     fn test_writer_write_slice() {
         let mut writer = Writer::from_vec(Vec::new());
@@ -300,6 +536,83 @@ mod tests {
         assert!(data[1..1501].iter().all(|&b| b == 0));
     }
 
+    #[test]
+    fn test_writer_seek_to_patches_placeholder() {
+        // Rust-specific: write a placeholder, seek back, overwrite it and
+        // resume appending, for both the Vec and a seekable sink
+        fn write_patched(writer: &mut Writer<'_>) {
+            writer.write_u64(0).unwrap();
+            writer.write_slice(b"payload").unwrap();
+            let end = writer.position();
+            assert_eq!(end, 15);
+
+            writer.seek_to(0).unwrap();
+            assert_eq!(writer.position(), 0);
+            writer.write_u64(0x0102_0304_0506_0708).unwrap();
+            assert_eq!(writer.position(), 8);
+            writer.seek_to(end).unwrap();
+            writer.write_slice(b"!").unwrap();
+            assert_eq!(writer.position(), 16);
+        }
+
+        let mut expected = 0x0102_0304_0506_0708u64.to_le_bytes().to_vec();
+        expected.extend_from_slice(b"payload!");
+
+        let mut writer = Writer::from_vec(b"prefix".to_vec());
+        write_patched(&mut writer);
+        let data = writer.into_inner().unwrap();
+        assert_eq!(&data[..6], b"prefix");
+        assert_eq!(&data[6..], &expected[..]);
+
+        let mut cursor = io::Cursor::new(Vec::new());
+        write_patched(&mut Writer::from_seekable(&mut cursor));
+        assert_eq!(cursor.into_inner(), expected);
+    }
+
+    #[test]
+    fn test_writer_seek_to_errors() {
+        // Rust-specific: a plain Write sink refuses seek_to, and no sink
+        // seeks past what was written
+        let mut writer = Writer::from_writer(Vec::new());
+        writer.write_u32(1).unwrap();
+        assert_eq!(writer.position(), 4);
+        let err = writer.seek_to(0).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+
+        let mut writer = Writer::from_vec(Vec::new());
+        writer.seek(3).unwrap();
+        assert_eq!(
+            writer.seek_to(4).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+        writer.seek_to(1).unwrap();
+        writer.write(&7u8).unwrap();
+        assert_eq!(writer.into_inner().unwrap(), vec![0, 7, 0]);
+
+        let mut writer = Writer::new();
+        assert_eq!(
+            writer.seek_to(0).unwrap_err().kind(),
+            io::ErrorKind::NotConnected
+        );
+    }
+
+    #[test]
+    fn test_writer_open_file_seek_to() {
+        // Rust-specific: files opened by Writer::open are seekable
+        use tempfile::NamedTempFile;
+
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut writer = Writer::open(file.path()).unwrap();
+            writer.write_u32(0).unwrap();
+            writer.write_u32(2).unwrap();
+            writer.seek_to(0).unwrap();
+            writer.write_u32(1).unwrap();
+        }
+        let data = std::fs::read(file.path()).unwrap();
+        assert_eq!(data, [1, 0, 0, 0, 2, 0, 0, 0]);
+    }
+
     #[test]
     fn test_writer_clear() {
         let mut writer = Writer::from_vec(Vec::new());
@@ -381,4 +694,28 @@ mod tests {
         let data = writer.into_inner().unwrap();
         assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
     }
+
+    #[test]
+    fn test_writer_from_writer_trie_cursor() {
+        // Rust-specific: a trie written through a generic sink matches from_vec
+        use crate::{Keyset, Trie};
+        use std::io::Cursor;
+
+        let mut keyset = Keyset::new();
+        for key in ["apple", "banana", "cherry"] {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut cursor = Cursor::new(Vec::new());
+        {
+            let mut writer = Writer::from_writer(&mut cursor);
+            trie.write(&mut writer).unwrap();
+        }
+
+        let mut expected = Writer::from_vec(Vec::new());
+        trie.write(&mut expected).unwrap();
+        assert_eq!(cursor.into_inner(), expected.into_inner().unwrap());
+    }
 }
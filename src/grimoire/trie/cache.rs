@@ -30,8 +30,8 @@ impl Default for LinkOrWeight {
 }
 
 // Debug implementation for union
-impl std::fmt::Debug for LinkOrWeight {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for LinkOrWeight {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         // We don't know which variant is active, so just show the raw bits
         unsafe {
             write!(
@@ -6,7 +6,7 @@
 //! Configuration flags control various aspects of trie construction including
 //! the number of tries, cache size, tail storage mode, and node ordering.
 
-use crate::base::{CacheLevel, NodeOrder, NumTries, TailMode};
+use crate::base::{build_flags, CacheLevel, NodeOrder, NumTries, TailMode};
 
 /// Configuration masks and constants.
 mod masks {
@@ -15,6 +15,7 @@ mod masks {
     pub const TAIL_MODE_MASK: i32 = 0x0F000;
     pub const NODE_ORDER_MASK: i32 = 0xF0000;
     pub const CONFIG_MASK: i32 = 0xFFFFF;
+    pub const BUILD_FLAGS_MASK: i32 = 0x3F00000;
 }
 
 /// Configuration for trie building.
@@ -32,6 +33,16 @@ pub struct Config {
     tail_mode: TailMode,
     /// Node ordering (by label or weight).
     node_order: NodeOrder,
+    /// Whether select indices are built lazily.
+    lazy_select: bool,
+    /// Whether the tail is built without suffix merging.
+    no_tail_merge: bool,
+    /// Whether keys are sorted with a stable sort.
+    stable_sort: bool,
+    /// Whether a lookup pre-filter is built.
+    lookup_filter: bool,
+    /// Whether weights are summed independently of key order.
+    deterministic_weights: bool,
 }
 
 impl Config {
@@ -42,6 +53,11 @@ impl Config {
             cache_level: CacheLevel::default(),
             tail_mode: TailMode::default(),
             node_order: NodeOrder::default(),
+            lazy_select: false,
+            no_tail_merge: false,
+            stable_sort: false,
+            lookup_filter: false,
+            deterministic_weights: false,
         }
     }
 
@@ -93,6 +109,36 @@ impl Config {
         self.node_order
     }
 
+    /// Returns true if select indices should be built lazily.
+    #[inline]
+    pub fn lazy_select(&self) -> bool {
+        self.lazy_select
+    }
+
+    /// Returns true if the tail should be built without suffix merging.
+    #[inline]
+    pub fn no_tail_merge(&self) -> bool {
+        self.no_tail_merge
+    }
+
+    /// Returns true if keys should be sorted with a stable sort.
+    #[inline]
+    pub fn stable_sort(&self) -> bool {
+        self.stable_sort
+    }
+
+    /// Returns true if a lookup pre-filter should be built.
+    #[inline]
+    pub fn lookup_filter(&self) -> bool {
+        self.lookup_filter
+    }
+
+    /// Returns true if weights should be summed independently of key order.
+    #[inline]
+    pub fn deterministic_weights(&self) -> bool {
+        self.deterministic_weights
+    }
+
     /// Clears the configuration to default values.
     pub fn clear(&mut self) {
         *self = Config::new();
@@ -100,16 +146,24 @@ impl Config {
 
     /// Swaps the contents of two configurations.
     pub fn swap(&mut self, other: &mut Config) {
-        std::mem::swap(&mut self.num_tries, &mut other.num_tries);
-        std::mem::swap(&mut self.cache_level, &mut other.cache_level);
-        std::mem::swap(&mut self.tail_mode, &mut other.tail_mode);
-        std::mem::swap(&mut self.node_order, &mut other.node_order);
+        core::mem::swap(&mut self.num_tries, &mut other.num_tries);
+        core::mem::swap(&mut self.cache_level, &mut other.cache_level);
+        core::mem::swap(&mut self.tail_mode, &mut other.tail_mode);
+        core::mem::swap(&mut self.node_order, &mut other.node_order);
+        core::mem::swap(&mut self.lazy_select, &mut other.lazy_select);
+        core::mem::swap(&mut self.no_tail_merge, &mut other.no_tail_merge);
+        core::mem::swap(&mut self.stable_sort, &mut other.stable_sort);
+        core::mem::swap(&mut self.lookup_filter, &mut other.lookup_filter);
+        core::mem::swap(
+            &mut self.deterministic_weights,
+            &mut other.deterministic_weights,
+        );
     }
 
     /// Internal parsing implementation.
     fn parse_internal(&mut self, config_flags: i32) {
         assert!(
-            (config_flags & !masks::CONFIG_MASK) == 0,
+            (config_flags & !(masks::CONFIG_MASK | masks::BUILD_FLAGS_MASK)) == 0,
             "Invalid configuration flags"
         );
 
@@ -117,6 +171,7 @@ impl Config {
         self.parse_cache_level(config_flags);
         self.parse_tail_mode(config_flags);
         self.parse_node_order(config_flags);
+        self.parse_build_flags(config_flags);
     }
 
     /// Parses the number of tries from flags.
@@ -150,6 +205,7 @@ impl Config {
             0 => TailMode::default(),
             x if x == TailMode::TextTail as i32 => TailMode::TextTail,
             x if x == TailMode::BinaryTail as i32 => TailMode::BinaryTail,
+            x if x == TailMode::Auto as i32 => TailMode::Auto,
             _ => panic!("Undefined tail mode"),
         };
     }
@@ -162,9 +218,30 @@ impl Config {
             0 => NodeOrder::default(),
             x if x == NodeOrder::Label as i32 => NodeOrder::Label,
             x if x == NodeOrder::Weight as i32 => NodeOrder::Weight,
+            x if x == NodeOrder::None as i32 => NodeOrder::None,
             _ => panic!("Undefined node order"),
         };
     }
+
+    /// Parses the Rust-specific build flags.
+    fn parse_build_flags(&mut self, config_flags: i32) {
+        let build_flag_bits = config_flags & masks::BUILD_FLAGS_MASK;
+        assert!(
+            (build_flag_bits
+                & !(build_flags::LAZY_SELECT
+                    | build_flags::NO_TAIL_MERGE
+                    | build_flags::STABLE_SORT
+                    | build_flags::LOOKUP_FILTER
+                    | build_flags::DETERMINISTIC_WEIGHTS))
+                == 0,
+            "Undefined build flag"
+        );
+        self.lazy_select = (build_flag_bits & build_flags::LAZY_SELECT) != 0;
+        self.no_tail_merge = (build_flag_bits & build_flags::NO_TAIL_MERGE) != 0;
+        self.stable_sort = (build_flag_bits & build_flags::STABLE_SORT) != 0;
+        self.lookup_filter = (build_flag_bits & build_flags::LOOKUP_FILTER) != 0;
+        self.deterministic_weights = (build_flag_bits & build_flags::DETERMINISTIC_WEIGHTS) != 0;
+    }
 }
 
 impl Default for Config {
@@ -217,6 +294,13 @@ mod tests {
 
         config.parse(TailMode::BinaryTail as i32);
         assert_eq!(config.tail_mode() as i32, TailMode::BinaryTail as i32);
+
+        config.parse(TailMode::Auto as i32);
+        assert_eq!(config.tail_mode(), TailMode::Auto);
+        assert_eq!(
+            config.flags() & masks::TAIL_MODE_MASK,
+            TailMode::Auto as i32
+        );
     }
 
     #[test]
@@ -227,6 +311,14 @@ mod tests {
 
         config.parse(NodeOrder::Weight as i32);
         assert_eq!(config.node_order() as i32, NodeOrder::Weight as i32);
+
+        // Rust-specific: insertion order round-trips through flags
+        config.parse(NodeOrder::None as i32);
+        assert_eq!(config.node_order(), NodeOrder::None);
+        assert_eq!(
+            config.flags() & masks::NODE_ORDER_MASK,
+            NodeOrder::None as i32
+        );
     }
 
     #[test]
@@ -309,4 +401,75 @@ mod tests {
         let mut config = Config::new();
         config.parse(0xC0000); // Invalid node order
     }
+
+    #[test]
+    fn test_config_parse_lazy_select() {
+        // Rust-specific: build flags parse but stay out of flags()
+        let mut config = Config::new();
+        assert!(!config.lazy_select());
+
+        config.parse(3 | build_flags::LAZY_SELECT);
+        assert!(config.lazy_select());
+        assert_eq!(config.num_tries(), 3);
+        assert_eq!(config.flags() & build_flags::LAZY_SELECT, 0);
+
+        config.parse(3);
+        assert!(!config.lazy_select());
+    }
+
+    #[test]
+    fn test_config_parse_no_tail_merge() {
+        // Rust-specific: NO_TAIL_MERGE combines with other build flags
+        let mut config = Config::new();
+        assert!(!config.no_tail_merge());
+
+        config.parse(build_flags::NO_TAIL_MERGE | build_flags::LAZY_SELECT);
+        assert!(config.no_tail_merge());
+        assert!(config.lazy_select());
+        assert_eq!(config.flags() & build_flags::NO_TAIL_MERGE, 0);
+    }
+
+    #[test]
+    fn test_config_parse_stable_sort() {
+        // Rust-specific: STABLE_SORT is a build flag and is not serialized
+        let mut config = Config::new();
+        assert!(!config.stable_sort());
+
+        config.parse(2 | build_flags::STABLE_SORT);
+        assert!(config.stable_sort());
+        assert_eq!(config.num_tries(), 2);
+        assert_eq!(config.flags() & build_flags::STABLE_SORT, 0);
+    }
+
+    #[test]
+    fn test_config_parse_lookup_filter() {
+        // Rust-specific: LOOKUP_FILTER is a build flag and is not serialized
+        let mut config = Config::new();
+        assert!(!config.lookup_filter());
+
+        config.parse(build_flags::LOOKUP_FILTER | build_flags::STABLE_SORT);
+        assert!(config.lookup_filter());
+        assert!(config.stable_sort());
+        assert_eq!(config.flags() & build_flags::LOOKUP_FILTER, 0);
+    }
+
+    #[test]
+    fn test_config_parse_deterministic_weights() {
+        // Rust-specific: DETERMINISTIC_WEIGHTS is a build flag and is not
+        // serialized
+        let mut config = Config::new();
+        assert!(!config.deterministic_weights());
+
+        config.parse(build_flags::DETERMINISTIC_WEIGHTS | NodeOrder::Weight as i32);
+        assert!(config.deterministic_weights());
+        assert_eq!(config.node_order(), NodeOrder::Weight);
+        assert_eq!(config.flags() & build_flags::DETERMINISTIC_WEIGHTS, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Undefined build flag")]
+    fn test_config_parse_invalid_build_flag() {
+        let mut config = Config::new();
+        config.parse(0x2000000);
+    }
 }
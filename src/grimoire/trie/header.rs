@@ -5,11 +5,32 @@
 //! The header is a simple magic string "We love Marisa." used to identify
 //! valid trie files and verify file format integrity.
 
-use crate::grimoire::io::{Mapper, Reader, Writer};
+use crate::grimoire::io::{self, Mapper};
+#[cfg(feature = "std")]
+use crate::grimoire::io::{Reader, Writer};
+use alloc::format;
+use alloc::string::String;
 
 /// Size of the header in bytes.
 pub const HEADER_SIZE: usize = 16;
 
+/// Size of the optional checksum trailer in bytes.
+pub const CHECKSUM_SIZE: usize = 16;
+
+/// Version of the on-disk format this crate reads and writes.
+///
+/// Rust-specific: C++ marisa has no version field, and adding one would
+/// break byte compatibility, so the version is carried by the header
+/// magic itself. Version 1 is the format of C++ marisa 0.2 and later,
+/// marked by "We love Marisa.\0".
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Header bytes shared by every format version ("We love Marisa").
+///
+/// A header that starts with these bytes but differs in the rest is from
+/// a format version this crate does not know.
+const MAGIC_PREFIX_SIZE: usize = 14;
+
 /// Header for trie file format identification.
 ///
 /// The header contains a magic string to verify that a file or memory region
@@ -50,6 +71,39 @@ impl Header {
         bytes == Self::get_header()
     }
 
+    /// Returns the format version a header declares.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The header bytes
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidData` if `bytes` is not a MARISA header, and
+    /// `Unsupported` if it is a MARISA header of an unknown format version.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn version(bytes: &[u8]) -> io::Result<u32> {
+        let header = Self::get_header();
+        if bytes.len() != HEADER_SIZE || bytes[..MAGIC_PREFIX_SIZE] != header[..MAGIC_PREFIX_SIZE] {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Invalid MARISA header",
+            ));
+        }
+        if !Self::test_header(bytes) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!(
+                    "Unsupported MARISA format version (header {:?}, expected version {})",
+                    String::from_utf8_lossy(bytes),
+                    FORMAT_VERSION
+                ),
+            ));
+        }
+        Ok(FORMAT_VERSION)
+    }
+
     /// Maps the header from a mapper (for memory-mapped I/O).
     ///
     /// # Arguments
@@ -58,18 +112,13 @@ impl Header {
     ///
     /// # Errors
     ///
-    /// Returns an error if the header is invalid or mapping fails
-    pub fn map(&mut self, mapper: &mut Mapper) -> std::io::Result<()> {
+    /// Returns `InvalidData` if the header is invalid, `Unsupported` if it
+    /// declares an unknown format version, or an error if mapping fails
+    pub fn map(&mut self, mapper: &mut Mapper<'_>) -> io::Result<()> {
         let mut buf = [0u8; HEADER_SIZE];
         mapper.map_slice(&mut buf)?;
 
-        if !Self::test_header(&buf) {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid MARISA header",
-            ));
-        }
-
+        Self::version(&buf)?;
         Ok(())
     }
 
@@ -81,18 +130,14 @@ impl Header {
     ///
     /// # Errors
     ///
-    /// Returns an error if the header is invalid or reading fails
-    pub fn read(&mut self, reader: &mut Reader<'_>) -> std::io::Result<()> {
+    /// Returns `InvalidData` if the header is invalid, `Unsupported` if it
+    /// declares an unknown format version, or an error if reading fails
+    #[cfg(feature = "std")]
+    pub fn read(&mut self, reader: &mut Reader<'_>) -> io::Result<()> {
         let mut buf = [0u8; HEADER_SIZE];
         reader.read_slice(&mut buf)?;
 
-        if !Self::test_header(&buf) {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                "Invalid MARISA header",
-            ));
-        }
-
+        Self::version(&buf)?;
         Ok(())
     }
 
@@ -105,7 +150,8 @@ impl Header {
     /// # Errors
     ///
     /// Returns an error if writing fails
-    pub fn write(&self, writer: &mut Writer<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut Writer<'_>) -> io::Result<()> {
         writer.write_slice(Self::get_header())
     }
 
@@ -144,6 +190,57 @@ impl Header {
     }
 }
 
+/// Optional CRC-32 trailer appended after a serialized trie.
+///
+/// Rust-specific: C++ marisa has no checksum. The trailer is 16 bytes: an
+/// 8-byte magic, the little-endian CRC-32 of every preceding byte (header
+/// included), and 4 reserved zero bytes. C++ marisa stops reading at the
+/// end of the trie, so files with a trailer remain loadable there.
+pub struct Checksum;
+
+impl Checksum {
+    /// Magic bytes identifying the trailer.
+    const MAGIC: &'static [u8; 8] = b"RSMCRC32";
+
+    /// Encodes a trailer carrying `crc`.
+    pub fn encode(crc: u32) -> [u8; CHECKSUM_SIZE] {
+        let mut bytes = [0u8; CHECKSUM_SIZE];
+        bytes[..8].copy_from_slice(Self::MAGIC);
+        bytes[8..12].copy_from_slice(&crc.to_le_bytes());
+        bytes
+    }
+
+    /// Decodes a trailer, returning the stored CRC-32.
+    ///
+    /// Returns `None` if `bytes` is not a checksum trailer.
+    pub fn decode(bytes: &[u8; CHECKSUM_SIZE]) -> Option<u32> {
+        if &bytes[..8] != Self::MAGIC || bytes[12..] != [0u8; 4] {
+            return None;
+        }
+        Some(u32::from_le_bytes([
+            bytes[8], bytes[9], bytes[10], bytes[11],
+        ]))
+    }
+
+    /// Compares a stored checksum against the computed one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidData` if the checksums differ.
+    pub fn verify(expected: u32, actual: u32) -> io::Result<()> {
+        if expected != actual {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Checksum mismatch: expected {:#010x}, got {:#010x}",
+                    expected, actual
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -194,6 +291,44 @@ mod tests {
         assert!(!Header::validate(partial));
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_header_version() {
+        // Rust-specific: the magic carries the format version
+        assert_eq!(Header::version(&Header::bytes()).unwrap(), FORMAT_VERSION);
+
+        let mut bumped = Header::bytes();
+        bumped[14] = b'2';
+        let err = Header::version(&bumped).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::Unsupported);
+        assert!(err.to_string().contains("version"), "{}", err);
+        let err = Header::new()
+            .read(&mut Reader::from_bytes(&bumped))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+
+        for invalid in [&b"Invalid header!\0"[..], b"We love Marisa"] {
+            let err = Header::version(invalid).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn test_checksum_round_trip() {
+        // Rust-specific: checksum trailer is not part of C++ marisa
+        let bytes = Checksum::encode(0xDEAD_BEEF);
+        assert_eq!(&bytes[..8], b"RSMCRC32");
+        assert_eq!(Checksum::decode(&bytes), Some(0xDEAD_BEEF));
+
+        let mut bad = bytes;
+        bad[0] = b'X';
+        assert_eq!(Checksum::decode(&bad), None);
+
+        assert!(Checksum::verify(1, 1).is_ok());
+        let err = Checksum::verify(1, 2).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
     #[test]
     #[allow(clippy::default_constructed_unit_structs)]
     fn test_header_default() {
@@ -127,8 +127,11 @@ impl<'a> Key<'a> {
     }
 
     /// Returns a pointer to the string data.
+    ///
+    /// The slice borrows from the key's source data, not from the key, so
+    /// it stays valid after the key itself is dropped or overwritten.
     #[inline]
-    pub fn as_bytes(&self) -> &[u8] {
+    pub fn as_bytes(&self) -> &'a [u8] {
         self.bytes
     }
 
@@ -180,13 +183,13 @@ impl<'a> PartialEq for Key<'a> {
 impl<'a> Eq for Key<'a> {}
 
 impl<'a> PartialOrd for Key<'a> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl<'a> Ord for Key<'a> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         // Lexicographic comparison treating bytes as unsigned
         self.bytes.cmp(other.bytes)
     }
@@ -320,8 +323,10 @@ impl<'a> ReverseKey<'a> {
     }
 
     /// Returns the forward byte slice.
+    ///
+    /// The slice borrows from the key's source data, not from the key.
     #[inline]
-    pub fn as_bytes(&self) -> &[u8] {
+    pub fn as_bytes(&self) -> &'a [u8] {
         &self.bytes[self.end - self.length..self.end]
     }
 
@@ -381,19 +386,19 @@ impl<'a> PartialEq for ReverseKey<'a> {
 impl<'a> Eq for ReverseKey<'a> {}
 
 impl<'a> PartialOrd for ReverseKey<'a> {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl<'a> Ord for ReverseKey<'a> {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         for i in 0..self.length {
             if i == other.length {
-                return std::cmp::Ordering::Greater;
+                return core::cmp::Ordering::Greater;
             }
             match self.get(i).cmp(&other.get(i)) {
-                std::cmp::Ordering::Equal => continue,
+                core::cmp::Ordering::Equal => continue,
                 other => return other,
             }
         }
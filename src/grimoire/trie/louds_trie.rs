@@ -7,8 +7,10 @@
 //! This is the core trie data structure using LOUDS encoding for
 //! space-efficient storage while maintaining fast search operations.
 
-use crate::base::{CacheLevel, NodeOrder, TailMode};
-use crate::grimoire::io::{Mapper, Reader, Writer};
+use crate::base::{Advice, BuildPhase, CacheLevel, NodeOrder, TailMode};
+use crate::grimoire::io::{self, Mapper};
+#[cfg(feature = "std")]
+use crate::grimoire::io::{Reader, Writer};
 use crate::grimoire::trie::cache::Cache;
 use crate::grimoire::trie::config::Config;
 use crate::grimoire::trie::key::{Key, ReverseKey};
@@ -16,6 +18,14 @@ use crate::grimoire::trie::tail::Tail;
 use crate::grimoire::vector::bit_vector::BitVector;
 use crate::grimoire::vector::flat_vector::FlatVector;
 use crate::grimoire::vector::vector::Vector;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(not(feature = "std"))]
+use crate::grimoire::vector::once_lock::OnceLock;
 
 /// LOUDS-based trie structure.
 ///
@@ -44,17 +54,82 @@ pub struct LoudsTrie {
     num_l1_nodes: usize,
     /// Configuration.
     config: Config,
+    /// Pre-filter for lookups, built with `build_flags::LOOKUP_FILTER`.
+    lookup_filter: Option<LookupFilter>,
+    /// Sum of the key lengths, set by build or computed on first use.
+    total_key_bytes: OnceLock<usize>,
+    /// Cache hit and miss counters for profiling.
+    #[cfg(feature = "cache-stats")]
+    cache_counters: CacheCounters,
     /// Mapper for memory-mapped access.
     /// IMPORTANT: This field MUST be last in struct declaration.
     /// Rust drops fields in declaration order (top to bottom), so placing
     /// this last ensures all data structures are dropped before the Mapper,
     /// preventing dangling references to mmap'd memory.
     ///
-    /// Only file-backed `mmap()` needs to keep the Mapper alive, so this field
-    /// exists only with the `mmap` feature. `map()` borrows `&'static` data
-    /// that outlives the trie regardless.
-    #[cfg(feature = "mmap")]
-    mapper: Option<Mapper>,
+    /// Only file-backed `mmap()` and `map_arc()` need to keep the Mapper
+    /// alive. `map()` borrows `&'static` data that outlives the trie
+    /// regardless.
+    mapper: Option<Mapper<'static>>,
+}
+
+/// Path depth `reverse_lookup` records on the stack before spilling to the
+/// agent's history.
+const REVERSE_LOOKUP_INLINE_DEPTH: usize = 64;
+
+/// Cheap summary of the key set used to reject lookups before traversal.
+///
+/// Rust-specific: Not present in C++ version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LookupFilter {
+    /// Length of the shortest key.
+    min_length: usize,
+    /// Length of the longest key.
+    max_length: usize,
+    /// Bitmap of the bytes that start a key.
+    first_bytes: [u64; 4],
+}
+
+impl LookupFilter {
+    /// Summarizes the keys of `keyset`.
+    fn build(keyset: &crate::keyset::Keyset<'_>) -> Self {
+        let mut filter = LookupFilter {
+            min_length: usize::MAX,
+            max_length: 0,
+            first_bytes: [0; 4],
+        };
+        for key in keyset.iter() {
+            let bytes = key.as_bytes();
+            filter.min_length = filter.min_length.min(bytes.len());
+            filter.max_length = filter.max_length.max(bytes.len());
+            if let Some(&first) = bytes.first() {
+                filter.first_bytes[usize::from(first / 64)] |= 1 << (first % 64);
+            }
+        }
+        filter
+    }
+
+    /// Returns false if no key can equal `query`.
+    #[inline]
+    fn may_contain(&self, query: &[u8]) -> bool {
+        if query.len() < self.min_length || query.len() > self.max_length {
+            return false;
+        }
+        match query.first() {
+            Some(&first) => self.first_bytes[usize::from(first / 64)] & (1 << (first % 64)) != 0,
+            None => true,
+        }
+    }
+}
+
+/// Cache hit and miss counts, updated on every cache probe during search.
+///
+/// Rust-specific: Not present in C++ version.
+#[cfg(feature = "cache-stats")]
+#[derive(Debug, Default)]
+struct CacheCounters {
+    hits: core::sync::atomic::AtomicU64,
+    misses: core::sync::atomic::AtomicU64,
 }
 
 impl Default for LoudsTrie {
@@ -78,7 +153,10 @@ impl LoudsTrie {
             cache_mask: 0,
             num_l1_nodes: 0,
             config: Config::new(),
-            #[cfg(feature = "mmap")]
+            lookup_filter: None,
+            total_key_bytes: OnceLock::new(),
+            #[cfg(feature = "cache-stats")]
+            cache_counters: CacheCounters::default(),
             mapper: None,
         }
     }
@@ -93,6 +171,27 @@ impl LoudsTrie {
         self.size()
     }
 
+    /// Returns the sum of the lengths of all keys, in bytes.
+    ///
+    /// Each distinct key counts once. The value is recorded by build and
+    /// is not part of the C++-compatible file format, so a read or mapped
+    /// trie computes it by enumerating its keys on the first call.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn total_key_bytes(&self) -> usize {
+        *self.total_key_bytes.get_or_init(|| {
+            let mut agent = crate::agent::Agent::new();
+            agent
+                .init_state()
+                .expect("Failed to initialize agent state");
+            let mut total = 0;
+            while self.predictive_search(&mut agent) {
+                total += agent.key().length();
+            }
+            total
+        })
+    }
+
     /// Returns the number of nodes in the trie.
     pub fn num_nodes(&self) -> usize {
         (self.louds.size() / 2).saturating_sub(1)
@@ -111,6 +210,10 @@ impl LoudsTrie {
     }
 
     /// Returns the cache level configuration.
+    ///
+    /// The level is not part of the dictionary file (as in C++), so after
+    /// `read` or `map` this reports the default level. The cache itself is
+    /// stored and keeps the size it was built with.
     pub fn cache_level(&self) -> CacheLevel {
         self.config.cache_level()
     }
@@ -145,13 +248,126 @@ impl LoudsTrie {
             + self.tail.total_size()
             + self.next_trie.as_ref().map_or(0, |t| t.total_size())
             + self.cache.total_size()
-            + std::mem::size_of::<Self>()
+            + core::mem::size_of::<Self>()
+    }
+
+    /// Returns the part of [`total_size`](Self::total_size) borrowed from
+    /// mapped memory rather than allocated on the heap.
+    pub fn mapped_size(&self) -> usize {
+        self.louds.mapped_size()
+            + self.terminal_flags.mapped_size()
+            + self.link_flags.mapped_size()
+            + self.bases.mapped_size()
+            + self.extras.mapped_size()
+            + self.tail.mapped_size()
+            + self.next_trie.as_ref().map_or(0, |t| t.mapped_size())
+            + self.cache.mapped_size()
+    }
+
+    /// Returns the heap bytes reserved by this trie and its next tries,
+    /// including unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn allocated_size(&self) -> usize {
+        self.louds.allocated_size()
+            + self.terminal_flags.allocated_size()
+            + self.link_flags.allocated_size()
+            + self.bases.allocated_size()
+            + self.extras.allocated_size()
+            + self.tail.allocated_size()
+            + self.next_trie.as_ref().map_or(0, |t| t.allocated_size())
+            + self.cache.allocated_size()
+            + core::mem::size_of::<Self>()
+    }
+
+    /// Releases unused capacity in this trie and its next tries.
+    ///
+    /// The cache keeps its power-of-two size, which `cache_mask` relies
+    /// on, so only capacity beyond that size is released.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn shrink_to_fit(&mut self) {
+        self.louds.shrink_to_fit();
+        self.terminal_flags.shrink_to_fit();
+        self.link_flags.shrink_to_fit();
+        self.bases.shrink_unfixed();
+        self.extras.shrink_to_fit();
+        self.tail.shrink_to_fit();
+        if let Some(next_trie) = self.next_trie.as_mut() {
+            next_trie.shrink_to_fit();
+        }
+        self.cache.shrink_unfixed();
+        self.cache_mask = self.cache.size().saturating_sub(1);
+    }
+
+    /// Checks the structural invariants of this trie and its next tries.
+    ///
+    /// Rust-specific. Intended for data obtained through `read` or `map`,
+    /// which only check the header and vector sizes.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidData` naming the first invariant that does not hold.
+    pub fn validate(&self) -> io::Result<()> {
+        fn invalid(message: &str) -> io::Error {
+            io::Error::new(io::ErrorKind::InvalidData, message)
+        }
+
+        let num_nodes = self.louds.num_1s();
+        if !self.louds.is_consistent() {
+            return Err(invalid("LOUDS bit count does not match its bits"));
+        }
+        if self.louds.size() != 2 * (num_nodes + 1) {
+            return Err(invalid("LOUDS node counts are unbalanced"));
+        }
+        if !self.terminal_flags.is_consistent() {
+            return Err(invalid("terminal flag count does not match its bits"));
+        }
+        if !self.link_flags.is_consistent() {
+            return Err(invalid("link flag count does not match its bits"));
+        }
+        if self.bases.size() != num_nodes || self.link_flags.size() != num_nodes {
+            return Err(invalid("node arrays do not match the LOUDS node count"));
+        }
+        if self.extras.size() != self.link_flags.num_1s() {
+            return Err(invalid("extras size does not match the number of links"));
+        }
+        if self.num_l1_nodes > num_nodes {
+            return Err(invalid("num_l1_nodes exceeds the number of nodes"));
+        }
+        if !self.cache.size().is_power_of_two() {
+            return Err(invalid("cache size is not a power of two"));
+        }
+        match self.next_trie {
+            Some(ref next) => next.validate(),
+            None if self.link_flags.num_1s() != 0 && self.tail.empty() => {
+                Err(invalid("links have neither a tail nor a next trie"))
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the size of each component to `stats`, recursing into the
+    /// next trie.
+    pub(crate) fn add_stats(&self, stats: &mut crate::trie::TrieStats) {
+        stats.louds += self.louds.total_size();
+        stats.terminal_flags += self.terminal_flags.total_size();
+        stats.link_flags += self.link_flags.total_size();
+        stats.bases += self.bases.total_size();
+        stats.extras += self.extras.total_size();
+        stats.tail += self.tail.total_size();
+        stats.cache += self.cache.total_size();
+        stats.overhead += core::mem::size_of::<Self>();
+        match self.next_trie {
+            Some(ref next) => next.add_stats(stats),
+            None => stats.tail_compression_ratio = self.tail.compression_ratio(),
+        }
     }
 
     /// Returns the I/O size in bytes.
     pub fn io_size(&self) -> usize {
         use crate::grimoire::trie::header::Header;
-        use std::mem::size_of;
+        use core::mem::size_of;
 
         let mut size = Header::new().io_size()
             + self.louds.io_size()
@@ -179,7 +395,7 @@ impl LoudsTrie {
 
     /// Swaps with another trie.
     pub fn swap(&mut self, other: &mut LoudsTrie) {
-        std::mem::swap(self, other);
+        core::mem::swap(self, other);
     }
 
     /// Builds the trie from a keyset.
@@ -188,19 +404,79 @@ impl LoudsTrie {
     ///
     /// * `keyset` - Mutable keyset containing keys to build from
     /// * `flags` - Configuration flags
-    pub fn build(&mut self, keyset: &mut crate::keyset::Keyset, flags: i32) {
+    pub fn build(&mut self, keyset: &mut crate::keyset::Keyset<'_>, flags: i32) {
+        use crate::grimoire::trie::config::Config;
+
+        let mut config = Config::new();
+        config.parse(flags);
+
+        let mut temp = LoudsTrie::new();
+        temp.build_(keyset, &config, false, &mut |_| {});
+        self.swap(&mut temp);
+    }
+
+    /// Builds the trie from a keyset, reporting each build phase.
+    ///
+    /// Identical to [`build`](Self::build) except that `progress` is
+    /// called as each phase begins; see [`BuildPhase`] for the order.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyset` - Mutable keyset containing keys to build from
+    /// * `flags` - Configuration flags
+    /// * `progress` - Called with each phase as it begins
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn build_with_progress(
+        &mut self,
+        keyset: &mut crate::keyset::Keyset<'_>,
+        flags: i32,
+        progress: &mut dyn FnMut(BuildPhase),
+    ) {
+        use crate::grimoire::trie::config::Config;
+
+        let mut config = Config::new();
+        config.parse(flags);
+
+        let mut temp = LoudsTrie::new();
+        temp.build_(keyset, &config, false, progress);
+        self.swap(&mut temp);
+    }
+
+    /// Builds the trie from a keyset whose keys are already in byte order.
+    ///
+    /// Identical to [`build`](Self::build) except that the top-level sort
+    /// is replaced by a linear pass counting unique keys. Sortedness is
+    /// only checked in debug builds.
+    ///
+    /// # Arguments
+    ///
+    /// * `keyset` - Mutable keyset containing keys in ascending byte order
+    /// * `flags` - Configuration flags
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn build_sorted(&mut self, keyset: &mut crate::keyset::Keyset<'_>, flags: i32) {
         use crate::grimoire::trie::config::Config;
 
         let mut config = Config::new();
         config.parse(flags);
 
         let mut temp = LoudsTrie::new();
-        temp.build_(keyset, &config);
+        temp.build_(keyset, &config, true, &mut |_| {});
         self.swap(&mut temp);
     }
 
     /// Internal build implementation.
-    fn build_(&mut self, keyset: &mut crate::keyset::Keyset, config: &Config) {
+    ///
+    /// `presorted` skips sorting the top-level keys. `progress` is called
+    /// as each phase begins.
+    fn build_(
+        &mut self,
+        keyset: &mut crate::keyset::Keyset<'_>,
+        config: &Config,
+        presorted: bool,
+        progress: &mut dyn FnMut(BuildPhase),
+    ) {
         use crate::grimoire::trie::key::Key;
         use crate::grimoire::vector::vector::Vector;
 
@@ -215,7 +491,10 @@ impl LoudsTrie {
 
         // Build the trie structure
         let mut terminals: Vector<u32> = Vector::new();
-        self.build_trie_key(&mut keys, &mut terminals, config, 1);
+        self.build_trie_key(&mut keys, &mut terminals, config, 1, presorted, progress);
+        if config.lookup_filter() {
+            self.lookup_filter = Some(LookupFilter::build(keyset));
+        }
 
         // Build terminal flags from sorted terminal positions
         // Pairs of (node_id, original_index)
@@ -242,13 +521,28 @@ impl LoudsTrie {
             node_id += 1;
         }
         self.terminal_flags.push_back(false);
-        self.terminal_flags.build(false, true);
+        if config.lazy_select() {
+            self.terminal_flags.build_lazy(false, true);
+        } else {
+            self.terminal_flags.build(false, true);
+        }
 
         // Update keyset with final key IDs
         for &(terminal_node, original_idx) in &pairs {
             let key_id = self.terminal_flags.rank1(terminal_node as usize);
             keyset.get_mut(original_idx as usize).set_id(key_id);
         }
+
+        // Duplicates share an ID, so count each ID's length once
+        let mut counted = alloc::vec![false; self.terminal_flags.num_1s()];
+        let mut total_key_bytes = 0;
+        for i in 0..keyset.size() {
+            let key = keyset.get(i);
+            if !core::mem::replace(&mut counted[key.id()], true) {
+                total_key_bytes += key.length();
+            }
+        }
+        self.total_key_bytes = OnceLock::from(total_key_bytes);
     }
 
     /// Builds a trie level with Key type.
@@ -258,19 +552,23 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        presorted: bool,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
-        self.build_current_trie_key(keys, terminals, config, trie_id);
+        progress(BuildPhase::BuildingTrie { trie_id });
+        self.build_current_trie_key(keys, terminals, config, trie_id, presorted, progress);
 
         let mut next_terminals: Vector<u32> = Vector::new();
         if !keys.empty() {
-            self.build_next_trie_key(keys, &mut next_terminals, config, trie_id);
+            self.build_next_trie_key(keys, &mut next_terminals, config, trie_id, progress);
         }
 
         // Configure based on what was built
         if let Some(next) = &self.next_trie {
             let flags = ((next.num_tries() + 1) as i32)
                 | (next.tail_mode() as i32)
-                | (next.node_order() as i32);
+                | (next.node_order() as i32)
+                | (config.cache_level() as i32);
             self.config.parse(flags);
         } else {
             let flags = 1
@@ -295,6 +593,7 @@ impl LoudsTrie {
         }
         self.extras.build(&next_terminals);
 
+        progress(BuildPhase::FillingCache);
         self.fill_cache();
     }
 
@@ -305,18 +604,27 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        presorted: bool,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
         use crate::grimoire::algorithm::sort;
         use crate::grimoire::trie::range::{make_range, make_weighted_range, Range, WeightedRange};
-        use std::collections::VecDeque;
+        use alloc::collections::VecDeque;
 
         // Set IDs for sorting
         for i in 0..keys.size() {
             keys[i].set_id(i);
         }
 
-        // Sort keys
-        let num_keys = {
+        // Sort keys, or just count them if the caller vouches for the order
+        if !presorted {
+            progress(BuildPhase::Sorting);
+        }
+        let num_keys = if presorted {
+            sort::count_sorted(keys.as_slice())
+        } else if config.stable_sort() {
+            sort::sort_stable(keys.as_mut_slice())
+        } else {
             let key_slice = keys.as_mut_slice();
             sort::sort(key_slice)
         };
@@ -331,9 +639,9 @@ impl LoudsTrie {
         let mut queue: VecDeque<Range> = VecDeque::new();
         let mut w_ranges: Vec<WeightedRange> = Vec::new();
 
-        // Store raw pointers to avoid borrow checker issues
-        // The actual data lives in the original keyset (lifetime 'a)
-        let mut next_key_data: Vec<(*const [u8], f32)> = Vec::new(); // (ptr to bytes, weight)
+        // Substrings for the next level as (key index, start, length, weight),
+        // resolved against `keys` once this level is done with them
+        let mut next_key_data: Vec<(usize, usize, usize, f32)> = Vec::new();
 
         queue.push_back(make_range(0, keys.size(), 0));
 
@@ -380,10 +688,28 @@ impl LoudsTrie {
                 range.key_pos(),
                 weight as f32,
             ));
+            if config.deterministic_weights() {
+                for w_range in &mut w_ranges {
+                    w_range.set_weight(order_independent_sum(
+                        (w_range.begin()..w_range.end()).map(|j| keys[j].weight()),
+                    ));
+                }
+            }
 
-            // Sort by weight if configured
-            if config.node_order() == crate::base::NodeOrder::Weight {
-                w_ranges.sort_by(|a, b| b.cmp(a)); // Descending order
+            // Sort by weight or insertion order if configured
+            match config.node_order() {
+                crate::base::NodeOrder::Weight if config.deterministic_weights() => {
+                    // Stable, so equal weights keep ascending label order
+                    w_ranges.sort_by(|a, b| b.weight().total_cmp(&a.weight()));
+                }
+                crate::base::NodeOrder::Weight => {
+                    w_ranges.sort_by(|a, b| b.cmp(a)); // Descending order
+                }
+                crate::base::NodeOrder::None => {
+                    // Earliest-inserted key in each group comes first
+                    w_ranges.sort_by_key(|w| (w.begin()..w.end()).map(|j| keys[j].id()).min());
+                }
+                crate::base::NodeOrder::Label => {}
             }
 
             // Track level 1 node count
@@ -421,18 +747,13 @@ impl LoudsTrie {
                     self.bases.push_back(label);
                     self.link_flags.push_back(false);
                 } else {
-                    // Multi-character - store pointer for creating next_keys later
+                    // Multi-character - remember the substring for next_keys
                     self.bases.push_back(0);
                     self.link_flags.push_back(true);
 
                     let start = w_range.key_pos();
                     let len = key_pos - w_range.key_pos();
-                    let key_bytes = keys[w_range.begin()].as_bytes();
-                    let substring = &key_bytes[start..start + len];
-                    // Store raw pointer to avoid borrow checker issues
-                    // SAFETY: The slice is valid for lifetime 'a (from original keyset)
-                    let ptr: *const [u8] = substring as *const [u8];
-                    next_key_data.push((ptr, w_range.weight()));
+                    next_key_data.push((w_range.begin(), start, len, w_range.weight()));
                 }
 
                 w_range.set_key_pos(key_pos);
@@ -443,21 +764,28 @@ impl LoudsTrie {
         }
 
         self.louds.push_back(false);
-        self.louds.build(trie_id == 1, true);
+        if config.lazy_select() {
+            self.louds.build_lazy(trie_id == 1, true);
+        } else {
+            self.louds.build(trie_id == 1, true);
+        }
         self.bases.shrink();
 
         self.build_terminals_key(keys, terminals);
 
-        // Now clear keys and populate with new Keys from stored pointers
+        // Replace keys with the substrings that continue in the next level
+        let next_keys: Vec<Key<'a>> = next_key_data
+            .into_iter()
+            .map(|(key_id, start, len, weight)| {
+                let mut next_key = Key::new();
+                next_key.set_str(keys[key_id].as_bytes());
+                next_key.substr(start, len);
+                next_key.set_weight(weight);
+                next_key
+            })
+            .collect();
         keys.clear();
-        for (ptr, weight) in next_key_data {
-            // SAFETY: The pointer is valid for lifetime 'a (from original keyset)
-            // and keys vector lifetime doesn't affect the validity of the original data
-            let substring: &[u8] = unsafe { &*ptr };
-
-            let mut next_key = Key::new();
-            next_key.set_str(substring);
-            next_key.set_weight(weight);
+        for next_key in next_keys {
             keys.push_back(next_key);
         }
     }
@@ -469,42 +797,37 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
         use crate::grimoire::trie::entry::Entry;
         use crate::grimoire::trie::key::ReverseKey;
 
         if trie_id == config.num_tries() {
             // Build tail storage
+            progress(BuildPhase::BuildingTail);
             let mut entries: Vector<Entry<'_>> = Vector::new();
             entries.resize(keys.size(), Entry::new());
             for i in 0..keys.size() {
                 entries[i].set_str(keys[i].as_bytes());
             }
-            self.tail.build(&mut entries, terminals, config.tail_mode());
+            if config.no_tail_merge() {
+                self.tail
+                    .build_unmerged(&mut entries, terminals, config.tail_mode());
+            } else {
+                self.tail.build(&mut entries, terminals, config.tail_mode());
+            }
             return;
         }
 
         // Build next trie level with reversed keys
-        // Collect data using raw pointers to avoid borrow checker issues
-        let reverse_key_data: Vec<(*const [u8], f32)> = (0..keys.size())
-            .map(|i| {
-                let bytes = keys[i].as_bytes();
-                let ptr: *const [u8] = bytes as *const [u8];
-                (ptr, keys[i].weight())
-            })
-            .collect();
-
-        keys.clear();
-
-        let mut reverse_keys: Vector<ReverseKey<'_>> = Vector::new();
-        for (ptr, weight) in reverse_key_data {
-            // SAFETY: The pointer is valid for lifetime 'a (from original keyset)
-            let bytes: &[u8] = unsafe { &*ptr };
+        let mut reverse_keys: Vector<ReverseKey<'a>> = Vector::new();
+        for i in 0..keys.size() {
             let mut rev_key = ReverseKey::new();
-            rev_key.set_str(bytes);
-            rev_key.set_weight(weight);
+            rev_key.set_str(keys[i].as_bytes());
+            rev_key.set_weight(keys[i].weight());
             reverse_keys.push_back(rev_key);
         }
+        keys.clear();
 
         self.next_trie = Some(Box::new(LoudsTrie::new()));
         self.next_trie.as_mut().unwrap().build_trie_reverse(
@@ -512,6 +835,7 @@ impl LoudsTrie {
             terminals,
             config,
             trie_id + 1,
+            progress,
         );
     }
 
@@ -522,19 +846,22 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
-        self.build_current_trie_reverse(keys, terminals, config, trie_id);
+        progress(BuildPhase::BuildingTrie { trie_id });
+        self.build_current_trie_reverse(keys, terminals, config, trie_id, progress);
 
         let mut next_terminals: Vector<u32> = Vector::new();
         if !keys.empty() {
-            self.build_next_trie_reverse(keys, &mut next_terminals, config, trie_id);
+            self.build_next_trie_reverse(keys, &mut next_terminals, config, trie_id, progress);
         }
 
         // Configure based on what was built
         if let Some(next) = &self.next_trie {
             let flags = ((next.num_tries() + 1) as i32)
                 | (next.tail_mode() as i32)
-                | (next.node_order() as i32);
+                | (next.node_order() as i32)
+                | (config.cache_level() as i32);
             self.config.parse(flags);
         } else {
             let flags = 1
@@ -559,6 +886,7 @@ impl LoudsTrie {
         }
         self.extras.build(&next_terminals);
 
+        progress(BuildPhase::FillingCache);
         self.fill_cache();
     }
 
@@ -569,10 +897,11 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
         use crate::grimoire::algorithm::sort;
         use crate::grimoire::trie::range::{make_range, make_weighted_range, Range, WeightedRange};
-        use std::collections::VecDeque;
+        use alloc::collections::VecDeque;
 
         // Set IDs for sorting
         for i in 0..keys.size() {
@@ -580,7 +909,10 @@ impl LoudsTrie {
         }
 
         // Sort keys
-        let num_keys = {
+        progress(BuildPhase::Sorting);
+        let num_keys = if config.stable_sort() {
+            sort::sort_stable(keys.as_mut_slice())
+        } else {
             let key_slice = keys.as_mut_slice();
             sort::sort(key_slice)
         };
@@ -592,8 +924,9 @@ impl LoudsTrie {
         self.bases.push_back(0);
         self.link_flags.push_back(false);
 
-        // Store raw pointers to avoid borrow checker issues
-        let mut next_key_data: Vec<(*const [u8], f32)> = Vec::new();
+        // Substrings for the next level as (key index, start, length, weight),
+        // resolved against `keys` once this level is done with them
+        let mut next_key_data: Vec<(usize, usize, usize, f32)> = Vec::new();
 
         let mut queue: VecDeque<Range> = VecDeque::new();
         let mut w_ranges: Vec<WeightedRange> = Vec::new();
@@ -643,10 +976,28 @@ impl LoudsTrie {
                 range.key_pos(),
                 weight as f32,
             ));
+            if config.deterministic_weights() {
+                for w_range in &mut w_ranges {
+                    w_range.set_weight(order_independent_sum(
+                        (w_range.begin()..w_range.end()).map(|j| keys[j].weight()),
+                    ));
+                }
+            }
 
-            // Sort by weight if configured
-            if config.node_order() == crate::base::NodeOrder::Weight {
-                w_ranges.sort_by(|a, b| b.cmp(a)); // Descending order
+            // Sort by weight or insertion order if configured
+            match config.node_order() {
+                crate::base::NodeOrder::Weight if config.deterministic_weights() => {
+                    // Stable, so equal weights keep ascending label order
+                    w_ranges.sort_by(|a, b| b.weight().total_cmp(&a.weight()));
+                }
+                crate::base::NodeOrder::Weight => {
+                    w_ranges.sort_by(|a, b| b.cmp(a)); // Descending order
+                }
+                crate::base::NodeOrder::None => {
+                    // Earliest-inserted key in each group comes first
+                    w_ranges.sort_by_key(|w| (w.begin()..w.end()).map(|j| keys[j].id()).min());
+                }
+                crate::base::NodeOrder::Label => {}
             }
 
             // Track level 1 node count
@@ -684,24 +1035,15 @@ impl LoudsTrie {
                     self.bases.push_back(label);
                     self.link_flags.push_back(false);
                 } else {
-                    // Multi-character - store pointer for creating next_keys later
+                    // Multi-character - remember the substring for next_keys.
+                    // start/len are in reversed access order, which is what
+                    // ReverseKey::substr takes.
                     self.bases.push_back(0);
                     self.link_flags.push_back(true);
 
                     let start = w_range.key_pos();
                     let len = key_pos - w_range.key_pos();
-                    let key_bytes = keys[w_range.begin()].as_bytes();
-                    // For ReverseKey: as_bytes() returns forward bytes, but start/len
-                    // are indices in the reversed access order. We need to convert
-                    // reverse indices to forward indices.
-                    // If ReverseKey("banana") and start=3, len=3, we want "ban" not "ana"
-                    let forward_start = key_bytes.len() - start - len;
-                    let forward_end = key_bytes.len() - start;
-                    let substring = &key_bytes[forward_start..forward_end];
-                    // Store raw pointer to avoid borrow checker issues
-                    // SAFETY: The slice is valid for lifetime 'a (from original keyset)
-                    let ptr: *const [u8] = substring as *const [u8];
-                    next_key_data.push((ptr, w_range.weight()));
+                    next_key_data.push((w_range.begin(), start, len, w_range.weight()));
                 }
 
                 w_range.set_key_pos(key_pos);
@@ -712,20 +1054,28 @@ impl LoudsTrie {
         }
 
         self.louds.push_back(false);
-        self.louds.build(trie_id == 1, true);
+        if config.lazy_select() {
+            self.louds.build_lazy(trie_id == 1, true);
+        } else {
+            self.louds.build(trie_id == 1, true);
+        }
         self.bases.shrink();
 
         self.build_terminals_reverse(keys, terminals);
 
-        // Now clear keys and populate with new Keys from stored pointers
+        // Replace keys with the substrings that continue in the next level
+        let next_keys: Vec<ReverseKey<'a>> = next_key_data
+            .into_iter()
+            .map(|(key_id, start, len, weight)| {
+                let mut next_key = ReverseKey::new();
+                next_key.set_str(keys[key_id].as_bytes());
+                next_key.substr(start, len);
+                next_key.set_weight(weight);
+                next_key
+            })
+            .collect();
         keys.clear();
-        for (ptr, weight) in next_key_data {
-            // SAFETY: The pointer is valid for lifetime 'a (from original keyset)
-            let substring: &[u8] = unsafe { &*ptr };
-
-            let mut next_key = ReverseKey::new();
-            next_key.set_str(substring);
-            next_key.set_weight(weight);
+        for next_key in next_keys {
             keys.push_back(next_key);
         }
     }
@@ -737,26 +1087,36 @@ impl LoudsTrie {
         terminals: &mut Vector<u32>,
         config: &Config,
         trie_id: usize,
+        progress: &mut dyn FnMut(BuildPhase),
     ) {
         use crate::grimoire::trie::entry::Entry;
 
         if trie_id == config.num_tries() {
             // Build tail storage
+            progress(BuildPhase::BuildingTail);
             let mut entries: Vector<Entry<'_>> = Vector::new();
             entries.resize(keys.size(), Entry::new());
             for i in 0..keys.size() {
                 entries[i].set_str(keys[i].as_bytes());
             }
-            self.tail.build(&mut entries, terminals, config.tail_mode());
+            if config.no_tail_merge() {
+                self.tail
+                    .build_unmerged(&mut entries, terminals, config.tail_mode());
+            } else {
+                self.tail.build(&mut entries, terminals, config.tail_mode());
+            }
             return;
         }
 
         // Build next trie level (shouldn't happen for reverse keys in practice)
         self.next_trie = Some(Box::new(LoudsTrie::new()));
-        self.next_trie
-            .as_mut()
-            .unwrap()
-            .build_trie_reverse(keys, terminals, config, trie_id + 1);
+        self.next_trie.as_mut().unwrap().build_trie_reverse(
+            keys,
+            terminals,
+            config,
+            trie_id + 1,
+            progress,
+        );
     }
 
     /// Collects terminal positions from reverse keys.
@@ -854,7 +1214,7 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if mapping fails or data is invalid.
-    pub fn map(&mut self, data: &'static [u8]) -> std::io::Result<()> {
+    pub fn map(&mut self, data: &'static [u8]) -> io::Result<()> {
         let mut mapper = Mapper::open_memory(data);
         use crate::grimoire::trie::header::Header;
         Header::new().map(&mut mapper)?;
@@ -862,6 +1222,35 @@ impl LoudsTrie {
         Ok(())
     }
 
+    /// Maps the trie from a shared buffer without copying it.
+    ///
+    /// Rust-specific. The trie keeps a handle to `data`, so the mapped
+    /// components stay valid for the trie's lifetime without `data` being
+    /// `'static`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mapping fails or data is invalid.
+    pub fn map_arc(&mut self, data: Arc<[u8]>) -> io::Result<()> {
+        self.map_owned(Mapper::open_arc(data))
+    }
+
+    /// Maps the trie from a borrowed byte slice of any lifetime.
+    ///
+    /// Rust-specific. The vectors are copied out of `data`, so the trie does
+    /// not borrow it; use [`map`](Self::map) with `'static` data to avoid
+    /// the copy.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if mapping fails or data is invalid.
+    pub fn map_bytes(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut mapper = Mapper::open_borrowed(data);
+        use crate::grimoire::trie::header::Header;
+        Header::new().map(&mut mapper)?;
+        self.map_internal(&mut mapper)
+    }
+
     /// Maps the trie from a file using memory mapping.
     ///
     /// # Arguments
@@ -874,18 +1263,55 @@ impl LoudsTrie {
     ///
     /// Requires the `mmap` feature (enabled by default; unavailable on WASM).
     #[cfg(feature = "mmap")]
-    pub fn mmap(&mut self, filename: &str) -> std::io::Result<()> {
-        let mut mapper = Mapper::open_file(filename)?;
+    pub fn mmap(&mut self, filename: &str) -> io::Result<()> {
+        self.map_owned(Mapper::open_file(filename)?)
+    }
+
+    /// Maps the trie from a mapper that owns its memory, then keeps it.
+    ///
+    /// The trie is mapped into a temporary and only replaces `self` on
+    /// success. On error the partly mapped temporary is dropped before the
+    /// mapper, so no component is left pointing into freed memory.
+    fn map_owned(&mut self, mut mapper: Mapper<'static>) -> io::Result<()> {
         use crate::grimoire::trie::header::Header;
+        // SAFETY: the mapper is either stored in `temp` below, after every
+        // component mapped from it, or outlives `temp` on error.
+        unsafe { mapper.share() };
+        let mut temp = LoudsTrie::new();
         Header::new().map(&mut mapper)?;
-        self.map_internal(&mut mapper)?;
-        // CRITICAL: Keep mapper alive to keep mmap'd memory valid
-        self.mapper = Some(mapper);
+        temp.map_internal(&mut mapper)?;
+        // CRITICAL: Keep mapper alive to keep mapped memory valid
+        temp.mapper = Some(mapper);
+        self.swap(&mut temp);
         Ok(())
     }
 
+    /// Tells the OS how the mapped file will be accessed.
+    ///
+    /// A no-op unless the trie was mapped with [`mmap`](Self::mmap) on
+    /// Unix.
+    ///
+    /// # Arguments
+    ///
+    /// * `hint` - Expected access pattern
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the OS rejects the hint.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn advise(&self, hint: Advice) -> io::Result<()> {
+        match self.mapper {
+            Some(ref mapper) => mapper.advise(hint),
+            None => Ok(()),
+        }
+    }
+
     /// Internal map implementation (without header).
     ///
+    /// Every component, including the `extras` FlatVector and each nested
+    /// trie, is mapped through the same `mapper` in file order.
+    ///
     /// Format (matching read_internal):
     /// - louds: BitVector
     /// - terminal_flags: BitVector
@@ -905,7 +1331,7 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if mapping fails.
-    fn map_internal(&mut self, mapper: &mut Mapper) -> std::io::Result<()> {
+    fn map_internal(&mut self, mapper: &mut Mapper<'_>) -> io::Result<()> {
         // Map all component data structures
         self.louds.map(mapper)?;
         self.terminal_flags.map(mapper)?;
@@ -926,11 +1352,11 @@ impl LoudsTrie {
         self.cache_mask = self.cache.size().saturating_sub(1);
 
         // Map num_l1_nodes
-        let temp_num_l1_nodes: u32 = mapper.map_value()?;
+        let temp_num_l1_nodes = mapper.map_u32()?;
         self.num_l1_nodes = temp_num_l1_nodes as usize;
 
         // Map and parse config flags
-        let temp_config_flags: u32 = mapper.map_value()?;
+        let temp_config_flags = mapper.map_u32()?;
         self.config.parse(temp_config_flags as i32);
 
         Ok(())
@@ -945,7 +1371,8 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if reading fails or header is invalid
-    pub fn read(&mut self, reader: &mut Reader<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn read(&mut self, reader: &mut Reader<'_>) -> io::Result<()> {
         use crate::grimoire::trie::header::Header;
         Header::new().read(reader)?;
         self.read_internal(reader)
@@ -960,12 +1387,97 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if writing fails
-    pub fn write(&self, writer: &mut Writer<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut Writer<'_>) -> io::Result<()> {
         use crate::grimoire::trie::header::Header;
         Header::new().write(writer)?;
         self.write_internal(writer)
     }
 
+    /// Returns an owned deep copy made by writing the trie to memory and
+    /// reading it back.
+    ///
+    /// The copy never borrows mapped memory. The lookup filter is not part
+    /// of the serialized form, so it is copied over directly.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    #[cfg(feature = "std")]
+    pub(crate) fn reserialize(&self) -> LoudsTrie {
+        let mut writer = Writer::from_vec(Vec::with_capacity(self.io_size()));
+        self.write(&mut writer)
+            .expect("writing to memory cannot fail");
+        let data = writer.into_inner().expect("writer owns a buffer");
+
+        let mut copy = LoudsTrie::new();
+        copy.read(&mut Reader::from_bytes(&data))
+            .expect("a freshly written trie reads back");
+        copy.lookup_filter = self.lookup_filter;
+        if let Some(&total_key_bytes) = self.total_key_bytes.get() {
+            copy.total_key_bytes = OnceLock::from(total_key_bytes);
+        }
+        copy
+    }
+
+    /// Writes the trie followed by a CRC-32 checksum trailer.
+    ///
+    /// Rust-specific: see [`Checksum`](crate::grimoire::trie::header::Checksum).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing fails
+    #[cfg(feature = "std")]
+    pub fn write_with_checksum(&self, writer: &mut Writer<'_>) -> io::Result<()> {
+        use crate::grimoire::io::crc32::crc32;
+        use crate::grimoire::trie::header::Checksum;
+
+        let mut buffer = Writer::from_vec(Vec::with_capacity(self.io_size()));
+        self.write(&mut buffer)?;
+        let bytes = buffer.into_inner()?;
+        writer.write_slice(&bytes)?;
+        writer.write_slice(&Checksum::encode(crc32(&bytes)))
+    }
+
+    /// Reads the trie and verifies a trailing CRC-32 checksum.
+    ///
+    /// Rust-specific. If `required` is false, a missing or unrecognized
+    /// trailer is accepted so that plain C++-format files still load; a
+    /// trailer that is present but does not match is always an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidData` if the checksum does not match, or if
+    /// `required` is true and no trailer follows the trie.
+    #[cfg(feature = "std")]
+    pub fn read_with_checksum(
+        &mut self,
+        reader: &mut Reader<'_>,
+        required: bool,
+    ) -> io::Result<()> {
+        use crate::grimoire::trie::header::{Checksum, CHECKSUM_SIZE};
+
+        reader.begin_crc32();
+        let result = self.read(reader);
+        let actual = reader.end_crc32().unwrap_or(0);
+        result?;
+
+        let mut trailer = [0u8; CHECKSUM_SIZE];
+        let expected = match reader.read_slice_or_eof(&mut trailer) {
+            Ok(true) => Checksum::decode(&trailer),
+            Ok(false) => None,
+            Err(e) if !required && e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e),
+        };
+
+        match expected {
+            Some(expected) => Checksum::verify(expected, actual),
+            None if required => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Missing checksum trailer",
+            )),
+            None => Ok(()),
+        }
+    }
+
     /// Reads the trie from a reader (internal version without header).
     ///
     /// Format:
@@ -987,7 +1499,8 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if reading fails
-    fn read_internal(&mut self, reader: &mut Reader<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    fn read_internal(&mut self, reader: &mut Reader<'_>) -> io::Result<()> {
         // Read all component data structures
         self.louds.read(reader)?;
         self.terminal_flags.read(reader)?;
@@ -1008,11 +1521,11 @@ impl LoudsTrie {
         self.cache_mask = self.cache.size().saturating_sub(1);
 
         // Read num_l1_nodes
-        let temp_num_l1_nodes: u32 = reader.read()?;
+        let temp_num_l1_nodes = reader.read_u32()?;
         self.num_l1_nodes = temp_num_l1_nodes as usize;
 
         // Read and parse config flags
-        let temp_config_flags: u32 = reader.read()?;
+        let temp_config_flags = reader.read_u32()?;
         self.config.parse(temp_config_flags as i32);
 
         Ok(())
@@ -1039,7 +1552,8 @@ impl LoudsTrie {
     /// # Errors
     ///
     /// Returns an error if writing fails
-    fn write_internal(&self, writer: &mut Writer<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    fn write_internal(&self, writer: &mut Writer<'_>) -> io::Result<()> {
         // Write all component data structures
         self.louds.write(writer)?;
         self.terminal_flags.write(writer)?;
@@ -1057,10 +1571,10 @@ impl LoudsTrie {
         self.cache.write(writer)?;
 
         // Write num_l1_nodes as u32
-        writer.write(&(self.num_l1_nodes as u32))?;
+        writer.write_u32(self.num_l1_nodes as u32)?;
 
         // Write config flags as u32
-        writer.write(&(self.config.flags() as u32))?;
+        writer.write_u32(self.config.flags() as u32)?;
 
         Ok(())
     }
@@ -1086,6 +1600,13 @@ impl LoudsTrie {
             state.lookup_init();
         }
 
+        // Reject queries no key can match without walking the trie
+        if let Some(ref filter) = self.lookup_filter {
+            if !filter.may_contain(agent.query().as_bytes()) {
+                return false;
+            }
+        }
+
         // Traverse trie following query
         let query_len = agent.query().length();
         while agent.state().expect("Agent must have state").query_pos() < query_len {
@@ -1108,60 +1629,238 @@ impl LoudsTrie {
         true
     }
 
-    /// Performs reverse lookup: finds the key corresponding to a key ID.
-    ///
-    /// Reconstructs the key string from its ID by traversing the trie.
-    ///
-    /// # Arguments
-    ///
-    /// * `agent` - Agent with initialized state and query containing key ID
+    /// Returns how many query bytes can be matched along a trie path.
     ///
-    /// # Panics
+    /// Walks children like [`lookup`](Self::lookup) but ignores terminal
+    /// flags, and stops at the first byte with no matching edge. Bytes
+    /// matched part-way into a multi-byte edge (tail or next trie) count.
     ///
-    /// Panics if agent doesn't have state or if key ID is out of range.
-    pub fn reverse_lookup(&self, agent: &mut crate::agent::Agent) {
+    /// Rust-specific: Not present in C++ version.
+    pub fn common_prefix_len(&self, agent: &mut crate::agent::Agent) -> usize {
         assert!(agent.has_state(), "Agent must have state initialized");
 
-        let key_id = agent.query().id();
-        assert!(key_id < self.size(), "Key ID out of range");
+        agent
+            .state_mut()
+            .expect("Agent must have state")
+            .lookup_init();
 
-        // Initialize for reverse lookup
-        {
-            let state = agent.state_mut().expect("Agent must have state");
-            state.reverse_lookup_init();
+        let query_len = agent.query().length();
+        while agent.state().expect("Agent must have state").query_pos() < query_len {
+            if !self.find_child(agent) {
+                break;
+            }
         }
+        agent.state().expect("Agent must have state").query_pos()
+    }
 
-        // Find the terminal node for this key ID
-        let node_id = self.terminal_flags.select1(key_id);
-        {
+    /// Walks the agent's query onward from a streaming cursor position.
+    ///
+    /// Starts at `node_id` with `query_pos` query bytes already matched and
+    /// follows whole edges while the query lasts. An edge the query ends
+    /// part-way through is left pending instead of failing, so the walk can
+    /// resume from the same node once more bytes are appended.
+    ///
+    /// # Returns
+    ///
+    /// The last node fully reached and the query position after it, or
+    /// `None` if a query byte matches no edge.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub(crate) fn advance_cursor(
+        &self,
+        agent: &mut crate::agent::Agent,
+        mut node_id: usize,
+        mut query_pos: usize,
+    ) -> Option<(usize, usize)> {
+        let query_len = agent.query().length();
+        while query_pos < query_len {
             let state = agent.state_mut().expect("Agent must have state");
             state.set_node_id(node_id);
+            state.set_query_pos(query_pos);
+            if !self.find_child(agent) {
+                // Running out of query inside a multi-byte edge is not a
+                // mismatch; every other failure stops short of the end.
+                let state = agent.state().expect("Agent must have state");
+                return (state.query_pos() == query_len).then_some((node_id, query_pos));
+            }
+            let state = agent.state().expect("Agent must have state");
+            node_id = state.node_id();
+            query_pos = state.query_pos();
         }
+        Some((node_id, query_pos))
+    }
 
-        // Handle root node case
-        if node_id == 0 {
-            agent.set_key_from_state_buf();
-            agent.set_key_id(key_id);
-            return;
-        }
+    /// Returns the key ID of `node_id` if a key ends there.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub(crate) fn terminal_key_id(&self, node_id: usize) -> Option<usize> {
+        self.terminal_flags
+            .get(node_id)
+            .then(|| self.terminal_flags.rank1(node_id))
+    }
 
-        // Traverse upward to root, building key in reverse
-        let mut current_node = node_id;
-        loop {
-            if self.link_flags.get(current_node) {
-                // Save position before restore
-                let prev_key_pos = agent
+    /// Counts the keys that start with the agent's query.
+    ///
+    /// Descends to the node reached by the query, as predictive search
+    /// does, then counts terminals in its subtree without enumerating keys.
+    /// In LOUDS order a subtree covers one contiguous node-ID range per
+    /// depth, so each depth costs two `rank1` calls on the terminal flags
+    /// and two `select0` calls to find the next depth's range.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn num_keys_with_prefix(&self, agent: &mut crate::agent::Agent) -> usize {
+        assert!(agent.has_state(), "Agent must have state initialized");
+
+        agent
+            .state_mut()
+            .expect("Agent must have state")
+            .predictive_search_init();
+        let query_len = agent.query().length();
+        while agent.state().expect("Agent must have state").query_pos() < query_len {
+            if !self.predictive_find_child(agent) {
+                return 0;
+            }
+        }
+
+        let node_id = agent.state().expect("Agent must have state").node_id();
+        let (mut begin, mut end) = (node_id, node_id + 1);
+        let mut count = 0;
+        while begin < end {
+            count += self.terminal_flags.rank1(end) - self.terminal_flags.rank1(begin);
+            // Children of nodes [begin, end) are [first_child(begin), first_child(end))
+            begin = self.louds.select0(begin) - begin;
+            end = self.louds.select0(end) - end;
+        }
+        count
+    }
+
+    /// Counts the nodes at each depth of this trie, the root at depth 0.
+    ///
+    /// As in [`num_keys_with_prefix`](Self::num_keys_with_prefix), the
+    /// nodes of one depth form a contiguous node-ID range, and the next
+    /// depth's range comes from two `select0` calls, so this costs
+    /// `O(depth)` rather than a walk over every node.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn node_count_by_depth(&self) -> Vec<usize> {
+        let mut counts = Vec::new();
+        let (mut begin, mut end) = (0, 1);
+        while begin < end {
+            counts.push(end - begin);
+            begin = self.louds.select0(begin) - begin;
+            end = self.louds.select0(end) - end;
+        }
+        counts
+    }
+
+    /// Checks that no key is a proper prefix of another key.
+    ///
+    /// Every leaf of the trie ends a key, so a key is a prefix of another
+    /// exactly when its terminal node has a child. One pass over the LOUDS
+    /// bits visits each node's child list: node `n`'s children start right
+    /// after the `n`-th 0 bit.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn is_prefix_free(&self) -> bool {
+        let num_nodes = self.terminal_flags.size();
+        let mut node_id = 0;
+        for pos in 0..self.louds.size() {
+            if self.louds.get(pos) {
+                continue;
+            }
+            if node_id >= num_nodes {
+                break;
+            }
+            let has_child = pos + 1 < self.louds.size() && self.louds.get(pos + 1);
+            if has_child && self.terminal_flags.get(node_id) {
+                return false;
+            }
+            node_id += 1;
+        }
+        true
+    }
+
+    /// Performs reverse lookup: finds the key corresponding to a key ID.
+    ///
+    /// Reconstructs the key string from its ID by traversing the trie.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - Agent with initialized state and query containing key ID
+    ///
+    /// # Panics
+    ///
+    /// Panics if agent doesn't have state or if key ID is out of range.
+    pub fn reverse_lookup(&self, agent: &mut crate::agent::Agent) {
+        use crate::grimoire::trie::history::History;
+
+        assert!(agent.has_state(), "Agent must have state initialized");
+
+        let key_id = agent.query().id();
+        assert!(
+            key_id < self.size(),
+            "Key ID {} out of range: trie has {} keys",
+            key_id,
+            self.size()
+        );
+
+        // Initialize for reverse lookup
+        {
+            let state = agent.state_mut().expect("Agent must have state");
+            state.reverse_lookup_init();
+        }
+
+        // Find the terminal node for this key ID
+        let node_id = self.terminal_flags.select1(key_id);
+        {
+            let state = agent.state_mut().expect("Agent must have state");
+            state.set_node_id(node_id);
+        }
+
+        // Handle root node case
+        if node_id == 0 {
+            agent.set_key_from_state_buf();
+            agent.set_key_id(key_id);
+            return;
+        }
+
+        // Walk up to a level-1 node, recording the path. Emitting labels and
+        // link strings root-first afterwards puts the key in order directly;
+        // building it leaf-first would need every link string reversed and
+        // then the whole buffer reversed again. The first nodes of the path
+        // go in a stack array so fresh agents need no extra allocation; any
+        // deeper ones spill to the history stack.
+        let mut path = [0u32; REVERSE_LOOKUP_INLINE_DEPTH];
+        let mut depth = 0;
+        let state = agent.state_mut().expect("Agent must have state");
+        state.history_mut().clear();
+        let mut current_node = node_id;
+        loop {
+            if depth < path.len() {
+                path[depth] = current_node as u32;
+            } else {
+                let mut history = History::new();
+                history.set_node_id(current_node);
+                state.push_history(history);
+            }
+            depth += 1;
+            if current_node <= self.num_l1_nodes {
+                break;
+            }
+            current_node = self.louds.select1(current_node) - current_node - 1;
+        }
+
+        for i in (0..depth).rev() {
+            let current_node = match path.get(i) {
+                Some(&node) => node as usize,
+                None => agent
                     .state()
                     .expect("Agent must have state")
-                    .key_buf()
-                    .len();
-
+                    .history_at(i - path.len())
+                    .node_id(),
+            };
+            if self.link_flags.get(current_node) {
                 self.restore(agent, self.get_link_simple(current_node));
-
-                // Reverse the newly added portion
-                let state = agent.state_mut().expect("Agent must have state");
-                let key_buf = state.key_buf_mut();
-                key_buf[prev_key_pos..].reverse();
             } else {
                 agent
                     .state_mut()
@@ -1169,24 +1868,10 @@ impl LoudsTrie {
                     .key_buf_mut()
                     .push(self.bases[current_node]);
             }
-
-            if current_node <= self.num_l1_nodes {
-                // Reverse entire key buffer
-                let state = agent.state_mut().expect("Agent must have state");
-                state.key_buf_mut().reverse();
-                let _ = state;
-
-                agent.set_key_from_state_buf();
-                agent.set_key_id(key_id);
-                return;
-            }
-
-            current_node = self.louds.select1(current_node) - current_node - 1;
-            agent
-                .state_mut()
-                .expect("Agent must have state")
-                .set_node_id(current_node);
         }
+
+        agent.set_key_from_state_buf();
+        agent.set_key_id(key_id);
     }
 
     /// Finds a child node matching the current query character.
@@ -1206,7 +1891,9 @@ impl LoudsTrie {
         // hit registers/stack instead of repeating the Vector bounds check.
         let cache_id = self.get_cache_id_with_label(node_id, query_char);
         let cache_entry = self.cache[cache_id];
-        if node_id == cache_entry.parent() {
+        let hit = node_id == cache_entry.parent();
+        self.count_cache_probe(hit);
+        if hit {
             use crate::base::INVALID_EXTRA;
             if cache_entry.extra() != INVALID_EXTRA as usize {
                 if !self.match_link(agent, cache_entry.link()) {
@@ -1281,6 +1968,11 @@ impl LoudsTrie {
     /// Finds all keys that are prefixes of the query string.
     /// Call repeatedly to get all matches.
     ///
+    /// Each match is reported once, shortest first: every step moves to a
+    /// child node and consumes at least one query byte, and only the node
+    /// just reached is checked for a terminal, so no node (and no key ID)
+    /// can be visited twice.
+    ///
     /// # Arguments
     ///
     /// * `agent` - Agent with initialized state and query
@@ -1364,10 +2056,52 @@ impl LoudsTrie {
     ///
     /// Panics if agent doesn't have state initialized.
     pub fn predictive_search(&self, agent: &mut crate::agent::Agent) -> bool {
+        self.predictive_search_impl(agent, None)
+    }
+
+    /// Performs predictive search restricted by the byte after the query.
+    ///
+    /// Like [`predictive_search`](Self::predictive_search), but only keys
+    /// longer than the query whose byte right after the query passes
+    /// `allowed_first_byte` are reported. Subtrees of the query node whose
+    /// first byte fails are skipped without being visited. The query itself
+    /// is never reported, since it has no next byte.
+    ///
+    /// # Arguments
+    ///
+    /// * `agent` - Agent with initialized state and query
+    /// * `allowed_first_byte` - Filter on the byte following the query
+    ///
+    /// # Panics
+    ///
+    /// Panics if agent doesn't have state initialized.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn predictive_search_filtered(
+        &self,
+        agent: &mut crate::agent::Agent,
+        allowed_first_byte: &dyn Fn(u8) -> bool,
+    ) -> bool {
+        self.predictive_search_impl(agent, Some(allowed_first_byte))
+    }
+
+    /// Shared predictive search state machine.
+    ///
+    /// With `filter`, the byte following the query must pass it. That byte
+    /// is either already in the key buffer when the query ends inside an
+    /// edge, and is then checked once up front, or it starts one of the
+    /// query node's child edges, checked as each child is entered.
+    fn predictive_search_impl(
+        &self,
+        agent: &mut crate::agent::Agent,
+        filter: Option<&dyn Fn(u8) -> bool>,
+    ) -> bool {
+        use crate::base::INVALID_KEY_ID;
         use crate::grimoire::trie::history::History;
         use crate::grimoire::trie::state::StatusCode;
 
         assert!(agent.has_state(), "Agent must have state initialized");
+        let query_len = agent.query().length();
 
         // Check if search is complete
         {
@@ -1382,7 +2116,6 @@ impl LoudsTrie {
             let state = agent.state().expect("Agent must have state");
             if state.status_code() != StatusCode::ReadyToPredictiveSearch {
                 let _ = state;
-                let query_len = agent.query().length();
                 let state = agent.state_mut().expect("Agent must have state");
                 state.predictive_search_init();
                 let _ = state;
@@ -1396,6 +2129,15 @@ impl LoudsTrie {
                     }
                 }
                 let state = agent.state_mut().expect("Agent must have state");
+                // Where the query ended inside an edge, the byte after it
+                // is already known and decides the whole search
+                let past_query = state.key_buf().get(query_len).copied();
+                if let (Some(filter), Some(byte)) = (filter, past_query) {
+                    if !filter(byte) {
+                        state.set_status_code(StatusCode::EndOfPredictiveSearch);
+                        return false;
+                    }
+                }
 
                 // Push initial history
                 let mut history = History::new();
@@ -1404,9 +2146,10 @@ impl LoudsTrie {
                 state.push_history(history);
                 state.set_history_pos(1);
 
-                // Check if current node is terminal
+                // Check if current node is terminal; with a filter, the
+                // query itself has no next byte and is not a match
                 let node_id = state.node_id();
-                if self.terminal_flags.get(node_id) {
+                if self.terminal_flags.get(node_id) && (filter.is_none() || past_query.is_some()) {
                     let _ = state;
 
                     agent.set_key_from_state_buf();
@@ -1475,12 +2218,30 @@ impl LoudsTrie {
                     state.history_at_mut(history_pos).set_key_pos(key_len);
                 }
 
+                // Skip a child of the query node whose edge starts with a
+                // filtered byte, as if its subtree had been walked
+                if let Some(filter) = filter {
+                    let state = agent.state_mut().expect("Agent must have state");
+                    let start = state.history_at(0).key_pos();
+                    if history_pos == 1 && start == query_len && !filter(state.key_buf()[start]) {
+                        let current = state.history_at_mut(1);
+                        current.set_node_id(current.node_id() + 1);
+                        // The skipped terminal breaks the key ID sequence
+                        current.set_key_id(INVALID_KEY_ID as usize);
+                        state.key_buf_mut().truncate(start);
+                        // Deeper iterators assume every subtree is walked
+                        // in order, so rebuild them for the next child
+                        state.history_mut().truncate(2);
+                        state.set_history_pos(1);
+                        continue;
+                    }
+                }
+
                 // Check if terminal
                 if self.terminal_flags.get(next_node_id) {
                     let state = agent.state_mut().expect("Agent must have state");
                     let next_key_id = state.history_at(history_pos).key_id();
 
-                    use crate::base::INVALID_KEY_ID;
                     let key_id = if next_key_id == INVALID_KEY_ID as usize {
                         let id = self.terminal_flags.rank1(next_node_id);
                         state.history_at_mut(history_pos).set_key_id(id);
@@ -1534,7 +2295,9 @@ impl LoudsTrie {
         // hit registers/stack instead of repeating the Vector bounds check.
         let cache_id = self.get_cache_id_with_label(node_id, query_char);
         let cache_entry = self.cache[cache_id];
-        if node_id == cache_entry.parent() {
+        let hit = node_id == cache_entry.parent();
+        self.count_cache_probe(hit);
+        if hit {
             use crate::base::INVALID_EXTRA;
             if cache_entry.extra() != INVALID_EXTRA as usize {
                 let _ = state;
@@ -1605,6 +2368,37 @@ impl LoudsTrie {
 
     // Helper methods
 
+    /// Records a cache probe when the `cache-stats` feature is enabled.
+    #[inline(always)]
+    fn count_cache_probe(&self, hit: bool) {
+        #[cfg(feature = "cache-stats")]
+        {
+            use core::sync::atomic::Ordering;
+            let counter = if hit {
+                &self.cache_counters.hits
+            } else {
+                &self.cache_counters.misses
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+        }
+        #[cfg(not(feature = "cache-stats"))]
+        let _ = hit;
+    }
+
+    /// Returns cache `(hits, misses)` summed over this and all next tries.
+    #[cfg(feature = "cache-stats")]
+    pub(crate) fn cache_stats(&self) -> (u64, u64) {
+        use core::sync::atomic::Ordering;
+        let (hits, misses) = self
+            .next_trie
+            .as_ref()
+            .map_or((0, 0), |next| next.cache_stats());
+        (
+            hits + self.cache_counters.hits.load(Ordering::Relaxed),
+            misses + self.cache_counters.misses.load(Ordering::Relaxed),
+        )
+    }
+
     /// Gets cache ID from node ID and label.
     #[inline]
     fn get_cache_id_with_label(&self, node_id: usize, label: u8) -> usize {
@@ -1701,7 +2495,9 @@ impl LoudsTrie {
         loop {
             let cache_id = self.get_cache_id(node_id);
             let cache_entry = self.cache[cache_id];
-            if node_id == cache_entry.child() {
+            let hit = node_id == cache_entry.child();
+            self.count_cache_probe(hit);
+            if hit {
                 use crate::base::INVALID_EXTRA;
                 if cache_entry.extra() != INVALID_EXTRA as usize {
                     self.restore(agent, cache_entry.link());
@@ -1750,7 +2546,9 @@ impl LoudsTrie {
         loop {
             let cache_id = self.get_cache_id(node_id);
             let cache_entry = self.cache[cache_id];
-            if node_id == cache_entry.child() {
+            let hit = node_id == cache_entry.child();
+            self.count_cache_probe(hit);
+            if hit {
                 use crate::base::INVALID_EXTRA;
                 if cache_entry.extra() != INVALID_EXTRA as usize {
                     if !self.match_link(agent, cache_entry.link()) {
@@ -1824,7 +2622,9 @@ impl LoudsTrie {
         loop {
             let cache_id = self.get_cache_id(node_id);
             let cache_entry = self.cache[cache_id];
-            if node_id == cache_entry.child() {
+            let hit = node_id == cache_entry.child();
+            self.count_cache_probe(hit);
+            if hit {
                 use crate::base::INVALID_EXTRA;
                 if cache_entry.extra() != INVALID_EXTRA as usize {
                     if !self.prefix_match(agent, cache_entry.link()) {
@@ -1887,6 +2687,17 @@ impl LoudsTrie {
     }
 }
 
+/// Sums weights so that the result does not depend on their order.
+///
+/// `f64` addition rounds differently depending on the order of the terms,
+/// so the weights are sorted first and the sum becomes a function of the
+/// weights alone.
+fn order_independent_sum(weights: impl Iterator<Item = f32>) -> f32 {
+    let mut weights: Vec<f32> = weights.collect();
+    weights.sort_unstable_by(f32::total_cmp);
+    weights.iter().map(|&weight| weight as f64).sum::<f64>() as f32
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1936,6 +2747,7 @@ mod tests {
         assert_eq!(trie.node_order(), NodeOrder::Weight);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_louds_trie_write_read_empty() {
         // Rust-specific: Test empty LoudsTrie serialization
@@ -1961,6 +2773,7 @@ mod tests {
         assert_eq!(trie2.num_nodes(), 0);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_louds_trie_write_read_with_keys() {
         // Rust-specific: Test LoudsTrie serialization with keys
@@ -2016,6 +2829,7 @@ mod tests {
         assert!(!trie2.lookup(&mut agent));
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_louds_trie_write_read_config_preserved() {
         // Rust-specific: Test that configuration is preserved through serialization
@@ -2046,4 +2860,600 @@ mod tests {
         assert_eq!(trie2.tail_mode(), TailMode::TextTail);
         assert_eq!(trie2.node_order(), NodeOrder::Label);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_build_lazy_select() {
+        // Rust-specific: LAZY_SELECT changes memory use, not results or output
+        use crate::base::build_flags;
+        use crate::grimoire::io::Writer;
+        use crate::keyset::Keyset;
+
+        let words: Vec<String> = (0..2000).map(|i| format!("key{:05}", i * 7)).collect();
+        let build = |flags: i32| {
+            let mut keyset = Keyset::new();
+            for word in &words {
+                keyset.push_back_str(word).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+            trie
+        };
+
+        let eager = build(0);
+        let lazy = build(build_flags::LAZY_SELECT);
+        assert!(lazy.total_size() < eager.total_size());
+
+        let mut agent = crate::agent::Agent::new();
+        agent.init_state().unwrap();
+        for word in &words {
+            agent.set_query_str(word);
+            assert!(lazy.lookup(&mut agent), "lookup {}", word);
+        }
+        for id in 0..lazy.num_keys() {
+            agent.set_query_id(id);
+            lazy.reverse_lookup(&mut agent);
+        }
+
+        let mut eager_writer = Writer::from_vec(Vec::new());
+        eager.write(&mut eager_writer).unwrap();
+        let mut lazy_writer = Writer::from_vec(Vec::new());
+        lazy.write(&mut lazy_writer).unwrap();
+        assert_eq!(
+            lazy_writer.into_inner().unwrap(),
+            eager_writer.into_inner().unwrap()
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_louds_trie_mmap_truncated_keeps_trie() {
+        // Rust-specific: a failed mmap leaves the trie as it was instead of
+        // partly mapped from a file that is unmapped again
+        use crate::grimoire::io::Writer;
+        use crate::keyset::Keyset;
+        use std::io::Write;
+        use tempfile::NamedTempFile;
+
+        let mut keyset = Keyset::new();
+        for key in ["apple", "banana", "cherry"] {
+            keyset.push_back_str(key).unwrap();
+        }
+        let mut trie = LoudsTrie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut writer = Writer::from_vec(Vec::new());
+        trie.write(&mut writer).unwrap();
+        let data = writer.into_inner().unwrap();
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data[..data.len() - 4]).unwrap();
+        file.flush().unwrap();
+
+        assert!(trie.mmap(file.path().to_str().unwrap()).is_err());
+        assert_eq!(trie.num_keys(), 3);
+        let mut agent = crate::agent::Agent::new();
+        agent.init_state().unwrap();
+        agent.set_query_str("banana");
+        assert!(trie.lookup(&mut agent));
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    fn test_louds_trie_mmap_extras_match_read() {
+        // Rust-specific: extras (and the rest of each level) come through
+        // the mmap path intact, level by level, compared with read()
+        use crate::grimoire::io::{Reader, Writer};
+        use crate::keyset::Keyset;
+        use tempfile::NamedTempFile;
+
+        let mut keyset = Keyset::new();
+        for i in 0..20000u32 {
+            let key = format!("{:08x}/{:08x}", i.wrapping_mul(2_654_435_761), i);
+            keyset.push_back_str(&key).unwrap();
+        }
+        let mut built = LoudsTrie::new();
+        built.build(&mut keyset, 3);
+
+        let temp_file = NamedTempFile::new().unwrap();
+        let path = temp_file.path().to_str().unwrap();
+        {
+            let mut writer = Writer::open(path).unwrap();
+            built.write(&mut writer).unwrap();
+        }
+
+        let mut loaded = LoudsTrie::new();
+        loaded.read(&mut Reader::open(path).unwrap()).unwrap();
+        let mut mapped = LoudsTrie::new();
+        mapped.mmap(path).unwrap();
+
+        let mut loaded_level = Some(&loaded);
+        let mut mapped_level = Some(&mapped);
+        let mut num_extras = 0;
+        while let (Some(l), Some(m)) = (loaded_level, mapped_level) {
+            assert_eq!(m.extras.size(), l.extras.size());
+            assert_eq!(m.extras.value_size(), l.extras.value_size());
+            for i in 0..l.extras.size() {
+                assert_eq!(m.extras.get(i), l.extras.get(i), "extras[{}]", i);
+            }
+            num_extras += l.extras.size();
+            loaded_level = l.next_trie.as_deref();
+            mapped_level = m.next_trie.as_deref();
+        }
+        assert!(loaded_level.is_none() && mapped_level.is_none());
+        assert!(num_extras > 1000, "only {} extras", num_extras);
+
+        let mut agent = crate::agent::Agent::new();
+        agent.init_state().unwrap();
+        for i in 0..keyset.size() {
+            agent.set_query_bytes(keyset.get(i).as_bytes());
+            assert!(mapped.lookup(&mut agent));
+            assert_eq!(agent.key().id(), keyset.get(i).id());
+        }
+    }
+
+    #[test]
+    fn test_louds_trie_build_no_tail_merge() {
+        // Rust-specific: NO_TAIL_MERGE grows the tail but keeps every search working
+        use crate::base::{build_flags, TailMode};
+        use crate::keyset::Keyset;
+
+        let mut words: Vec<Vec<u8>> = (0..2000)
+            .map(|i| format!("word{:04}-shared-suffix", i).into_bytes())
+            .collect();
+        words.push(b"bin\0ary\0suffix".to_vec());
+        let build = |flags: i32| {
+            let mut keyset = Keyset::new();
+            for word in &words {
+                keyset.push_back_bytes(word, 1.0).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+            trie
+        };
+
+        let merged = build(1);
+        let unmerged = build(1 | build_flags::NO_TAIL_MERGE);
+        assert_eq!(unmerged.tail_mode(), TailMode::BinaryTail);
+        assert!(unmerged.tail.size() > merged.tail.size());
+        unmerged.validate().unwrap();
+
+        let mut agent = crate::agent::Agent::new();
+        agent.init_state().unwrap();
+        for word in &words {
+            agent.set_query_bytes(word);
+            assert!(unmerged.lookup(&mut agent), "lookup {:?}", word);
+            let id = agent.key().id();
+            agent.set_query_id(id);
+            unmerged.reverse_lookup(&mut agent);
+            assert_eq!(agent.key().as_bytes(), &word[..]);
+        }
+
+        agent.set_query_str("word12");
+        let mut count = 0;
+        while unmerged.predictive_search(&mut agent) {
+            assert!(agent.key().as_bytes().starts_with(b"word12"));
+            count += 1;
+        }
+        assert_eq!(count, 100);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_build_stable_sort() {
+        // Rust-specific: STABLE_SORT changes only how ties are ordered, so
+        // key IDs and the serialized trie match a regular build
+        use crate::base::build_flags;
+        use crate::grimoire::io::Writer;
+        use crate::keyset::Keyset;
+
+        let words: Vec<String> = (0..3000)
+            .map(|i| format!("dup{}-{}", i % 97, i % 13))
+            .collect();
+        let build = |flags: i32| {
+            let mut keyset = Keyset::new();
+            for word in &words {
+                keyset.push_back_str(word).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+            trie.validate().unwrap();
+            let ids: Vec<usize> = (0..keyset.size()).map(|i| keyset.get(i).id()).collect();
+            let mut writer = Writer::from_vec(Vec::new());
+            trie.write(&mut writer).unwrap();
+            (writer.into_inner().unwrap(), ids)
+        };
+
+        let (expected_bytes, expected_ids) = build(3);
+        let (bytes, ids) = build(3 | build_flags::STABLE_SORT);
+        assert_eq!(ids, expected_ids);
+        assert_eq!(bytes, expected_bytes);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_build_deterministic_weights() {
+        // Rust-specific: with DETERMINISTIC_WEIGHTS, the same weighted keys
+        // pushed in different orders give the same key IDs and bytes, even
+        // when duplicates carry weights whose sum depends on the order
+        use crate::base::build_flags;
+        use crate::grimoire::io::Writer;
+        use crate::keyset::Keyset;
+
+        let mut entries: Vec<(String, f32)> = Vec::new();
+        for i in 0..400 {
+            let key = format!("{}{}", ["a", "ab", "b", "ba"][i % 4], i % 50);
+            // Each key occurs four times, once with each of these weights
+            let weight = [1.0e20, -1.0e20, 0.5, (i % 7) as f32][i / 100];
+            entries.push((key, weight));
+        }
+        let build = |entries: &[(String, f32)]| {
+            let mut keyset = Keyset::new();
+            for (key, weight) in entries {
+                keyset.push_back_bytes(key.as_bytes(), *weight).unwrap();
+            }
+            let flags = 3 | NodeOrder::Weight as i32 | build_flags::DETERMINISTIC_WEIGHTS;
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+            trie.validate().unwrap();
+            let mut ids: Vec<(String, usize)> = (0..keyset.size())
+                .map(|i| (entries[i].0.clone(), keyset.get(i).id()))
+                .collect();
+            ids.sort();
+            let mut writer = Writer::from_vec(Vec::new());
+            trie.write(&mut writer).unwrap();
+            (ids, writer.into_inner().unwrap())
+        };
+
+        let expected = build(&entries);
+        let mut reversed = entries.clone();
+        reversed.reverse();
+        let mut rotated = entries.clone();
+        rotated.rotate_left(137);
+        let mut interleaved: Vec<(String, f32)> = entries.iter().step_by(2).cloned().collect();
+        interleaved.extend(entries.iter().skip(1).step_by(2).cloned());
+        for order in [reversed, rotated, interleaved] {
+            assert_eq!(build(&order), expected);
+        }
+    }
+
+    #[test]
+    fn test_louds_trie_many_links_fit_extras() {
+        // Rust-specific: with hundreds of thousands of link nodes the link
+        // values need far more than 8 bits; the extras width must match
+        // the largest stored value and every link must stay in range
+        use crate::keyset::Keyset;
+
+        let words: Vec<String> = (0..200_000u32)
+            .map(|i| format!("{:05x}/{}-payload-{}", i, i.wrapping_mul(2_654_435_761), i))
+            .collect();
+        for num_tries in [1, 3] {
+            let mut keyset = Keyset::new();
+            for word in &words {
+                keyset.push_back_str(word).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, num_tries);
+            trie.validate().unwrap();
+
+            let mut level = Some(&trie);
+            while let Some(current) = level {
+                let num_links = current.link_flags.num_1s();
+                assert!(num_links > 100_000);
+                let max_extra = (0..current.extras.size())
+                    .map(|i| current.extras.get(i))
+                    .max()
+                    .unwrap();
+                assert_eq!(
+                    current.extras.value_size(),
+                    (u32::BITS - max_extra.leading_zeros()) as usize
+                );
+                assert!(max_extra > 0xFF);
+
+                let bound = match current.next_trie {
+                    Some(ref next) => next.num_nodes(),
+                    None => current.tail.size(),
+                };
+                for (link_id, node_id) in current.link_flags.ones().enumerate() {
+                    assert!(current.get_link_with_id(node_id, link_id) < bound);
+                }
+                level = current.next_trie.as_deref();
+            }
+
+            let mut agent = crate::agent::Agent::new();
+            agent.init_state().unwrap();
+            for (i, word) in words.iter().enumerate().step_by(97) {
+                agent.set_query_str(word);
+                assert!(trie.lookup(&mut agent), "lookup {}", word);
+                assert_eq!(agent.key().id(), keyset.get(i).id());
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_lookup_filter() {
+        // Rust-specific: the filter rejects impossible queries up front and
+        // never changes a lookup result; it is not serialized
+        use crate::base::build_flags;
+        use crate::grimoire::io::{Reader, Writer};
+        use crate::keyset::Keyset;
+
+        let words = ["apple", "apricot", "banana", "band", "cherry-pie"];
+        let build = |flags: i32| {
+            let mut keyset = Keyset::new();
+            for word in words {
+                keyset.push_back_str(word).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+            let mut writer = Writer::from_vec(Vec::new());
+            trie.write(&mut writer).unwrap();
+            (trie, writer.into_inner().unwrap())
+        };
+        let (plain, plain_bytes) = build(0);
+        assert!(plain.lookup_filter.is_none());
+        let (filtered, bytes) = build(build_flags::LOOKUP_FILTER);
+        assert_eq!(bytes, plain_bytes);
+
+        let filter = filtered.lookup_filter.unwrap();
+        assert_eq!((filter.min_length, filter.max_length), (4, 10));
+        assert!(filter.may_contain(b"bxxx"));
+        assert!(!filter.may_contain(b"app"));
+        assert!(!filter.may_contain(b"cherry-pies"));
+        assert!(!filter.may_contain(b"zebra"));
+        assert!(!filter.may_contain(b""));
+
+        let mut agent = crate::agent::Agent::new();
+        agent.init_state().unwrap();
+        let queries = [
+            "apple",
+            "apricot",
+            "banana",
+            "band",
+            "cherry-pie",
+            "app",
+            "bandana",
+            "zebra",
+            "",
+            "apples",
+            "cherry-pies",
+            "b\u{ff}nd",
+        ];
+        for query in queries {
+            agent.set_query_str(query);
+            let expected = plain.lookup(&mut agent);
+            agent.set_query_str(query);
+            assert_eq!(filtered.lookup(&mut agent), expected, "{:?}", query);
+            if expected {
+                assert_eq!(agent.key().as_bytes(), query.as_bytes());
+            }
+        }
+
+        let mut loaded = LoudsTrie::new();
+        loaded.read(&mut Reader::from_bytes(&bytes)).unwrap();
+        assert!(loaded.lookup_filter.is_none());
+    }
+
+    #[test]
+    fn test_louds_trie_reverse_lookup_long_keys() {
+        // Rust-specific: long keys restored through many links come back
+        // byte-identical at every trie depth and tail mode, including when
+        // the agent's history was left over from a predictive search
+        use crate::keyset::Keyset;
+
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        let mut keys: Vec<Vec<u8>> = (0..500)
+            .map(|i| {
+                let mut key = format!("k{}", i % 7).into_bytes();
+                for _ in 0..(i % 60) {
+                    x ^= x << 13;
+                    x ^= x >> 7;
+                    x ^= x << 17;
+                    key.extend_from_slice(b"/seg");
+                    key.push((x % 5) as u8);
+                    key.extend_from_slice(format!("{:x}", x >> 56).as_bytes());
+                }
+                key
+            })
+            .collect();
+        // Branching at every byte gives paths deeper than the inline
+        // buffer, so the rest of the path spills to the history stack
+        for depth in [63, 64, 65, 150] {
+            keys.push(vec![b'a'; depth]);
+            let mut key = vec![b'a'; depth - 1];
+            key.push(b'b');
+            keys.push(key);
+        }
+
+        for flags in [1, 2, 3, 4, 0x02000 | 3, 0x10000 | 5] {
+            let mut keyset = Keyset::new();
+            for key in &keys {
+                keyset.push_back_bytes(key, 1.0).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, flags);
+
+            let mut agent = crate::agent::Agent::new();
+            agent.init_state().unwrap();
+            agent.set_query_str("k");
+            assert!(trie.predictive_search(&mut agent));
+
+            for key in &keys {
+                agent.set_query_bytes(key);
+                assert!(trie.lookup(&mut agent));
+                let id = agent.key().id();
+                agent.set_query_id(id);
+                trie.reverse_lookup(&mut agent);
+                assert_eq!(agent.key().as_bytes(), key.as_slice(), "flags {:#x}", flags);
+                assert_eq!(agent.key().id(), id);
+            }
+        }
+    }
+
+    #[test]
+    fn test_louds_trie_common_prefix_search_no_duplicates() {
+        // Rust-specific: link strings and tails that equal shorter keys
+        // never make a key be reported twice, at any trie depth or cache
+        // level
+        use crate::keyset::Keyset;
+        use std::collections::HashSet;
+
+        let keys: Vec<String> = ["ab", "abab", "ababab", "xab", "xabab", "b", "bab", "babab"]
+            .iter()
+            .map(|key| key.to_string())
+            .chain((0..200).map(|i| format!("{}{}", "ab".repeat(i % 9), i % 5)))
+            .collect();
+        let unique: HashSet<&[u8]> = keys.iter().map(|key| key.as_bytes()).collect();
+
+        for tries in 1..=4 {
+            for extra in [0, 0x02000, 0x10000, 0x00080, 0x00800] {
+                let flags = tries | extra;
+                let mut keyset = Keyset::new();
+                for key in &keys {
+                    keyset.push_back_str(key).unwrap();
+                }
+                let mut trie = LoudsTrie::new();
+                trie.build(&mut keyset, flags);
+
+                let mut agent = crate::agent::Agent::new();
+                agent.init_state().unwrap();
+                for key in keys
+                    .iter()
+                    .map(|key| key.as_bytes())
+                    .chain([&b"abababab"[..]])
+                {
+                    agent.set_query_bytes(key);
+                    let mut ids = HashSet::new();
+                    let mut lengths = Vec::new();
+                    while trie.common_prefix_search(&mut agent) {
+                        assert!(ids.insert(agent.key().id()), "flags {:#x} {:?}", flags, key);
+                        lengths.push(agent.key().length());
+                    }
+                    let expected: Vec<usize> = (0..=key.len())
+                        .filter(|&len| unique.contains(&key[..len]))
+                        .collect();
+                    assert_eq!(lengths, expected, "flags {:#x} {:?}", flags, key);
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn build_validation_trie() -> LoudsTrie {
+        use crate::keyset::Keyset;
+
+        let mut keyset = Keyset::new();
+        for i in 0..200 {
+            keyset
+                .push_back_str(&format!("validate-{:03}-key", i))
+                .unwrap();
+        }
+        let mut trie = LoudsTrie::new();
+        trie.build(&mut keyset, 0);
+        trie
+    }
+
+    #[cfg(feature = "std")]
+    fn write_to_vec(trie: &LoudsTrie) -> Vec<u8> {
+        let mut writer = Writer::from_vec(Vec::new());
+        trie.write(&mut writer).unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_validate_accepts_built_and_read() {
+        // Rust-specific: validate() passes for well-formed tries
+        let built = build_validation_trie();
+        built.validate().unwrap();
+
+        let mut read = LoudsTrie::new();
+        read.read(&mut Reader::from_bytes(&write_to_vec(&built)))
+            .unwrap();
+        read.validate().unwrap();
+
+        let mut empty = LoudsTrie::new();
+        empty.build(&mut crate::keyset::Keyset::new(), 0);
+        empty.validate().unwrap();
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_validate_rejects_tampered_terminal_count() {
+        // Rust-specific: a wrong terminal num_1s still reads, but fails validate()
+        let built = build_validation_trie();
+        let mut bytes = write_to_vec(&built);
+
+        // Header, LOUDS, then terminal_flags: units (u64 length + data), size u32, num_1s u32.
+        let num_units = (built.terminal_flags.size() + 63) / 64;
+        let offset = 16 + built.louds.io_size() + 8 + num_units * 8 + 4;
+        let num_1s = u32::from_le_bytes(bytes[offset..offset + 4].try_into().unwrap());
+        assert_eq!(num_1s as usize, built.num_keys());
+        bytes[offset..offset + 4].copy_from_slice(&(num_1s - 1).to_le_bytes());
+
+        let mut read = LoudsTrie::new();
+        read.read(&mut Reader::from_bytes(&bytes)).unwrap();
+        let err = read.validate().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("terminal flag"), "{}", err);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_validate_rejects_tampered_num_l1_nodes() {
+        // Rust-specific: num_l1_nodes is the second-to-last u32 of the file
+        let built = build_validation_trie();
+        let mut bytes = write_to_vec(&built);
+        let offset = bytes.len() - 8;
+        bytes[offset..offset + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+        let mut read = LoudsTrie::new();
+        read.read(&mut Reader::from_bytes(&bytes)).unwrap();
+        let err = read.validate().unwrap_err();
+        assert!(err.to_string().contains("num_l1_nodes"), "{}", err);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_louds_trie_multi_trie_build_output_stable() {
+        // Rust-specific: serialized multi-trie builds must not change when
+        // the build internals are refactored
+        use crate::grimoire::io::crc32::crc32;
+        use crate::keyset::Keyset;
+
+        let keys: Vec<Vec<u8>> = (0..500u32)
+            .map(|i| {
+                let mut key = format!("{:03}-shared-middle-{}", i % 97, i).into_bytes();
+                if i % 7 == 0 {
+                    key.push(0);
+                }
+                key
+            })
+            .collect();
+
+        let mut checksums = Vec::new();
+        for num_tries in 1..=5 {
+            let mut keyset = Keyset::new();
+            for (i, key) in keys.iter().enumerate() {
+                keyset.push_back_bytes(key, (i % 13) as f32).unwrap();
+            }
+            let mut trie = LoudsTrie::new();
+            trie.build(&mut keyset, num_tries);
+            assert_eq!(trie.num_tries(), num_tries as usize);
+            trie.validate().unwrap();
+
+            let mut agent = crate::agent::Agent::new();
+            agent.init_state().unwrap();
+            for i in 0..keyset.size() {
+                agent.set_query_bytes(keyset.get(i).as_bytes());
+                assert!(trie.lookup(&mut agent), "num_tries {} key {}", num_tries, i);
+                assert_eq!(agent.key().id(), keyset.get(i).id());
+            }
+            checksums.push(crc32(&write_to_vec(&trie)));
+        }
+        assert_eq!(
+            checksums,
+            [0xd3d11dee, 0xe1825504, 0x04f54d9b, 0x59940f55, 0xdffdd79d]
+        );
+    }
 }
@@ -206,17 +206,17 @@ impl PartialEq for WeightedRange {
 impl Eq for WeightedRange {}
 
 impl PartialOrd for WeightedRange {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
         Some(self.cmp(other))
     }
 }
 
 impl Ord for WeightedRange {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
         // For NaN handling, treat NaN as less than everything
         self.weight
             .partial_cmp(&other.weight)
-            .unwrap_or(std::cmp::Ordering::Less)
+            .unwrap_or(core::cmp::Ordering::Less)
     }
 }
 
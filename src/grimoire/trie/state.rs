@@ -6,6 +6,7 @@
 //! including node position, query position, history stack, and operation status.
 
 use super::history::History;
+use alloc::vec::Vec;
 
 /// Status codes for search operations.
 ///
@@ -216,6 +217,19 @@ impl State {
         self.status_code = StatusCode::ReadyToAll;
     }
 
+    /// Empties the key buffer and history and resets every position,
+    /// keeping the buffers' capacity for the next search.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn clear(&mut self) {
+        self.key_buf.clear();
+        self.history.clear();
+        self.node_id = 0;
+        self.query_pos = 0;
+        self.history_pos = 0;
+        self.status_code = StatusCode::ReadyToAll;
+    }
+
     /// Initializes state for lookup operation.
     pub fn lookup_init(&mut self) {
         self.node_id = 0;
@@ -9,11 +9,11 @@
 //! binary (bit-vector terminated).
 
 use crate::base::TailMode;
+use crate::grimoire::io;
 use crate::grimoire::vector::bit_vector::BitVector;
 use crate::grimoire::vector::vector::Vector;
-use std::io;
 
-#[allow(unused_imports)]
+#[cfg(feature = "std")]
 use crate::grimoire::io::{Reader, Writer};
 
 /// Tail structure for storing trie suffixes.
@@ -27,6 +27,10 @@ pub struct Tail {
     buf: Vector<u8>,
     /// Bit vector marking end positions (binary mode only).
     end_flags: BitVector,
+    /// Buffer size the entries would need without suffix merging.
+    ///
+    /// Only known for a tail built in this process; zero after read or map.
+    unmerged_size: usize,
 }
 
 impl Default for Tail {
@@ -41,6 +45,7 @@ impl Tail {
         Tail {
             buf: Vector::new(),
             end_flags: BitVector::new(),
+            unmerged_size: 0,
         }
     }
 
@@ -81,11 +86,45 @@ impl Tail {
         self.buf.size()
     }
 
+    /// Returns the ratio of the buffer size to the size it would have
+    /// without suffix merging.
+    ///
+    /// Rust-specific. Values well below 1.0 mean many entries share
+    /// suffixes. Returns `None` if the tail was read or mapped rather than
+    /// built, since the unmerged size is not stored in the file.
+    pub fn compression_ratio(&self) -> Option<f64> {
+        if self.unmerged_size == 0 {
+            return None;
+        }
+        Some(self.buf.size() as f64 / self.unmerged_size as f64)
+    }
+
     /// Returns the total memory size.
     pub fn total_size(&self) -> usize {
         self.buf.total_size() + self.end_flags.total_size()
     }
 
+    /// Returns the part of [`total_size`](Self::total_size) borrowed from
+    /// mapped memory.
+    pub fn mapped_size(&self) -> usize {
+        self.buf.mapped_size() + self.end_flags.mapped_size()
+    }
+
+    /// Returns the heap bytes reserved, including unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn allocated_size(&self) -> usize {
+        self.buf.allocated_size() + self.end_flags.allocated_size()
+    }
+
+    /// Releases unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn shrink_to_fit(&mut self) {
+        self.buf.shrink_unfixed();
+        self.end_flags.shrink_to_fit();
+    }
+
     /// Returns the I/O size for serialization.
     pub fn io_size(&self) -> usize {
         self.buf.io_size() + self.end_flags.io_size()
@@ -99,32 +138,107 @@ impl Tail {
     /// * `offsets` - Output vector for tail offsets
     /// * `mode` - Tail mode (text or binary)
     pub fn build(
+        &mut self,
+        entries: &mut Vector<crate::grimoire::trie::entry::Entry<'_>>,
+        offsets: &mut Vector<u32>,
+        mode: TailMode,
+    ) {
+        self.build_impl(entries, offsets, mode, true);
+    }
+
+    /// Builds tail storage without sharing common suffixes.
+    ///
+    /// Rust-specific: each entry is appended in input order with its own
+    /// terminator, skipping the sort and suffix merge of [`build`](Self::build).
+    /// The buffer is larger but faster to build when entries rarely share
+    /// suffixes.
+    ///
+    /// # Arguments
+    ///
+    /// * `entries` - Vector of entries to build from
+    /// * `offsets` - Output vector for tail offsets
+    /// * `mode` - Tail mode (text or binary)
+    pub fn build_unmerged(
+        &mut self,
+        entries: &mut Vector<crate::grimoire::trie::entry::Entry<'_>>,
+        offsets: &mut Vector<u32>,
+        mode: TailMode,
+    ) {
+        self.build_impl(entries, offsets, mode, false);
+    }
+
+    /// Chooses the tail mode and builds with or without suffix merging.
+    fn build_impl(
         &mut self,
         entries: &mut Vector<crate::grimoire::trie::entry::Entry<'_>>,
         offsets: &mut Vector<u32>,
         mut mode: TailMode,
+        merge_suffixes: bool,
     ) {
-        // Check if any entry contains NULL bytes - if so, use binary mode
-        if mode == TailMode::TextTail {
-            for i in 0..entries.size() {
-                let bytes = entries[i].as_bytes();
-                for &b in bytes {
-                    if b == 0 {
-                        mode = TailMode::BinaryTail;
-                        break;
-                    }
-                }
-                if mode == TailMode::BinaryTail {
-                    break;
-                }
-            }
+        // Text mode (and Auto) falls back to binary mode if any entry
+        // contains a NULL byte
+        if mode != TailMode::BinaryTail {
+            let has_null = (0..entries.size()).any(|i| entries[i].as_bytes().contains(&0));
+            mode = if has_null {
+                TailMode::BinaryTail
+            } else {
+                TailMode::TextTail
+            };
         }
 
         let mut temp = Tail::new();
-        temp.build_(entries, offsets, mode);
+        if merge_suffixes {
+            temp.build_(entries, offsets, mode);
+        } else {
+            temp.build_unmerged_(entries, offsets, mode);
+        }
         self.swap(&mut temp);
     }
 
+    /// Internal build implementation without suffix merging.
+    fn build_unmerged_(
+        &mut self,
+        entries: &Vector<crate::grimoire::trie::entry::Entry<'_>>,
+        offsets: &mut Vector<u32>,
+        mode: TailMode,
+    ) {
+        let mut temp_offsets: Vector<u32> = Vector::new();
+        temp_offsets.resize(entries.size(), 0);
+
+        self.unmerged_size = unmerged_size(entries, mode);
+        for i in 0..entries.size() {
+            let current = entries[i];
+            assert!(current.length() > 0, "Entry length must be > 0");
+
+            temp_offsets[i] = self.buf.size() as u32;
+            // Entry::get(j) reads in reverse, as in build_
+            for j in 0..current.length() {
+                self.buf.push_back(current.get(current.length() - 1 - j));
+            }
+
+            if mode == TailMode::TextTail {
+                self.buf.push_back(0);
+            } else {
+                for _ in 0..(current.length() - 1) {
+                    self.end_flags.push_back(false);
+                }
+                self.end_flags.push_back(true);
+            }
+
+            assert!(
+                self.buf.size() <= u32::MAX as usize,
+                "Tail buffer too large"
+            );
+        }
+
+        if mode == TailMode::BinaryTail {
+            self.end_flags.build(false, false);
+        }
+
+        self.buf.shrink();
+        offsets.swap(&mut temp_offsets);
+    }
+
     /// Internal build implementation.
     fn build_(
         &mut self,
@@ -148,7 +262,7 @@ impl Tail {
             for i in 0..a.length() {
                 if i == b.length() {
                     // a is longer than b, a comes after
-                    return std::cmp::Ordering::Greater;
+                    return core::cmp::Ordering::Greater;
                 }
                 let a_byte = a.get(i);
                 let b_byte = b.get(i);
@@ -163,6 +277,7 @@ impl Tail {
 
         let mut temp_offsets: Vector<u32> = Vector::new();
         temp_offsets.resize(entries.size(), 0);
+        self.unmerged_size = unmerged_size(entries, mode);
 
         // Process entries in reverse order to find common suffixes
         let dummy = Entry::new();
@@ -239,9 +354,10 @@ impl Tail {
     /// # Errors
     ///
     /// Returns an error if mapping fails.
-    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper) -> io::Result<()> {
+    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper<'_>) -> io::Result<()> {
         self.buf.map(mapper)?;
         self.end_flags.map(mapper)?;
+        self.unmerged_size = 0;
         Ok(())
     }
 
@@ -258,9 +374,11 @@ impl Tail {
     /// # Errors
     ///
     /// Returns an error if reading fails.
+    #[cfg(feature = "std")]
     pub fn read(&mut self, reader: &mut Reader<'_>) -> io::Result<()> {
         self.buf.read(reader)?;
         self.end_flags.read(reader)?;
+        self.unmerged_size = 0;
         Ok(())
     }
 
@@ -277,6 +395,7 @@ impl Tail {
     /// # Errors
     ///
     /// Returns an error if writing fails.
+    #[cfg(feature = "std")]
     pub fn write(&self, writer: &mut Writer<'_>) -> io::Result<()> {
         self.buf.write(writer)?;
         self.end_flags.write(writer)?;
@@ -425,32 +544,29 @@ impl Tail {
         let (query_bytes, state) = agent.query_bytes_and_state_mut();
         let mut query_pos = state.query_pos();
 
+        // In C++ text mode, ptr = &buf_[offset] - query_pos, which can point
+        // before the buffer. Walk an absolute index from `offset` instead so
+        // that offsets smaller than query_pos cannot underflow.
+        let mut i = offset;
         if self.end_flags.empty() {
             // Text mode
-            let start_offset = offset - query_pos;
-            loop {
-                if self.buf[start_offset + query_pos] != query_bytes[query_pos] {
+            while query_pos < query_bytes.len() {
+                if self.buf[i] != query_bytes[query_pos] {
                     state.set_query_pos(query_pos);
                     return false;
                 }
-                state.key_buf_mut().push(self.buf[start_offset + query_pos]);
+                state.key_buf_mut().push(self.buf[i]);
                 query_pos += 1;
+                i += 1;
 
-                if start_offset + query_pos >= self.buf.size()
-                    || self.buf[start_offset + query_pos] == 0
-                {
+                if i >= self.buf.size() || self.buf[i] == 0 {
                     state.set_query_pos(query_pos);
                     return true;
                 }
-
-                if query_pos >= query_bytes.len() {
-                    break;
-                }
             }
 
-            // Append rest of tail
+            // Query exhausted: append rest of tail
             state.set_query_pos(query_pos);
-            let mut i = start_offset + query_pos;
             while i < self.buf.size() && self.buf[i] != 0 {
                 state.key_buf_mut().push(self.buf[i]);
                 i += 1;
@@ -458,8 +574,7 @@ impl Tail {
             true
         } else {
             // Binary mode
-            let mut i = offset;
-            loop {
+            while query_pos < query_bytes.len() {
                 if self.buf[i] != query_bytes[query_pos] {
                     state.set_query_pos(query_pos);
                     return false;
@@ -474,13 +589,9 @@ impl Tail {
                     state.set_query_pos(query_pos);
                     return true;
                 }
-
-                if query_pos >= query_bytes.len() {
-                    break;
-                }
             }
 
-            // Append rest of tail
+            // Query exhausted: append rest of tail
             state.set_query_pos(query_pos);
             loop {
                 state.key_buf_mut().push(self.buf[i]);
@@ -501,11 +612,23 @@ impl Tail {
 
     /// Swaps with another tail.
     pub fn swap(&mut self, other: &mut Tail) {
-        std::mem::swap(&mut self.buf, &mut other.buf);
-        std::mem::swap(&mut self.end_flags, &mut other.end_flags);
+        core::mem::swap(&mut self.buf, &mut other.buf);
+        core::mem::swap(&mut self.end_flags, &mut other.end_flags);
+        core::mem::swap(&mut self.unmerged_size, &mut other.unmerged_size);
     }
 }
 
+/// Returns the buffer size needed to store every entry separately.
+fn unmerged_size(
+    entries: &Vector<crate::grimoire::trie::entry::Entry<'_>>,
+    mode: TailMode,
+) -> usize {
+    let terminator = usize::from(mode == TailMode::TextTail);
+    (0..entries.size())
+        .map(|i| entries[i].length() + terminator)
+        .sum()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -599,6 +722,7 @@ mod tests {
         let _ = (total, io); // Use the variables
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tail_write_read_text_mode() {
         // Rust-specific: Test Tail serialization in text mode
@@ -637,6 +761,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tail_write_read_binary_mode() {
         // Rust-specific: Test Tail serialization in binary mode
@@ -683,6 +808,7 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_tail_write_read_empty() {
         // Rust-specific: Test empty Tail serialization
@@ -707,4 +833,101 @@ mod tests {
         assert_eq!(tail2.size(), 0);
         assert_eq!(tail2.mode(), TailMode::TextTail);
     }
+
+    fn prefix_match_at(
+        tail: &Tail,
+        query: &[u8],
+        query_pos: usize,
+        offset: usize,
+    ) -> Option<Vec<u8>> {
+        let mut agent = crate::agent::Agent::new();
+        agent.set_query_bytes(query);
+        agent.init_state().unwrap();
+        agent.state_mut().unwrap().set_query_pos(query_pos);
+        if tail.prefix_match(&mut agent, offset) {
+            Some(agent.state().unwrap().key_buf().to_vec())
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn test_tail_prefix_match_offset_below_query_pos() {
+        // Rust-specific: offset < query_pos underflowed in text mode
+        let mut tail = Tail::new();
+        for &c in b"cdef\0yz\0" {
+            tail.buf.push_back(c);
+        }
+
+        // Query "abc" has consumed "ab"; link "cdef" starts at offset 0.
+        assert_eq!(prefix_match_at(&tail, b"abc", 2, 0), Some(b"cdef".to_vec()));
+        assert_eq!(
+            prefix_match_at(&tail, b"abcdefg", 2, 0),
+            Some(b"cdef".to_vec())
+        );
+        assert_eq!(prefix_match_at(&tail, b"abx", 2, 0), None);
+        assert_eq!(prefix_match_at(&tail, b"aby", 2, 5), Some(b"yz".to_vec()));
+    }
+
+    #[test]
+    fn test_tail_prefix_match_binary_query_exhausted() {
+        // Rust-specific: binary mode restores the whole tail when no query bytes remain
+        let mut tail = Tail::new();
+        for (i, &c) in b"cd\0ef".iter().enumerate() {
+            tail.buf.push_back(c);
+            tail.end_flags.push_back(i == 4);
+        }
+        tail.end_flags.build(false, false);
+
+        assert_eq!(
+            prefix_match_at(&tail, b"ab", 2, 0),
+            Some(b"cd\0ef".to_vec())
+        );
+        assert_eq!(
+            prefix_match_at(&tail, b"abcd", 2, 0),
+            Some(b"cd\0ef".to_vec())
+        );
+        assert_eq!(prefix_match_at(&tail, b"abce", 2, 0), None);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_tail_compression_ratio() {
+        // Rust-specific: shared suffixes are merged, so the ratio drops below 1.0
+        use crate::grimoire::trie::entry::Entry;
+
+        let words: [&[u8]; 6] = [
+            b"running",
+            b"jumping",
+            b"swimming",
+            b"ing",
+            b"ping",
+            b"mming",
+        ];
+        let mut entries: Vector<Entry<'_>> = Vector::new();
+        entries.resize(words.len(), Entry::new());
+        for (i, word) in words.iter().enumerate() {
+            entries[i].set_str(word);
+        }
+
+        let mut tail = Tail::new();
+        assert_eq!(tail.compression_ratio(), None);
+
+        let mut offsets = Vector::new();
+        tail.build(&mut entries, &mut offsets, TailMode::TextTail);
+        let ratio = tail.compression_ratio().unwrap();
+        assert!(ratio < 0.75, "ratio {}", ratio);
+
+        let mut unmerged = Tail::new();
+        unmerged.build_unmerged(&mut entries, &mut offsets, TailMode::TextTail);
+        assert_eq!(unmerged.compression_ratio(), Some(1.0));
+
+        // The unmerged size is not serialized.
+        let mut writer = Writer::from_vec(Vec::new());
+        tail.write(&mut writer).unwrap();
+        let data = writer.into_inner().unwrap();
+        let mut read = Tail::new();
+        read.read(&mut Reader::from_bytes(&data)).unwrap();
+        assert_eq!(read.compression_ratio(), None);
+    }
 }
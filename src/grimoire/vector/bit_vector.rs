@@ -9,11 +9,22 @@
 //! - Efficient select queries (find position of nth 0 or 1)
 //! - Space-efficient storage using bit packing
 
-use super::pop_count::{popcount, popcount_unit, Unit};
+use super::pop_count::{popcount, popcount_units, Unit};
 use super::rank_index::RankIndex;
 use super::select_bit::select_bit_u64;
 use super::vector::Vector;
-use crate::base::WORD_SIZE;
+use crate::base::{MAX_BIT_VECTOR_SIZE, WORD_SIZE};
+use crate::grimoire::io;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(not(feature = "std"))]
+use super::once_lock::OnceLock;
+
+/// Number of units whose popcounts are computed together during index build.
+const POPCOUNT_CHUNK_UNITS: usize = 64;
 
 /// Bit vector supporting rank and select operations.
 ///
@@ -33,6 +44,81 @@ pub struct BitVector {
     select0s: Vector<u32>,
     /// Select index for 1-bits (optional).
     select1s: Vector<u32>,
+    /// Select indices deferred by `build_lazy`.
+    lazy_select: LazySelect,
+}
+
+impl FromIterator<bool> for BitVector {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bv = BitVector::new();
+        let mut unit: Unit = 0;
+        let mut bit_offset = 0;
+
+        for bit in iter {
+            assert!(
+                bv.size < MAX_BIT_VECTOR_SIZE,
+                "BitVector size cannot exceed u32::MAX"
+            );
+            if bit {
+                unit |= (1 as Unit) << bit_offset;
+                bv.num_1s += 1;
+            }
+            bv.size += 1;
+            bit_offset += 1;
+            if bit_offset == WORD_SIZE {
+                bv.units.push_back(unit);
+                unit = 0;
+                bit_offset = 0;
+            }
+        }
+        if bit_offset != 0 {
+            bv.units.push_back(unit);
+        }
+
+        bv
+    }
+}
+
+/// Compares the stored bits.
+///
+/// Two vectors are equal when they hold the same bits, whatever their
+/// capacity, whether they own or map their storage, and which rank/select
+/// indices have been built; the indices are derived from the bits.
+///
+/// Rust-specific: Not present in C++ version.
+impl PartialEq for BitVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.num_1s == other.num_1s
+            && self.units.as_slice() == other.units.as_slice()
+    }
+}
+
+impl Eq for BitVector {}
+
+impl fmt::Debug for BitVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BitVector")
+            .field("size", &self.size)
+            .field("num_1s", &self.num_1s)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Select indices whose construction is deferred until first use.
+///
+/// Set up by [`BitVector::build_lazy`]. Each index is built at most once,
+/// on the first `select0`/`select1` call (or when the vector is written).
+#[derive(Default)]
+struct LazySelect {
+    /// Whether select0 was requested.
+    enables_select0: bool,
+    /// Whether select1 was requested.
+    enables_select1: bool,
+    /// Deferred select0 index.
+    select0s: OnceLock<Vector<u32>>,
+    /// Deferred select1 index.
+    select1s: OnceLock<Vector<u32>>,
 }
 
 impl BitVector {
@@ -46,11 +132,30 @@ impl BitVector {
             ranks: Vector::new(),
             select0s: Vector::new(),
             select1s: Vector::new(),
+            lazy_select: LazySelect::default(),
         }
     }
 
+    /// Creates a bit vector holding `bits` in order.
+    ///
+    /// Equivalent to calling [`push_back`](Self::push_back) for each bit,
+    /// but packs a whole unit at a time. Use `collect()` (or
+    /// `BitVector::from_iter`) to build from any iterator of `bool`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the size would exceed u32::MAX.
+    pub fn from_bits(bits: &[bool]) -> Self {
+        bits.iter().copied().collect()
+    }
+
     /// Pushes a bit onto the end of the vector.
     ///
+    /// Rust-specific: a built vector accepts more bits too. Its rank and
+    /// select indices no longer cover every bit, so they are dropped, and
+    /// [`build`](Self::build) must be called again before the next rank or
+    /// select. This lets a vector be built in stages.
+    ///
     /// # Arguments
     ///
     /// * `bit` - The bit value to push (true for 1, false for 0)
@@ -60,9 +165,12 @@ impl BitVector {
     /// Panics if the size would exceed u32::MAX.
     pub fn push_back(&mut self, bit: bool) {
         assert!(
-            self.size < u32::MAX as usize,
+            self.size < MAX_BIT_VECTOR_SIZE,
             "BitVector size cannot exceed u32::MAX"
         );
+        if !self.ranks.empty() {
+            self.clear_indices();
+        }
 
         // Expand units if needed (one 64-bit unit at a time)
         if self.size == WORD_SIZE * self.units.size() {
@@ -102,6 +210,52 @@ impl BitVector {
         (self.units[unit_index] & ((1 as Unit) << bit_offset)) != 0
     }
 
+    /// Returns an iterator over the positions of 1-bits, in ascending order.
+    ///
+    /// Scans the units word by word, so the cost is proportional to the
+    /// number of units plus the number of 1-bits. No index is required.
+    pub fn ones(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bit_positions(false)
+    }
+
+    /// Returns an iterator over the positions of 0-bits, in ascending order.
+    pub fn zeros(&self) -> impl Iterator<Item = usize> + '_ {
+        self.bit_positions(true)
+    }
+
+    /// Yields the positions of 1-bits (or 0-bits if `invert`) below `size`.
+    fn bit_positions(&self, invert: bool) -> impl Iterator<Item = usize> + '_ {
+        let size = self.size;
+        self.units
+            .as_slice()
+            .iter()
+            .enumerate()
+            .flat_map(move |(unit_id, &unit)| {
+                let bit_id = unit_id * WORD_SIZE;
+                let mut word = if invert { !unit } else { unit };
+                let num_bits = size.saturating_sub(bit_id);
+                if num_bits < WORD_SIZE {
+                    word &= ((1 as Unit) << num_bits) - 1;
+                }
+                core::iter::from_fn(move || {
+                    if word == 0 {
+                        return None;
+                    }
+                    let pos = bit_id + word.trailing_zeros() as usize;
+                    word &= word - 1;
+                    Some(pos)
+                })
+            })
+    }
+
+    /// Returns the maximum number of bits a bit vector can hold.
+    ///
+    /// See [`MAX_BIT_VECTOR_SIZE`].
+    #[inline]
+    pub const fn max_size() -> usize {
+        MAX_BIT_VECTOR_SIZE
+    }
+
     /// Returns the number of 0-bits in the vector.
     #[inline]
     pub fn num_0s(&self) -> usize {
@@ -114,6 +268,25 @@ impl BitVector {
         self.num_1s
     }
 
+    /// Checks that the stored bits cover `size()` and that `num_1s()`
+    /// matches the number of set bits among them.
+    ///
+    /// Used to validate vectors read from untrusted data.
+    pub(crate) fn is_consistent(&self) -> bool {
+        let num_units = (self.size + WORD_SIZE - 1) / WORD_SIZE;
+        if self.units.size() < num_units {
+            return false;
+        }
+        let units = &self.units.as_slice()[..num_units];
+        let mut count: usize = units.iter().map(|unit| unit.count_ones() as usize).sum();
+        let tail_bits = self.size % WORD_SIZE;
+        if tail_bits != 0 {
+            let extra = units[num_units - 1] >> tail_bits;
+            count -= extra.count_ones() as usize;
+        }
+        count == self.num_1s
+    }
+
     /// Returns true if the vector is empty.
     #[inline]
     pub fn empty(&self) -> bool {
@@ -127,22 +300,78 @@ impl BitVector {
     }
 
     /// Returns the total size in bytes.
+    ///
+    /// Select indices deferred by [`build_lazy`](Self::build_lazy) are only
+    /// counted once they have been built.
     #[inline]
     pub fn total_size(&self) -> usize {
         self.units.total_size()
             + self.ranks.total_size()
             + self.select0s.total_size()
             + self.select1s.total_size()
+            + self
+                .lazy_select
+                .select0s
+                .get()
+                .map_or(0, |select0s| select0s.total_size())
+            + self
+                .lazy_select
+                .select1s
+                .get()
+                .map_or(0, |select1s| select1s.total_size())
+    }
+
+    /// Returns the part of [`total_size`](Self::total_size) borrowed from
+    /// mapped memory. Lazily built select indices are always on the heap.
+    #[inline]
+    pub fn mapped_size(&self) -> usize {
+        self.units.mapped_size()
+            + self.ranks.mapped_size()
+            + self.select0s.mapped_size()
+            + self.select1s.mapped_size()
+    }
+
+    /// Returns the heap bytes reserved by the vector and its indices,
+    /// including unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn allocated_size(&self) -> usize {
+        self.units.allocated_size()
+            + self.ranks.allocated_size()
+            + self.select0s.allocated_size()
+            + self.select1s.allocated_size()
+            + self
+                .lazy_select
+                .select0s
+                .get()
+                .map_or(0, |select0s| select0s.allocated_size())
+            + self
+                .lazy_select
+                .select1s
+                .get()
+                .map_or(0, |select1s| select1s.allocated_size())
+    }
+
+    /// Releases unused capacity of the bits and built indices.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn shrink_to_fit(&mut self) {
+        self.units.shrink_unfixed();
+        self.ranks.shrink_unfixed();
+        self.select0s.shrink_unfixed();
+        self.select1s.shrink_unfixed();
     }
 
     /// Returns the I/O size needed for serialization.
+    ///
+    /// Builds any deferred select index, since it is part of the format.
     #[inline]
     pub fn io_size(&self) -> usize {
         self.units.io_size()
-            + std::mem::size_of::<u32>() * 2
+            + core::mem::size_of::<u32>() * 2
             + self.ranks.io_size()
-            + self.select0s.io_size()
-            + self.select1s.io_size()
+            + self.select0_index().io_size()
+            + self.select1_index().io_size()
     }
 
     /// Clears the bit vector.
@@ -155,11 +384,12 @@ impl BitVector {
     #[inline]
     pub fn swap(&mut self, other: &mut BitVector) {
         self.units.swap(&mut other.units);
-        std::mem::swap(&mut self.size, &mut other.size);
-        std::mem::swap(&mut self.num_1s, &mut other.num_1s);
+        core::mem::swap(&mut self.size, &mut other.size);
+        core::mem::swap(&mut self.num_1s, &mut other.num_1s);
         self.ranks.swap(&mut other.ranks);
         self.select0s.swap(&mut other.select0s);
         self.select1s.swap(&mut other.select1s);
+        core::mem::swap(&mut self.lazy_select, &mut other.lazy_select);
     }
 
     /// Maps the bit vector from a mapper.
@@ -179,19 +409,19 @@ impl BitVector {
     /// # Errors
     ///
     /// Returns an error if mapping fails or if num_1s > size.
-    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper) -> std::io::Result<()> {
+    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper<'_>) -> io::Result<()> {
         // Map units
         self.units.map(mapper)?;
 
         // Map size
-        let temp_size: u32 = mapper.map_value()?;
+        let temp_size = mapper.map_u32()?;
         self.size = temp_size as usize;
 
         // Map num_1s and validate
-        let temp_num_1s: u32 = mapper.map_value()?;
+        let temp_num_1s = mapper.map_u32()?;
         if temp_num_1s as usize > self.size {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
                 "num_1s exceeds size",
             ));
         }
@@ -201,6 +431,7 @@ impl BitVector {
         self.ranks.map(mapper)?;
         self.select0s.map(mapper)?;
         self.select1s.map(mapper)?;
+        self.lazy_select = LazySelect::default();
 
         Ok(())
     }
@@ -222,19 +453,20 @@ impl BitVector {
     /// # Errors
     ///
     /// Returns an error if reading fails or if num_1s > size.
-    pub fn read(&mut self, reader: &mut crate::grimoire::io::Reader<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn read(&mut self, reader: &mut crate::grimoire::io::Reader<'_>) -> io::Result<()> {
         // Read units
         self.units.read(reader)?;
 
         // Read size
-        let temp_size: u32 = reader.read()?;
+        let temp_size = reader.read_u32()?;
         self.size = temp_size as usize;
 
         // Read num_1s and validate
-        let temp_num_1s: u32 = reader.read()?;
+        let temp_num_1s = reader.read_u32()?;
         if temp_num_1s as usize > self.size {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
                 "num_1s exceeds size",
             ));
         }
@@ -244,6 +476,7 @@ impl BitVector {
         self.ranks.read(reader)?;
         self.select0s.read(reader)?;
         self.select1s.read(reader)?;
+        self.lazy_select = LazySelect::default();
 
         Ok(())
     }
@@ -265,32 +498,47 @@ impl BitVector {
     /// # Errors
     ///
     /// Returns an error if writing fails.
-    pub fn write(&self, writer: &mut crate::grimoire::io::Writer<'_>) -> std::io::Result<()> {
+    ///
+    /// Deferred select indices are built first so the output is identical
+    /// to that of an eagerly built vector.
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut crate::grimoire::io::Writer<'_>) -> io::Result<()> {
         // Write units
         self.units.write(writer)?;
 
         // Write size and num_1s as u32
-        writer.write(&(self.size as u32))?;
-        writer.write(&(self.num_1s as u32))?;
+        writer.write_u32(self.size as u32)?;
+        writer.write_u32(self.num_1s as u32)?;
 
         // Write rank and select indices
         self.ranks.write(writer)?;
-        self.select0s.write(writer)?;
-        self.select1s.write(writer)?;
+        self.select0_index().write(writer)?;
+        self.select1_index().write(writer)?;
 
         Ok(())
     }
 
+    /// Drops the rank and select indices, including deferred ones.
+    fn clear_indices(&mut self) {
+        self.ranks.clear();
+        self.disable_select0();
+        self.disable_select1();
+    }
+
     /// Disables the select0 index.
     #[inline]
     pub fn disable_select0(&mut self) {
         self.select0s.clear();
+        self.lazy_select.enables_select0 = false;
+        self.lazy_select.select0s = OnceLock::new();
     }
 
     /// Disables the select1 index.
     #[inline]
     pub fn disable_select1(&mut self) {
         self.select1s.clear();
+        self.lazy_select.enables_select1 = false;
+        self.lazy_select.select1s = OnceLock::new();
     }
 
     /// Returns the number of 0-bits in the range [0, i).
@@ -358,9 +606,109 @@ impl BitVector {
         offset
     }
 
+    /// Returns the number of 0-bits in the range [0, i) without the rank index.
+    ///
+    /// See [`rank1_slow`](Self::rank1_slow).
+    ///
+    /// # Panics
+    ///
+    /// Panics if i > size()
+    pub fn rank0_slow(&self, i: usize) -> usize {
+        i - self.rank1_slow(i)
+    }
+
+    /// Returns the number of 1-bits in the range [0, i) without the rank index.
+    ///
+    /// Counts bits directly over the units, so it works before
+    /// [`build`](Self::build) at O(i) cost. Meant for one-off queries in
+    /// tests, debugging and validation; use [`rank1`](Self::rank1) on hot
+    /// paths.
+    ///
+    /// # Panics
+    ///
+    /// Panics if i > size()
+    pub fn rank1_slow(&self, i: usize) -> usize {
+        assert!(i <= self.size, "Index out of bounds");
+        let units = self.units.as_slice();
+        let mut count: usize = units[..i / WORD_SIZE]
+            .iter()
+            .map(|&unit| popcount(unit))
+            .sum();
+        let bit_offset = i % WORD_SIZE;
+        if bit_offset > 0 {
+            let mask = ((1 as Unit) << bit_offset) - 1;
+            count += popcount(units[i / WORD_SIZE] & mask);
+        }
+        count
+    }
+
+    /// Computes `rank1` for many positions at once.
+    ///
+    /// When `positions` is sorted in ascending order, the units are walked
+    /// forward once: nearby positions reuse the running count and only far
+    /// jumps go back to the rank index. Unsorted input falls back to calling
+    /// [`rank1`](Self::rank1) per position. Either way `out[k]` receives
+    /// `rank1(positions[k])`.
+    ///
+    /// # Arguments
+    ///
+    /// * `positions` - Positions (exclusive) to count up to
+    /// * `out` - Output buffer, one entry per position
+    ///
+    /// # Panics
+    ///
+    /// Panics if `out.len() != positions.len()`, and under the same
+    /// conditions as `rank1`.
+    pub fn rank1_many(&self, positions: &[usize], out: &mut [usize]) {
+        assert_eq!(
+            positions.len(),
+            out.len(),
+            "out must have one entry per position"
+        );
+
+        if !positions.windows(2).all(|w| w[0] <= w[1]) {
+            for (rank, &pos) in out.iter_mut().zip(positions) {
+                *rank = self.rank1(pos);
+            }
+            return;
+        }
+
+        // `unit_rank` is rank1(unit_id * WORD_SIZE).
+        let mut unit_id = 0;
+        let mut unit_rank = 0;
+        let mut started = false;
+        for (rank, &pos) in out.iter_mut().zip(positions) {
+            debug_assert!(pos <= self.size, "Index out of bounds");
+            let target = pos / WORD_SIZE;
+
+            // Re-enter the rank index for the first position and for jumps
+            // past a rank block; otherwise popcount the units in between.
+            if !started || target >= unit_id + 8 {
+                unit_id = target;
+                unit_rank = self.rank1(target * WORD_SIZE);
+                started = true;
+            } else {
+                while unit_id < target {
+                    unit_rank += popcount(self.units[unit_id]);
+                    unit_id += 1;
+                }
+            }
+
+            let bit_offset = pos % WORD_SIZE;
+            *rank = if bit_offset > 0 {
+                let mask = ((1 as Unit) << bit_offset) - 1;
+                unit_rank + popcount(self.units[target] & mask)
+            } else {
+                unit_rank
+            };
+        }
+    }
+
     /// Builds the rank and select indices.
     ///
     /// This must be called before using rank() or select() operations.
+    /// Building again, for example after more bits were pushed, replaces
+    /// the previous indices.
     ///
     /// # Arguments
     ///
@@ -381,9 +729,116 @@ impl BitVector {
         }
     }
 
+    /// Builds the rank index now and defers the select indices.
+    ///
+    /// Each requested select index is built on the first `select0`/`select1`
+    /// call instead of up front, so a vector whose select is never used does
+    /// not pay for it in memory or build time. Writing the vector builds any
+    /// pending index, keeping the serialized form unchanged.
+    ///
+    /// # Arguments
+    ///
+    /// * `enables_select0` - Whether select0 may be used
+    /// * `enables_select1` - Whether select1 may be used
+    pub fn build_lazy(&mut self, enables_select0: bool, enables_select1: bool) {
+        self.build(false, false);
+        self.lazy_select = LazySelect {
+            enables_select0,
+            enables_select1,
+            ..LazySelect::default()
+        };
+    }
+
+    /// Returns the select0 index, building a deferred one if needed.
+    ///
+    /// Eagerly built vectors return their index after a single flag test;
+    /// only lazy vectors touch the `OnceLock`.
+    #[inline]
+    fn select0_index(&self) -> &Vector<u32> {
+        if !self.lazy_select.enables_select0 {
+            return &self.select0s;
+        }
+        match self.lazy_select.select0s.get() {
+            Some(select0s) => select0s,
+            None => self.init_select_index(false),
+        }
+    }
+
+    /// Returns the select1 index, building a deferred one if needed.
+    ///
+    /// See [`select0_index`](Self::select0_index).
+    #[inline]
+    fn select1_index(&self) -> &Vector<u32> {
+        if !self.lazy_select.enables_select1 {
+            return &self.select1s;
+        }
+        match self.lazy_select.select1s.get() {
+            Some(select1s) => select1s,
+            None => self.init_select_index(true),
+        }
+    }
+
+    /// Builds a deferred select index on its first use.
+    ///
+    /// Kept out of line so the select fast paths stay small.
+    #[cold]
+    #[inline(never)]
+    fn init_select_index(&self, bit: bool) -> &Vector<u32> {
+        let cell = if bit {
+            &self.lazy_select.select1s
+        } else {
+            &self.lazy_select.select0s
+        };
+        cell.get_or_init(|| self.build_select_index(bit))
+    }
+
+    /// Builds a select index for `bit` from the units alone.
+    ///
+    /// Produces the same samples as `build_index_internal`: the position of
+    /// every 512th matching bit, followed by `size` as a sentinel.
+    fn build_select_index(&self, bit: bool) -> Vector<u32> {
+        let num_bits = self.size;
+        assert!(
+            num_bits <= MAX_BIT_VECTOR_SIZE,
+            "BitVector size exceeds the u32 range of its select indices"
+        );
+        let mut select = Vector::new();
+        let mut count = 0usize;
+
+        for unit_id in 0..self.units.size() {
+            let bit_id = unit_id * WORD_SIZE;
+            let unit_num_1s = popcount(self.units[unit_id]);
+            let (unit, unit_count) = if bit {
+                (self.units[unit_id], unit_num_1s)
+            } else {
+                let bits_remaining = num_bits - bit_id;
+                (
+                    !self.units[unit_id],
+                    core::cmp::min(bits_remaining, WORD_SIZE) - unit_num_1s,
+                )
+            };
+
+            let target = (0usize.wrapping_sub(count)) % 512;
+            if unit_count > target {
+                select.push_back(select_bit_u64(target, bit_id, unit) as u32);
+            }
+            count += unit_count;
+        }
+
+        select.push_back(num_bits as u32);
+        select.shrink();
+        select
+    }
+
     /// Internal function to build rank/select indices in-place.
     fn build_index_internal(&mut self, enables_select0: bool, enables_select1: bool) {
+        self.clear_indices();
         let num_bits = self.size;
+        // Select entries (including the trailing sentinel) are stored as u32.
+        assert!(
+            num_bits <= MAX_BIT_VECTOR_SIZE,
+            "BitVector size exceeds the u32 range of its select indices"
+        );
 
         // Allocate ranks array
         let num_ranks = (num_bits / 512) + if (num_bits % 512) != 0 { 1 } else { 0 } + 1;
@@ -399,9 +854,22 @@ impl BitVector {
         let mut temp_ranks = Vec::new();
         temp_ranks.resize(self.ranks.size(), RankIndex::default());
 
+        // Per-unit popcounts are computed a chunk at a time so the SIMD
+        // path in popcount_units can process several units per iteration.
+        let mut unit_counts = [0u8; POPCOUNT_CHUNK_UNITS];
+
         for unit_id in 0..num_units {
             let bit_id = unit_id * WORD_SIZE;
 
+            let chunk_pos = unit_id % POPCOUNT_CHUNK_UNITS;
+            if chunk_pos == 0 {
+                let chunk_end = core::cmp::min(unit_id + POPCOUNT_CHUNK_UNITS, num_units);
+                popcount_units(
+                    &self.units.as_slice()[unit_id..chunk_end],
+                    &mut unit_counts[..chunk_end - unit_id],
+                );
+            }
+
             // Update rank index at 64-bit boundaries
             if (bit_id % 64) == 0 {
                 let rank_id = bit_id / 512;
@@ -420,11 +888,11 @@ impl BitVector {
             }
 
             let unit = self.units[unit_id];
-            let unit_num_1s = popcount_unit(unit);
+            let unit_num_1s = unit_counts[chunk_pos] as usize;
 
             if enables_select0 {
                 let bits_remaining = num_bits - bit_id;
-                let unit_num_0s = std::cmp::min(bits_remaining, WORD_SIZE) - unit_num_1s;
+                let unit_num_0s = core::cmp::min(bits_remaining, WORD_SIZE) - unit_num_1s;
 
                 // Wrapping negation to get modulo behavior
                 let zero_bit_id = (0usize.wrapping_sub(num_0s)) % 512;
@@ -505,20 +973,21 @@ impl BitVector {
     ///
     /// Panics if the select0 index is empty or if i >= num_0s()
     pub fn select0(&self, mut i: usize) -> usize {
-        debug_assert!(!self.select0s.empty(), "Select0 index not built");
+        let select0s = self.select0_index();
+        debug_assert!(!select0s.empty(), "Select0 index not built");
         debug_assert!(i < self.num_0s(), "Index out of bounds");
 
         let select_id = i / 512;
-        assert!(select_id + 1 < self.select0s.size());
+        assert!(select_id + 1 < select0s.size());
 
         // Fast path for exact 512-bit boundaries
         if (i % 512) == 0 {
-            return self.select0s[select_id] as usize;
+            return select0s[select_id] as usize;
         }
 
         // Binary/linear search to find the rank block
-        let mut begin = self.select0s[select_id] as usize / 512;
-        let mut end = (self.select0s[select_id + 1] as usize + 511) / 512;
+        let mut begin = select0s[select_id] as usize / 512;
+        let mut end = (select0s[select_id + 1] as usize + 511) / 512;
 
         if begin + 10 >= end {
             // Linear search for small ranges
@@ -592,20 +1061,21 @@ impl BitVector {
     ///
     /// Panics if the select1 index is empty or if i >= num_1s()
     pub fn select1(&self, mut i: usize) -> usize {
-        debug_assert!(!self.select1s.empty(), "Select1 index not built");
+        let select1s = self.select1_index();
+        debug_assert!(!select1s.empty(), "Select1 index not built");
         debug_assert!(i < self.num_1s(), "Index out of bounds");
 
         let select_id = i / 512;
-        assert!(select_id + 1 < self.select1s.size());
+        assert!(select_id + 1 < select1s.size());
 
         // Fast path for exact 512-bit boundaries
         if (i % 512) == 0 {
-            return self.select1s[select_id] as usize;
+            return select1s[select_id] as usize;
         }
 
         // Binary/linear search to find the rank block
-        let mut begin = self.select1s[select_id] as usize / 512;
-        let mut end = (self.select1s[select_id + 1] as usize + 511) / 512;
+        let mut begin = select1s[select_id] as usize / 512;
+        let mut end = (select1s[select_id + 1] as usize + 511) / 512;
 
         if begin + 10 >= end {
             // Linear search for small ranges
@@ -996,6 +1466,7 @@ mod tests {
         bv.select0(0); // Should panic
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_bit_vector_write_read() {
         // Rust-specific: Test BitVector serialization
@@ -1019,6 +1490,7 @@ mod tests {
         bv2.read(&mut reader).unwrap();
 
         // Verify
+        assert_eq!(bv2, bv);
         assert_eq!(bv2.size(), 100);
         assert_eq!(bv2.num_1s(), 34); // 100/3 rounded up
         for i in 0..100 {
@@ -1031,6 +1503,48 @@ mod tests {
         }
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_bit_vector_eq_round_trip() {
+        // Rust-specific: read and mapped copies compare equal to the
+        // original, though their capacity and storage differ
+        use crate::grimoire::io::{Mapper, Reader, Writer};
+
+        let mut bv = BitVector::new();
+        build_pattern(&mut bv);
+        bv.build(true, true);
+
+        let mut writer = Writer::from_vec(Vec::new());
+        bv.write(&mut writer).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let mut read = BitVector::new();
+        read.read(&mut Reader::from_bytes(&data)).unwrap();
+        assert_eq!(read, bv);
+
+        let mut mapped = BitVector::new();
+        mapped.map(&mut Mapper::open_borrowed(&data)).unwrap();
+        assert_eq!(mapped, bv);
+
+        // Unbuilt indices do not matter
+        let mut unbuilt = BitVector::new();
+        build_pattern(&mut unbuilt);
+        assert_eq!(unbuilt, bv);
+
+        let mut longer = BitVector::new();
+        build_pattern(&mut longer);
+        longer.push_back(false);
+        assert_ne!(longer, bv);
+
+        // Same size and number of 1s, bits 5 and 6 swapped
+        let bits: Vec<bool> = (0..bv.size())
+            .map(|i| bv.get(i) != (i == 5 || i == 6))
+            .collect();
+        assert_ne!(BitVector::from_bits(&bits), bv);
+        assert_eq!(BitVector::new(), BitVector::default());
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_bit_vector_write_read_empty() {
         // Rust-specific: Test empty BitVector serialization
@@ -1054,6 +1568,7 @@ mod tests {
         assert!(bv2.empty());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_bit_vector_read_invalid_num_1s() {
         // Rust-specific: Test validation of num_1s <= size
@@ -1068,8 +1583,8 @@ mod tests {
         empty_vec.write(&mut writer).unwrap();
 
         // Write size = 10, num_1s = 20 (invalid!)
-        writer.write(&10u32).unwrap();
-        writer.write(&20u32).unwrap();
+        writer.write_u32(10).unwrap();
+        writer.write_u32(20).unwrap();
 
         // Write empty rank/select vectors
         let empty_ranks: crate::grimoire::vector::vector::Vector<super::RankIndex> =
@@ -1092,4 +1607,223 @@ mod tests {
         let err = result.unwrap_err();
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
     }
+
+    fn build_pattern(bv: &mut BitVector) {
+        for i in 0..5000 {
+            bv.push_back(i % 3 == 0 || i % 7 == 0);
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_build_lazy_select() {
+        // Rust-specific: deferred select indices answer like eager ones
+        let mut eager = BitVector::new();
+        build_pattern(&mut eager);
+        eager.build(true, true);
+
+        let mut lazy = BitVector::new();
+        build_pattern(&mut lazy);
+        lazy.build_lazy(true, true);
+
+        assert_eq!(lazy.num_1s(), eager.num_1s());
+        for i in 0..eager.num_1s() {
+            assert_eq!(lazy.select1(i), eager.select1(i), "select1({})", i);
+        }
+        for i in 0..eager.num_0s() {
+            assert_eq!(lazy.select0(i), eager.select0(i), "select0({})", i);
+        }
+        assert_eq!(lazy.total_size(), eager.total_size());
+    }
+
+    #[test]
+    fn test_bit_vector_build_lazy_total_size() {
+        // Rust-specific: unused deferred indices cost no memory
+        let mut eager = BitVector::new();
+        build_pattern(&mut eager);
+        eager.build(true, true);
+
+        let mut lazy = BitVector::new();
+        build_pattern(&mut lazy);
+        lazy.build_lazy(true, true);
+
+        assert!(lazy.total_size() < eager.total_size());
+        assert_eq!(lazy.rank1(4096), eager.rank1(4096));
+
+        lazy.select1(0);
+        assert!(lazy.total_size() < eager.total_size());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_bit_vector_build_lazy_write() {
+        // Rust-specific: serialized form does not depend on build_lazy
+        use crate::grimoire::io::Writer;
+
+        let mut eager = BitVector::new();
+        build_pattern(&mut eager);
+        eager.build(true, true);
+
+        let mut lazy = BitVector::new();
+        build_pattern(&mut lazy);
+        lazy.build_lazy(true, true);
+
+        let mut eager_writer = Writer::from_vec(Vec::new());
+        eager.write(&mut eager_writer).unwrap();
+        let mut lazy_writer = Writer::from_vec(Vec::new());
+        lazy.write(&mut lazy_writer).unwrap();
+
+        assert_eq!(lazy.io_size(), eager.io_size());
+        assert_eq!(
+            lazy_writer.into_inner().unwrap(),
+            eager_writer.into_inner().unwrap()
+        );
+    }
+
+    #[test]
+    fn test_bit_vector_from_bits_matches_push_back() {
+        // Rust-specific: bulk construction is identical to push_back
+        for len in [0usize, 1, 63, 64, 65, 1000] {
+            let bits: Vec<bool> = (0..len).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+
+            let mut pushed = BitVector::new();
+            for &bit in &bits {
+                pushed.push_back(bit);
+            }
+            let mut from_bits = BitVector::from_bits(&bits);
+            let mut collected: BitVector = bits.iter().copied().collect();
+
+            for bv in [&from_bits, &collected] {
+                assert_eq!(bv.units.as_slice(), pushed.units.as_slice());
+                assert_eq!(bv.size(), pushed.size());
+                assert_eq!(bv.num_1s(), pushed.num_1s());
+            }
+
+            pushed.build(true, true);
+            from_bits.build(true, true);
+            collected.build(true, true);
+            for i in 0..=len {
+                assert_eq!(from_bits.rank1(i), pushed.rank1(i));
+                assert_eq!(collected.rank1(i), pushed.rank1(i));
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_ones_zeros() {
+        // Rust-specific: set/unset positions agree with get()
+        for len in [0usize, 1, 64, 130, 1000] {
+            let bits: Vec<bool> = (0..len).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+            let bv = BitVector::from_bits(&bits);
+
+            let ones: Vec<usize> = bv.ones().collect();
+            let zeros: Vec<usize> = bv.zeros().collect();
+            assert_eq!(ones.len(), bv.num_1s());
+            assert_eq!(zeros.len(), bv.num_0s());
+            assert!(ones.windows(2).all(|w| w[0] < w[1]));
+            assert!(zeros.windows(2).all(|w| w[0] < w[1]));
+            assert!(ones.iter().all(|&i| bv.get(i)));
+            assert!(zeros.iter().all(|&i| !bv.get(i)));
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_push_after_build() {
+        // Rust-specific: pushing onto a built vector drops its indices, and
+        // building again indexes every bit; repeated builds do not stack
+        // select samples
+        let bit_at = |i: usize| (i * 7 + i / 300) % 5 < 2;
+        let check = |bv: &BitVector, len: usize| {
+            let mut num_1s = 0;
+            for i in 0..len {
+                assert_eq!(bv.rank1(i), num_1s, "rank1({})", i);
+                if bit_at(i) {
+                    assert_eq!(bv.select1(num_1s), i);
+                    num_1s += 1;
+                } else {
+                    assert_eq!(bv.select0(i - num_1s), i);
+                }
+            }
+            assert_eq!(bv.rank1(len), num_1s);
+            assert_eq!(bv.num_1s(), num_1s);
+        };
+
+        let mut bv = BitVector::new();
+        for lazy in [false, true] {
+            let start = bv.size();
+            for len in [start + 1000, start + 4100] {
+                while bv.size() < len {
+                    bv.push_back(bit_at(bv.size()));
+                }
+                if lazy {
+                    bv.build_lazy(true, true);
+                } else {
+                    bv.build(true, true);
+                    bv.build(true, true);
+                }
+                check(&bv, len);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_rank1_many() {
+        // Rust-specific: batch ranks equal individual rank1 calls
+        let bits: Vec<bool> = (0..5000).map(|i| i % 3 == 0 || i % 7 == 0).collect();
+        let mut bv = BitVector::from_bits(&bits);
+        bv.build(false, false);
+
+        let sorted: Vec<usize> = (0..=5000).step_by(7).chain([5000, 5000]).collect();
+        let sparse = vec![0, 1, 64, 65, 600, 4095, 4096, 5000];
+        let unsorted = vec![4000, 3, 5000, 64, 0, 2500];
+
+        for positions in [sorted, sparse, unsorted, Vec::new()] {
+            let mut out = vec![usize::MAX; positions.len()];
+            bv.rank1_many(&positions, &mut out);
+            for (k, &pos) in positions.iter().enumerate() {
+                assert_eq!(out[k], bv.rank1(pos), "rank1({})", pos);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bit_vector_rank_slow_matches_rank() {
+        // Rust-specific: the index-free rank agrees with rank1/rank0 and
+        // also works before build
+        let bits: Vec<bool> = (0..3000)
+            .map(|i| (i * 37) % 11 < 4 || i % 64 == 63)
+            .collect();
+        let mut bv = BitVector::from_bits(&bits);
+
+        let mut expected = 0;
+        for (i, &bit) in bits.iter().enumerate() {
+            assert_eq!(bv.rank1_slow(i), expected, "rank1_slow({})", i);
+            expected += usize::from(bit);
+        }
+        assert_eq!(bv.rank1_slow(bits.len()), expected);
+
+        bv.build(false, false);
+        for i in 0..=bits.len() {
+            assert_eq!(bv.rank1_slow(i), bv.rank1(i), "rank1({})", i);
+            assert_eq!(bv.rank0_slow(i), bv.rank0(i), "rank0({})", i);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "BitVector size cannot exceed u32::MAX")]
+    fn test_bit_vector_push_back_at_max_size() {
+        // Rust-specific: size is stubbed instead of allocating 512MB of units
+        let mut bv = BitVector::new();
+        bv.size = BitVector::max_size();
+        bv.push_back(true);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds the u32 range of its select indices")]
+    fn test_bit_vector_build_beyond_max_size() {
+        // Rust-specific: a size past the u32 range (e.g. from corrupt input)
+        // must not be truncated into the select index
+        let mut bv = BitVector::from_bits(&[true, false]);
+        bv.size = BitVector::max_size() + 1;
+        bv.build(true, true);
+    }
 }
@@ -8,6 +8,8 @@
 
 use super::vector::Vector;
 use crate::base::WORD_SIZE;
+use crate::grimoire::io;
+use core::fmt;
 
 // rsmarisa fixes the bit-vector word at 64 bits on every target
 // (see `crate::base::WORD_SIZE`), so the unit is always `u64`.
@@ -30,6 +32,50 @@ pub struct FlatVector {
     size: usize,
 }
 
+/// Number of decoded values shown by `FlatVector`'s `Debug` output.
+const DEBUG_VALUES: usize = 8;
+
+/// Compares the packed values.
+///
+/// Capacity and whether the storage is owned or mapped do not matter.
+///
+/// Rust-specific: Not present in C++ version.
+impl PartialEq for FlatVector {
+    fn eq(&self, other: &Self) -> bool {
+        self.size == other.size
+            && self.value_size == other.value_size
+            && self.mask == other.mask
+            && self.units.as_slice() == other.units.as_slice()
+    }
+}
+
+impl Eq for FlatVector {}
+
+/// Shows the size, the value width and the first few decoded values.
+impl fmt::Debug for FlatVector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        struct Head<'a>(&'a FlatVector);
+
+        impl fmt::Debug for Head<'_> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                let shown = self.0.size.min(DEBUG_VALUES);
+                let mut list = f.debug_list();
+                list.entries((0..shown).map(|i| self.0.get(i)));
+                if self.0.size > shown {
+                    list.entry(&format_args!(".."));
+                }
+                list.finish()
+            }
+        }
+
+        f.debug_struct("FlatVector")
+            .field("size", &self.size)
+            .field("value_size", &self.value_size)
+            .field("values", &Head(self))
+            .finish()
+    }
+}
+
 impl FlatVector {
     /// Creates a new empty flat vector.
     #[inline]
@@ -88,6 +134,59 @@ impl FlatVector {
         ((lo | hi) as u32) & self.mask
     }
 
+    /// Decodes `len` consecutive values starting at `start` into `out`.
+    ///
+    /// Equivalent to calling [`get`](Self::get) for each index, but walks the
+    /// packed units once: the current unit and bit offset carry over from one
+    /// value to the next, and the next unit is only read when a value spans
+    /// a unit boundary.
+    ///
+    /// # Arguments
+    ///
+    /// * `start` - Index of the first value to decode
+    /// * `len` - Number of values to decode
+    /// * `out` - Destination; the first `len` entries are overwritten
+    ///
+    /// # Panics
+    ///
+    /// Panics if `start + len > size()` or if `out.len() < len`.
+    pub fn get_range(&self, start: usize, len: usize, out: &mut [u32]) {
+        assert!(
+            start <= self.size && len <= self.size - start,
+            "Range out of bounds"
+        );
+        assert!(out.len() >= len, "Output buffer too small");
+
+        let out = &mut out[..len];
+        if self.value_size == 0 {
+            out.fill(0);
+            return;
+        }
+
+        let units = self.units.as_slice();
+        let pos = start * self.value_size;
+        let mut unit_id = pos / WORD_SIZE;
+        let mut unit_offset = pos % WORD_SIZE;
+
+        for value in out.iter_mut() {
+            let lo = units[unit_id] >> unit_offset;
+            let end = unit_offset + self.value_size;
+            let hi = if unit_offset != 0 && end > WORD_SIZE {
+                units[unit_id + 1] << (WORD_SIZE - unit_offset)
+            } else {
+                0
+            };
+            *value = ((lo | hi) as u32) & self.mask;
+
+            if end >= WORD_SIZE {
+                unit_id += 1;
+                unit_offset = end - WORD_SIZE;
+            } else {
+                unit_offset = end;
+            }
+        }
+    }
+
     /// Returns the number of bits per value.
     #[inline]
     pub fn value_size(&self) -> usize {
@@ -118,10 +217,33 @@ impl FlatVector {
         self.units.total_size()
     }
 
+    /// Returns the part of [`total_size`](Self::total_size) borrowed from
+    /// mapped memory.
+    #[inline]
+    pub fn mapped_size(&self) -> usize {
+        self.units.mapped_size()
+    }
+
+    /// Returns the heap bytes reserved, including unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    #[inline]
+    pub fn allocated_size(&self) -> usize {
+        self.units.allocated_size()
+    }
+
+    /// Releases unused capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.units.shrink_unfixed();
+    }
+
     /// Returns the I/O size needed for serialization.
     #[inline]
     pub fn io_size(&self) -> usize {
-        self.units.io_size() + std::mem::size_of::<u32>() * 2 + std::mem::size_of::<u64>()
+        self.units.io_size() + core::mem::size_of::<u32>() * 2 + core::mem::size_of::<u64>()
     }
 
     /// Clears the flat vector.
@@ -133,9 +255,9 @@ impl FlatVector {
     /// Swaps the contents of two flat vectors.
     pub fn swap(&mut self, other: &mut FlatVector) {
         self.units.swap(&mut other.units);
-        std::mem::swap(&mut self.value_size, &mut other.value_size);
-        std::mem::swap(&mut self.mask, &mut other.mask);
-        std::mem::swap(&mut self.size, &mut other.size);
+        core::mem::swap(&mut self.value_size, &mut other.value_size);
+        core::mem::swap(&mut self.mask, &mut other.mask);
+        core::mem::swap(&mut self.size, &mut other.size);
     }
 
     /// Maps the flat vector from a mapper.
@@ -153,26 +275,25 @@ impl FlatVector {
     /// # Errors
     ///
     /// Returns an error if mapping fails or if value_size > 32.
-    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper) -> std::io::Result<()> {
+    pub fn map(&mut self, mapper: &mut crate::grimoire::io::Mapper<'_>) -> io::Result<()> {
         // Map units
         self.units.map(mapper)?;
 
         // Map value_size and validate
-        let temp_value_size: u32 = mapper.map_value()?;
+        let temp_value_size = mapper.map_u32()?;
         if temp_value_size > 32 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
                 "value_size exceeds 32",
             ));
         }
         self.value_size = temp_value_size as usize;
 
         // Map mask
-        let temp_mask: u32 = mapper.map_value()?;
-        self.mask = temp_mask;
+        self.mask = mapper.map_u32()?;
 
         // Map size
-        let temp_size: u64 = mapper.map_value()?;
+        let temp_size = mapper.map_u64()?;
         self.size = temp_size as usize;
 
         Ok(())
@@ -193,26 +314,26 @@ impl FlatVector {
     /// # Errors
     ///
     /// Returns an error if reading fails or if value_size > 32.
-    pub fn read(&mut self, reader: &mut crate::grimoire::io::Reader<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn read(&mut self, reader: &mut crate::grimoire::io::Reader<'_>) -> io::Result<()> {
         // Read units
         self.units.read(reader)?;
 
         // Read value_size and validate
-        let temp_value_size: u32 = reader.read()?;
+        let temp_value_size = reader.read_u32()?;
         if temp_value_size > 32 {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
                 "value_size exceeds 32",
             ));
         }
         self.value_size = temp_value_size as usize;
 
         // Read mask
-        let temp_mask: u32 = reader.read()?;
-        self.mask = temp_mask;
+        self.mask = reader.read_u32()?;
 
         // Read size
-        let temp_size: u64 = reader.read()?;
+        let temp_size = reader.read_u64()?;
         self.size = temp_size as usize;
 
         Ok(())
@@ -233,14 +354,15 @@ impl FlatVector {
     /// # Errors
     ///
     /// Returns an error if writing fails.
-    pub fn write(&self, writer: &mut crate::grimoire::io::Writer<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut crate::grimoire::io::Writer<'_>) -> io::Result<()> {
         // Write units
         self.units.write(writer)?;
 
         // Write value_size, mask, size
-        writer.write(&(self.value_size as u32))?;
-        writer.write(&self.mask)?;
-        writer.write(&(self.size as u64))?;
+        writer.write_u32(self.value_size as u32)?;
+        writer.write_u32(self.mask)?;
+        writer.write_u64(self.size as u64)?;
 
         Ok(())
     }
@@ -510,6 +632,7 @@ mod tests {
         assert!(fv.empty());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_flat_vector_write_read() {
         // Rust-specific: Test FlatVector serialization
@@ -535,12 +658,59 @@ mod tests {
         fv2.read(&mut reader).unwrap();
 
         // Verify
+        assert_eq!(fv2, fv);
         assert_eq!(fv2.size(), 100);
         for i in 0..100usize {
             assert_eq!(fv2.get(i), ((i as u32 * 7) % 256));
         }
     }
 
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_flat_vector_eq_and_debug() {
+        // Rust-specific: a round-tripped vector compares equal, and Debug
+        // shows only the first values
+        use crate::grimoire::io::{Mapper, Reader, Writer};
+
+        let values: Vector<u32> = (0..1000u32)
+            .map(|i| i * 37 % 1000)
+            .collect::<Vec<_>>()
+            .into();
+        let mut fv = FlatVector::new();
+        fv.build(&values);
+
+        let mut writer = Writer::from_vec(Vec::new());
+        fv.write(&mut writer).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let mut read = FlatVector::new();
+        read.read(&mut Reader::from_bytes(&data)).unwrap();
+        assert_eq!(read, fv);
+        let mut mapped = FlatVector::new();
+        mapped.map(&mut Mapper::open_borrowed(&data)).unwrap();
+        assert_eq!(mapped, fv);
+
+        let mut changed = values.as_slice().to_vec();
+        changed[500] += 1;
+        let mut other = FlatVector::new();
+        other.build(&changed.into());
+        assert_ne!(other, fv);
+        assert_eq!(FlatVector::new(), FlatVector::default());
+
+        assert_eq!(
+            format!("{:?}", fv),
+            "FlatVector { size: 1000, value_size: 10, \
+             values: [0, 37, 74, 111, 148, 185, 222, 259, ..] }"
+        );
+        let mut short = FlatVector::new();
+        short.build(&vec![3, 1, 2].into());
+        assert_eq!(
+            format!("{:?}", short),
+            "FlatVector { size: 3, value_size: 2, values: [3, 1, 2] }"
+        );
+    }
+
+    #[cfg(feature = "std")]
     #[test]
     fn test_flat_vector_write_read_empty() {
         // Rust-specific: Test empty FlatVector serialization
@@ -563,6 +733,7 @@ mod tests {
         assert!(fv2.empty());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_flat_vector_read_invalid_value_size() {
         // Rust-specific: Test validation of value_size <= 32
@@ -577,9 +748,9 @@ mod tests {
         empty_vec.write(&mut writer).unwrap();
 
         // Write value_size = 40 (invalid!), mask = 0, size = 0
-        writer.write(&40u32).unwrap();
-        writer.write(&0u32).unwrap();
-        writer.write(&0u64).unwrap();
+        writer.write_u32(40).unwrap();
+        writer.write_u32(0).unwrap();
+        writer.write_u64(0).unwrap();
 
         let data = writer.into_inner().unwrap();
 
@@ -592,4 +763,57 @@ mod tests {
         let err = result.unwrap_err();
         assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
     }
+
+    #[test]
+    fn test_flat_vector_get_range_matches_get() {
+        // Rust-specific: bulk decode agrees with get() for every bit width
+        for bits in 1..=32u32 {
+            let max = u32::MAX >> (32 - bits);
+            let mut vec = Vector::new();
+            for i in 0..300u32 {
+                let value = i.wrapping_mul(2_654_435_761) & max;
+                vec.push_back(if i == 0 { max } else { value });
+            }
+
+            let mut fv = FlatVector::new();
+            fv.build(&vec);
+            assert_eq!(fv.value_size(), bits as usize);
+
+            for &(start, len) in &[(0, 300), (1, 299), (63, 70), (137, 1), (300, 0)] {
+                let mut out = vec![u32::MAX; len];
+                fv.get_range(start, len, &mut out);
+                for (j, &value) in out.iter().enumerate() {
+                    assert_eq!(value, fv.get(start + j), "bits={} i={}", bits, start + j);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_flat_vector_get_range_all_zeros() {
+        // Rust-specific: zero-width values decode without touching units
+        let mut vec = Vector::new();
+        for _ in 0..10 {
+            vec.push_back(0u32);
+        }
+        let mut fv = FlatVector::new();
+        fv.build(&vec);
+
+        let mut out = [7u32; 10];
+        fv.get_range(2, 8, &mut out);
+        assert_eq!(&out[..8], &[0; 8]);
+        assert_eq!(&out[8..], &[7, 7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Range out of bounds")]
+    fn test_flat_vector_get_range_out_of_bounds() {
+        let mut vec = Vector::new();
+        vec.push_back(1u32);
+        let mut fv = FlatVector::new();
+        fv.build(&vec);
+
+        let mut out = [0u32; 2];
+        fv.get_range(0, 2, &mut out);
+    }
 }
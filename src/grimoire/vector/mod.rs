@@ -5,12 +5,15 @@
 //! This module provides specialized vector implementations:
 //! - Bit vector: compact binary data with rank/select operations
 //! - Flat vector: space-efficient integer vector
+//! - Once lock: write-once cell for `no_std` builds
 //! - Pop count: population count utilities
 //! - Rank index: rank operation acceleration
 //! - Select tables: lookup tables for select operations
 
 pub mod bit_vector;
 pub mod flat_vector;
+#[cfg(any(not(feature = "std"), test))]
+pub(crate) mod once_lock;
 pub mod pop_count;
 pub mod rank_index;
 pub mod select_bit;
@@ -0,0 +1,137 @@
+//! Write-once cell for `no_std` builds.
+//!
+//! Rust-specific: Not present in C++ version.
+//!
+//! [`BitVector`](super::bit_vector::BitVector) defers its select indices,
+//! and `LoudsTrie` caches the total key length returned by
+//! `Trie::total_key_bytes`, in a `std::sync::OnceLock`, which `core` does
+//! not provide. Without the `std` feature this stand-in supplies the part
+//! of its API that these users need. Threads racing to initialize it may
+//! each build a value; the first one published is kept and the rest are
+//! dropped, which is harmless because both values are pure functions of
+//! the data they are computed from.
+
+use alloc::boxed::Box;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+
+/// A cell that is written at most once, readable from any thread.
+pub(crate) struct OnceLock<T> {
+    /// Null until initialized, then a pointer from `Box::into_raw`.
+    ptr: AtomicPtr<T>,
+}
+
+// SAFETY: the cell owns its value like a `Box<T>`, and shared access only
+// ever hands out `&T`, matching the bounds of `std::sync::OnceLock`.
+unsafe impl<T: Send> Send for OnceLock<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+    /// Creates an empty cell.
+    pub(crate) const fn new() -> Self {
+        OnceLock {
+            ptr: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the value, if the cell has been initialized.
+    pub(crate) fn get(&self) -> Option<&T> {
+        let ptr = self.ptr.load(Ordering::Acquire);
+        // SAFETY: a non-null pointer was published by `get_or_init` and is
+        // only freed by `drop`, which needs `&mut self`.
+        unsafe { ptr.as_ref() }
+    }
+
+    /// Returns the value, initializing the cell with `init` if it is empty.
+    pub(crate) fn get_or_init<F: FnOnce() -> T>(&self, init: F) -> &T {
+        if let Some(value) = self.get() {
+            return value;
+        }
+        let new = Box::into_raw(Box::new(init()));
+        match self
+            .ptr
+            .compare_exchange(ptr::null_mut(), new, Ordering::AcqRel, Ordering::Acquire)
+        {
+            // SAFETY: `new` is now published and lives until `drop`.
+            Ok(_) => unsafe { &*new },
+            Err(existing) => {
+                // SAFETY: another thread won the race, so `new` was never
+                // shared and `existing` lives until `drop`.
+                drop(unsafe { Box::from_raw(new) });
+                unsafe { &*existing }
+            }
+        }
+    }
+}
+
+impl<T> From<T> for OnceLock<T> {
+    /// Creates a cell already holding `value`.
+    fn from(value: T) -> Self {
+        OnceLock {
+            ptr: AtomicPtr::new(Box::into_raw(Box::new(value))),
+        }
+    }
+}
+
+impl<T> Default for OnceLock<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Drop for OnceLock<T> {
+    fn drop(&mut self) {
+        let ptr = *self.ptr.get_mut();
+        if !ptr.is_null() {
+            // SAFETY: the pointer came from `Box::into_raw` and, with
+            // `&mut self`, no reference to the value can remain.
+            drop(unsafe { Box::from_raw(ptr) });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_once_lock_get_or_init() {
+        // Rust-specific: the first initializer wins and later ones are not run
+        let cell = OnceLock::new();
+        assert_eq!(cell.get(), None);
+        assert_eq!(*cell.get_or_init(|| vec![1u32, 2]), [1, 2]);
+        assert_eq!(*cell.get_or_init(|| unreachable!()), [1, 2]);
+        assert_eq!(cell.get().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn test_once_lock_race_drops_losers() {
+        // Rust-specific: racing threads agree on one value, and every value
+        // built, published or not, is dropped exactly once
+        struct Counted(Arc<AtomicUsize>, usize);
+        impl Drop for Counted {
+            fn drop(&mut self) {
+                self.0.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let drops = Arc::new(AtomicUsize::new(0));
+        let cell = OnceLock::new();
+        let seen: Vec<usize> = std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|i| {
+                    let (cell, drops) = (&cell, &drops);
+                    scope.spawn(move || cell.get_or_init(|| Counted(Arc::clone(drops), i)).1)
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+        assert!(seen.iter().all(|&i| i == seen[0]));
+
+        let built = drops.load(Ordering::Relaxed) + 1;
+        drop(cell);
+        assert_eq!(drops.load(Ordering::Relaxed), built);
+    }
+}
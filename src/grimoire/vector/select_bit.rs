@@ -12,18 +12,18 @@
 
 use super::select_tables::SELECT_TABLE;
 
-#[cfg(target_arch = "x86_64")]
-use std::sync::atomic::{AtomicU8, Ordering};
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
+use core::sync::atomic::{AtomicU8, Ordering};
 
 /// Cached BMI2 (PDEP) detection result.
 ///
 /// 0 = unknown (not probed yet), 1 = available, 2 = unavailable.
 /// `Relaxed` is sufficient: the worst case is multiple threads racing the
 /// initial probe, all writing the same value.
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
 static BMI2_AVAILABLE: AtomicU8 = AtomicU8::new(0);
 
-#[cfg(target_arch = "x86_64")]
+#[cfg(all(target_arch = "x86_64", feature = "std"))]
 #[inline]
 fn has_bmi2() -> bool {
     match BMI2_AVAILABLE.load(Ordering::Relaxed) {
@@ -37,6 +37,13 @@ fn has_bmi2() -> bool {
     }
 }
 
+/// Without `std` there is no runtime detection; this build lacks BMI2.
+#[cfg(all(target_arch = "x86_64", not(feature = "std")))]
+#[inline]
+fn has_bmi2() -> bool {
+    false
+}
+
 /// PDEP-based select within a 64-bit word.
 ///
 /// Places `1 << i` into the i-th set bit position of `unit` and returns
@@ -172,4 +179,64 @@ mod tests {
             }
         }
     }
+
+    /// Positions of the set bits of `unit`, lowest first.
+    fn set_bit_positions(unit: u64) -> Vec<usize> {
+        (0..64).filter(|&pos| unit >> pos & 1 == 1).collect()
+    }
+
+    #[test]
+    fn test_select_bit_u64_every_position() {
+        // Rust-specific: every rank of every test word, including words
+        // whose set bits are all in the upper 32 bits
+        let mut units = vec![
+            0,
+            1,
+            1u64 << 63,
+            u64::MAX,
+            0xFFFF_FFFF_0000_0000,
+            0x0000_0000_FFFF_FFFF,
+            0x8000_0001_0000_0000,
+            0x0000_0001_0000_0000,
+            0x0101_0101_0101_0101,
+            0x8080_8080_8080_8080,
+        ];
+        // Single bits and adjacent pairs at every position
+        for pos in 0..64 {
+            units.push(1u64 << pos);
+            units.push(3u64.rotate_left(pos));
+            units.push(!(1u64 << pos));
+        }
+        // Deterministic pseudo-random words, also shifted into the high half
+        let mut x = 0x9E37_79B9_7F4A_7C15u64;
+        for _ in 0..200 {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            units.push(x);
+            units.push(x & 0xFFFF_FFFF_0000_0000);
+            units.push(x & x.rotate_left(21));
+        }
+
+        for unit in units {
+            for (i, &pos) in set_bit_positions(unit).iter().enumerate() {
+                for bit_id in [0, 64, 1 << 20] {
+                    assert_eq!(
+                        select_bit_u64(i, bit_id, unit),
+                        bit_id + pos,
+                        "dispatch i={} unit={:#x}",
+                        i,
+                        unit
+                    );
+                    assert_eq!(
+                        select_bit_u64_table(i, bit_id, unit),
+                        bit_id + pos,
+                        "table i={} unit={:#x}",
+                        i,
+                        unit
+                    );
+                }
+            }
+        }
+    }
 }
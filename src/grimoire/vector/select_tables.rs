@@ -160,6 +160,48 @@ pub const SELECT_TABLE: [[u8; 256]; 8] = [
   ],
 ];
 
+/// Computes `SELECT_TABLE` from its definition.
+///
+/// Entry `[i][byte]` is the position of the i-th set bit of `byte`, or 7
+/// when `byte` has fewer than i+1 set bits.
+///
+/// Rust-specific: Not present in C++ version.
+const fn generate_select_table() -> [[u8; 256]; 8] {
+    let mut table = [[7u8; 256]; 8];
+    let mut byte = 0;
+    while byte < 256 {
+        let mut rank = 0;
+        let mut pos = 0;
+        while pos < 8 {
+            if (byte >> pos) & 1 == 1 {
+                table[rank][byte] = pos as u8;
+                rank += 1;
+            }
+            pos += 1;
+        }
+        byte += 1;
+    }
+    table
+}
+
+/// Compile-time check that the transcribed table matches its definition,
+/// so a transcription error fails the build instead of a rare select query.
+const _: () = {
+    let expected = generate_select_table();
+    let mut i = 0;
+    while i < 8 {
+        let mut byte = 0;
+        while byte < 256 {
+            assert!(
+                SELECT_TABLE[i][byte] == expected[i][byte],
+                "SELECT_TABLE differs from its definition"
+            );
+            byte += 1;
+        }
+        i += 1;
+    }
+};
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -193,4 +235,18 @@ mod tests {
         assert_eq!(SELECT_TABLE[1][0b00000001], 7); // Only 1 bit, can't find 2nd
         assert_eq!(SELECT_TABLE[2][0b00000011], 7); // Only 2 bits, can't find 3rd
     }
+
+    #[test]
+    fn test_select_table_matches_definition() {
+        // Rust-specific: every entry for all 256 byte values agrees with a
+        // direct scan of the byte's bits
+        for byte in 0..=255u8 {
+            let positions: Vec<u8> = (0..8).filter(|&pos| byte >> pos & 1 == 1).collect();
+            for (i, row) in SELECT_TABLE.iter().enumerate() {
+                let expected = positions.get(i).copied().unwrap_or(7);
+                assert_eq!(row[byte as usize], expected, "[{}][{}]", i, byte);
+            }
+        }
+        assert_eq!(generate_select_table(), SELECT_TABLE);
+    }
 }
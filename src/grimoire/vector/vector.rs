@@ -5,28 +5,77 @@
 //! This module provides a custom vector implementation that supports
 //! serialization and memory mapping operations.
 
-use crate::grimoire::io::{Mapper, Reader, Writer};
+use crate::grimoire::io::{self, Mapper};
+#[cfg(feature = "std")]
+use crate::grimoire::io::{Reader, Writer};
+use alloc::vec::Vec;
+
+/// Backing storage of a [`Vector`].
+enum Storage<T> {
+    /// Heap-allocated elements owned by the vector.
+    Owned(Vec<T>),
+    /// Elements borrowed from a [`Mapper`] without copying.
+    ///
+    /// The memory is a `'static` slice, or belongs to a mapper that was
+    /// marked with `Mapper::share` and that the owner of the vector keeps
+    /// alive for as long as the vector exists.
+    Mapped { ptr: *const T, len: usize },
+}
 
 /// Generic vector for internal use with serialization support.
 ///
 /// This vector is similar to std::Vec but with additional features
 /// for memory mapping and serialization. It uses Copy/Clone trait
 /// bounds to ensure safe serialization.
+///
+/// A vector filled by [`map`](Self::map) refers to the mapped memory
+/// directly and is fixed: reads work as usual, modifications panic.
 pub struct Vector<T: Copy> {
-    data: Vec<T>,
+    storage: Storage<T>,
     fixed: bool,
 }
 
+// SAFETY: mapped storage is a read-only view of memory that is never
+// mutated through the vector, so sharing or sending it is as safe as for
+// `&[T]`; owned storage behaves like `Vec<T>`.
+unsafe impl<T: Copy + Send + Sync> Send for Vector<T> {}
+unsafe impl<T: Copy + Sync> Sync for Vector<T> {}
+
 impl<T: Copy> Vector<T> {
     /// Creates a new empty vector.
     #[inline]
     pub fn new() -> Self {
         Vector {
-            data: Vec::new(),
+            storage: Storage::Owned(Vec::new()),
+            fixed: false,
+        }
+    }
+
+    /// Creates a vector that takes ownership of `data` without copying.
+    ///
+    /// The resulting vector is not fixed.
+    #[inline]
+    pub fn from_vec(data: Vec<T>) -> Self {
+        Vector {
+            storage: Storage::Owned(data),
             fixed: false,
         }
     }
 
+    /// Returns the owned storage for modification.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the vector is fixed (mapped vectors are always fixed).
+    #[inline]
+    fn owned_mut(&mut self) -> &mut Vec<T> {
+        assert!(!self.fixed, "Cannot modify fixed vector");
+        match &mut self.storage {
+            Storage::Owned(data) => data,
+            Storage::Mapped { .. } => unreachable!("mapped vectors are fixed"),
+        }
+    }
+
     /// Pushes a value onto the end of the vector.
     ///
     /// # Panics
@@ -34,8 +83,7 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is fixed.
     #[inline]
     pub fn push_back(&mut self, value: T) {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.push(value);
+        self.owned_mut().push(value);
     }
 
     /// Removes the last element from the vector.
@@ -45,9 +93,9 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is empty or fixed.
     #[inline]
     pub fn pop_back(&mut self) {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        assert!(!self.data.is_empty(), "Cannot pop from empty vector");
-        self.data.pop();
+        let data = self.owned_mut();
+        assert!(!data.is_empty(), "Cannot pop from empty vector");
+        data.pop();
     }
 
     /// Resizes the vector to the given size, filling with default values.
@@ -57,8 +105,7 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is fixed.
     #[inline]
     pub fn resize(&mut self, size: usize, value: T) {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.resize(size, value);
+        self.owned_mut().resize(size, value);
     }
 
     /// Reserves capacity for at least `additional` more elements.
@@ -68,15 +115,13 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is fixed.
     #[inline]
     pub fn reserve(&mut self, capacity: usize) {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.reserve(capacity);
+        self.owned_mut().reserve(capacity);
     }
 
     /// Shrinks the capacity to match the size.
     #[inline]
     pub fn shrink(&mut self) {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.shrink_to_fit();
+        self.owned_mut().shrink_to_fit();
     }
 
     /// Fixes the vector, preventing further modifications.
@@ -88,19 +133,27 @@ impl<T: Copy> Vector<T> {
     /// Returns the number of elements in the vector.
     #[inline]
     pub fn size(&self) -> usize {
-        self.data.len()
+        match &self.storage {
+            Storage::Owned(data) => data.len(),
+            Storage::Mapped { len, .. } => *len,
+        }
     }
 
     /// Returns the capacity of the vector.
+    ///
+    /// For a mapped vector this equals [`size`](Self::size).
     #[inline]
     pub fn capacity(&self) -> usize {
-        self.data.capacity()
+        match &self.storage {
+            Storage::Owned(data) => data.capacity(),
+            Storage::Mapped { len, .. } => *len,
+        }
     }
 
     /// Returns true if the vector is empty.
     #[inline]
     pub fn empty(&self) -> bool {
-        self.data.is_empty()
+        self.size() == 0
     }
 
     /// Returns true if the vector is fixed.
@@ -109,22 +162,63 @@ impl<T: Copy> Vector<T> {
         self.fixed
     }
 
+    /// Returns true if the elements are borrowed from mapped memory.
+    #[inline]
+    pub fn mapped(&self) -> bool {
+        matches!(self.storage, Storage::Mapped { .. })
+    }
+
     /// Returns the total size in bytes.
     #[inline]
     pub fn total_size(&self) -> usize {
-        std::mem::size_of::<T>() * self.data.len()
+        core::mem::size_of::<T>() * self.size()
+    }
+
+    /// Returns the part of [`total_size`](Self::total_size) borrowed from
+    /// mapped memory rather than allocated on the heap.
+    #[inline]
+    pub fn mapped_size(&self) -> usize {
+        if self.mapped() {
+            self.total_size()
+        } else {
+            0
+        }
+    }
+
+    /// Returns the heap bytes reserved by the vector, including unused
+    /// capacity. Mapped elements are not on the heap and count as 0.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    #[inline]
+    pub fn allocated_size(&self) -> usize {
+        match &self.storage {
+            Storage::Owned(data) => core::mem::size_of::<T>() * data.capacity(),
+            Storage::Mapped { .. } => 0,
+        }
+    }
+
+    /// Shrinks the capacity to match the size unless the vector is fixed.
+    ///
+    /// Fixed vectors were read or mapped and have no spare capacity.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    #[inline]
+    pub(crate) fn shrink_unfixed(&mut self) {
+        if !self.fixed {
+            self.shrink();
+        }
     }
 
     /// Returns the I/O size needed for serialization.
     #[inline]
     pub fn io_size(&self) -> usize {
-        std::mem::size_of::<u64>() + ((self.total_size() + 7) & !0x07)
+        core::mem::size_of::<u64>() + ((self.total_size() + 7) & !0x07)
     }
 
     /// Accesses an element by index (const version).
     #[inline]
     pub fn get(&self, index: usize) -> Option<&T> {
-        self.data.get(index)
+        self.as_slice().get(index)
     }
 
     /// Accesses an element by index (mutable version).
@@ -134,27 +228,31 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is fixed.
     #[inline]
     pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.get_mut(index)
+        self.owned_mut().get_mut(index)
     }
 
     /// Returns a reference to the last element, or None if empty.
     #[inline]
     pub fn back(&self) -> Option<&T> {
-        self.data.last()
+        self.as_slice().last()
     }
 
     /// Returns a mutable reference to the last element, or None if empty.
     #[inline]
     pub fn back_mut(&mut self) -> Option<&mut T> {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        self.data.last_mut()
+        self.owned_mut().last_mut()
     }
 
     /// Returns the vector as an immutable slice.
     #[inline]
     pub fn as_slice(&self) -> &[T] {
-        &self.data
+        match &self.storage {
+            Storage::Owned(data) => data,
+            // SAFETY: ptr/len came from Mapper::map_ptr, which checked bounds
+            // and alignment and only hands out pointers into memory that
+            // outlives the vector (see Storage::Mapped).
+            Storage::Mapped { ptr, len } => unsafe { core::slice::from_raw_parts(*ptr, *len) },
+        }
     }
 
     /// Returns the vector as a mutable slice.
@@ -164,8 +262,7 @@ impl<T: Copy> Vector<T> {
     /// Panics if the vector is fixed.
     #[inline]
     pub fn as_mut_slice(&mut self) -> &mut [T] {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        &mut self.data
+        self.owned_mut()
     }
 
     /// Clears the vector.
@@ -177,12 +274,19 @@ impl<T: Copy> Vector<T> {
     /// Swaps the contents of two vectors.
     #[inline]
     pub fn swap(&mut self, other: &mut Vector<T>) {
-        std::mem::swap(&mut self.data, &mut other.data);
-        std::mem::swap(&mut self.fixed, &mut other.fixed);
+        core::mem::swap(&mut self.storage, &mut other.storage);
+        core::mem::swap(&mut self.fixed, &mut other.fixed);
     }
 
     /// Maps the vector from a mapper.
     ///
+    /// The elements are borrowed without copying from a mapper opened with
+    /// [`Mapper::open_memory`] when the data is suitably aligned for `T`.
+    /// Otherwise (an unaligned byte slice from `include_bytes!`, or a file
+    /// or `Arc` buffer that is released with the mapper) they are copied.
+    /// Either way the vector becomes fixed and stays valid after the mapper
+    /// is dropped.
+    ///
     /// # Arguments
     ///
     /// * `mapper` - Mapper to read from
@@ -190,12 +294,12 @@ impl<T: Copy> Vector<T> {
     /// # Errors
     ///
     /// Returns an error if mapping fails.
-    pub fn map(&mut self, mapper: &mut Mapper) -> std::io::Result<()> {
+    pub fn map(&mut self, mapper: &mut Mapper<'_>) -> io::Result<()> {
         // Read the total size (u64)
-        let total_size: u64 = mapper.map_value()?;
+        let total_size = mapper.map_u64()?;
 
         // Calculate number of elements
-        let elem_size = std::mem::size_of::<T>();
+        let elem_size = core::mem::size_of::<T>();
         if elem_size == 0 {
             self.fixed = true;
             return Ok(()); // Zero-sized types
@@ -203,16 +307,20 @@ impl<T: Copy> Vector<T> {
 
         let num_elements = (total_size as usize) / elem_size;
 
-        // Allocate and map elements
-        self.data.clear();
-        self.data.reserve(num_elements);
-        #[allow(clippy::uninit_vec)]
-        unsafe {
-            self.data.set_len(num_elements);
-        }
-
-        if num_elements > 0 {
-            mapper.map_slice(&mut self.data[..])?;
+        if let Some(ptr) = mapper.map_ptr::<T>(num_elements)? {
+            self.storage = Storage::Mapped {
+                ptr,
+                len: num_elements,
+            };
+        } else {
+            // Unaligned source: fall back to copying the elements.
+            let mut data = Vec::with_capacity(num_elements);
+            #[allow(clippy::uninit_vec)]
+            unsafe {
+                data.set_len(num_elements);
+            }
+            mapper.map_slice(&mut data[..])?;
+            self.storage = Storage::Owned(data);
         }
 
         // Skip alignment padding
@@ -234,12 +342,13 @@ impl<T: Copy> Vector<T> {
     /// # Errors
     ///
     /// Returns an error if reading fails.
-    pub fn read(&mut self, reader: &mut Reader<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn read(&mut self, reader: &mut Reader<'_>) -> io::Result<()> {
         // Read the total size (u64)
-        let total_size: u64 = reader.read()?;
+        let total_size = reader.read_u64()?;
 
         // Calculate number of elements
-        let elem_size = std::mem::size_of::<T>();
+        let elem_size = core::mem::size_of::<T>();
         if elem_size == 0 {
             return Ok(()); // Zero-sized types
         }
@@ -247,16 +356,16 @@ impl<T: Copy> Vector<T> {
         let size = (total_size as usize) / elem_size;
 
         // Allocate and read elements
-        self.data.clear();
-        self.data.reserve(size);
+        let mut data = Vec::with_capacity(size);
         #[allow(clippy::uninit_vec)]
         unsafe {
-            self.data.set_len(size);
+            data.set_len(size);
         }
 
         if size > 0 {
-            reader.read_slice(&mut self.data[..])?;
+            reader.read_slice(&mut data[..])?;
         }
+        self.storage = Storage::Owned(data);
 
         // Skip alignment padding
         let padding = ((8 - (total_size % 8)) % 8) as usize;
@@ -281,14 +390,15 @@ impl<T: Copy> Vector<T> {
     /// # Errors
     ///
     /// Returns an error if writing fails.
-    pub fn write(&self, writer: &mut Writer<'_>) -> std::io::Result<()> {
+    #[cfg(feature = "std")]
+    pub fn write(&self, writer: &mut Writer<'_>) -> io::Result<()> {
         // Write total size as u64
         let total = self.total_size() as u64;
-        writer.write(&total)?;
+        writer.write_u64(total)?;
 
         // Write array elements
-        if !self.data.is_empty() {
-            writer.write_slice(&self.data[..])?;
+        if !self.empty() {
+            writer.write_slice(self.as_slice())?;
         }
 
         // Write alignment padding to 8 bytes
@@ -307,20 +417,26 @@ impl<T: Copy> Default for Vector<T> {
     }
 }
 
-impl<T: Copy> std::ops::Index<usize> for Vector<T> {
+impl<T: Copy> From<Vec<T>> for Vector<T> {
+    #[inline]
+    fn from(data: Vec<T>) -> Self {
+        Vector::from_vec(data)
+    }
+}
+
+impl<T: Copy> core::ops::Index<usize> for Vector<T> {
     type Output = T;
 
     #[inline]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.data[index]
+        &self.as_slice()[index]
     }
 }
 
-impl<T: Copy> std::ops::IndexMut<usize> for Vector<T> {
+impl<T: Copy> core::ops::IndexMut<usize> for Vector<T> {
     #[inline]
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        assert!(!self.fixed, "Cannot modify fixed vector");
-        &mut self.data[index]
+        &mut self.owned_mut()[index]
     }
 }
 
@@ -379,6 +495,7 @@ mod tests {
         vec.push_back(1);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_vector_write_read() {
         // Rust-specific: Test Vector<T> serialization
@@ -406,6 +523,7 @@ mod tests {
         assert_eq!(vec2[2], 3);
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_vector_write_read_empty() {
         // Rust-specific: Test empty Vector<T> serialization
@@ -428,6 +546,7 @@ mod tests {
         assert!(vec2.empty());
     }
 
+    #[cfg(feature = "std")]
     #[test]
     fn test_vector_write_alignment() {
         // Rust-specific: Test 8-byte alignment padding
@@ -464,4 +583,174 @@ mod tests {
         assert_eq!(data[14], 0);
         assert_eq!(data[15], 0);
     }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vector_write_len_matches_io_size() {
+        // Rust-specific: padding is written as zero bytes for both buffer
+        // and stream writers, so the output is exactly io_size() long
+        use crate::grimoire::io::Writer;
+        use std::io::Read;
+
+        for len in 0..=17u8 {
+            let mut vec = Vector::new();
+            for i in 0..len {
+                vec.push_back(i + 1);
+            }
+
+            let mut writer = Writer::from_vec(Vec::new());
+            vec.write(&mut writer).unwrap();
+            let data = writer.into_inner().unwrap();
+            assert_eq!(data.len(), vec.io_size(), "len={}", len);
+            assert!(data[8 + len as usize..].iter().all(|&b| b == 0));
+
+            let mut file = tempfile::tempfile().unwrap();
+            {
+                let mut writer = Writer::from_writer(&mut file);
+                vec.write(&mut writer).unwrap();
+            }
+            let mut streamed = Vec::new();
+            std::io::Seek::rewind(&mut file).unwrap();
+            file.read_to_end(&mut streamed).unwrap();
+            assert_eq!(streamed, data, "len={}", len);
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn mapped_bytes(values: &[u32]) -> &'static [u8] {
+        use crate::grimoire::io::Writer;
+
+        let mut vec = Vector::new();
+        for &value in values {
+            vec.push_back(value);
+        }
+        vec.push_back(0xDEAD_BEEF);
+        let mut writer = Writer::from_vec(Vec::new());
+        vec.write(&mut writer).unwrap();
+        let bytes = writer.into_inner().unwrap();
+
+        // Copy into u64 storage so the buffer is 8-byte aligned.
+        let mut words = vec![0u64; (bytes.len() + 7) / 8];
+        unsafe {
+            std::ptr::copy_nonoverlapping(
+                bytes.as_ptr(),
+                words.as_mut_ptr() as *mut u8,
+                bytes.len(),
+            );
+        }
+        let words: &'static [u64] = Box::leak(words.into_boxed_slice());
+        unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, bytes.len()) }
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vector_map_borrows() {
+        // Rust-specific: aligned mapped data is borrowed, not copied
+        let data = mapped_bytes(&[1, 2, 3]);
+        let mut mapper = Mapper::open_memory(data);
+        let mut vec: Vector<u32> = Vector::new();
+        vec.map(&mut mapper).unwrap();
+
+        assert!(vec.mapped());
+        assert!(vec.fixed());
+        assert_eq!(vec.size(), 4);
+        assert_eq!(vec.capacity(), 4);
+        assert_eq!(vec.as_slice(), &[1, 2, 3, 0xDEAD_BEEF]);
+        assert_eq!(vec[1], 2);
+        assert_eq!(vec.back(), Some(&0xDEAD_BEEF));
+        assert_eq!(vec.as_slice().as_ptr() as *const u8, data[8..].as_ptr());
+        assert_eq!(mapper.position(), data.len());
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vector_map_arc_copies() {
+        // Rust-specific: an Arc buffer is freed with its mapper, so the
+        // vector must not point into it
+        let data: std::sync::Arc<[u8]> = mapped_bytes(&[1, 2, 3]).into();
+        let mut mapper = Mapper::open_arc(data);
+        let mut vec: Vector<u32> = Vector::new();
+        vec.map(&mut mapper).unwrap();
+        drop(mapper);
+
+        assert!(!vec.mapped());
+        assert!(vec.fixed());
+        assert_eq!(vec.as_slice(), &[1, 2, 3, 0xDEAD_BEEF]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vector_map_unaligned_copies() {
+        // Rust-specific: misaligned data falls back to an owned copy
+        let aligned = mapped_bytes(&[7, 8, 9]);
+        let mut shifted = vec![0u8; aligned.len() + 1];
+        shifted[1..].copy_from_slice(aligned);
+        let shifted: &'static [u8] = Box::leak(shifted.into_boxed_slice());
+        let data = &shifted[1..];
+
+        let mut mapper = Mapper::open_memory(data);
+        let mut vec: Vector<u32> = Vector::new();
+        vec.map(&mut mapper).unwrap();
+
+        if (data[8..].as_ptr() as usize) % std::mem::align_of::<u32>() != 0 {
+            assert!(!vec.mapped());
+        }
+        assert!(vec.fixed());
+        assert_eq!(vec.as_slice(), &[7, 8, 9, 0xDEAD_BEEF]);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    #[should_panic(expected = "Cannot modify fixed vector")]
+    fn test_vector_map_push_panics() {
+        let data = mapped_bytes(&[1]);
+        let mut mapper = Mapper::open_memory(data);
+        let mut vec: Vector<u32> = Vector::new();
+        vec.map(&mut mapper).unwrap();
+        vec.push_back(2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_vector_map_clear() {
+        // Rust-specific: clearing a mapped vector makes it owned again
+        let data = mapped_bytes(&[1, 2]);
+        let mut mapper = Mapper::open_memory(data);
+        let mut vec: Vector<u32> = Vector::new();
+        vec.map(&mut mapper).unwrap();
+
+        vec.clear();
+        assert!(!vec.mapped());
+        vec.push_back(5);
+        assert_eq!(vec.as_slice(), &[5]);
+    }
+
+    #[test]
+    fn test_vector_send_sync() {
+        // Rust-specific: mapped storage must not make tries !Send/!Sync
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Vector<u32>>();
+    }
+
+    #[test]
+    fn test_vector_from_vec() {
+        // Rust-specific: from_vec moves the buffer and matches a push-built vector
+        let data = vec![1u32, 2, 3, 4, 5];
+        let ptr = data.as_ptr();
+        let vec = Vector::from_vec(data);
+        assert!(!vec.fixed());
+        assert_eq!(vec.as_slice().as_ptr(), ptr);
+
+        let mut pushed = Vector::new();
+        for value in 1..=5u32 {
+            pushed.push_back(value);
+        }
+        assert_eq!(vec.as_slice(), pushed.as_slice());
+        assert_eq!(vec.total_size(), pushed.total_size());
+        assert_eq!(vec.io_size(), pushed.io_size());
+
+        let mut vec: Vector<u32> = vec![7u32, 8].into();
+        vec.push_back(9);
+        assert_eq!(vec.as_slice(), &[7, 8, 9]);
+    }
 }
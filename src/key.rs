@@ -5,7 +5,9 @@
 //! This is the public API Key type, distinct from grimoire::trie::Key
 //! which is used internally.
 
-use std::fmt;
+use alloc::borrow::Cow;
+use alloc::string::String;
+use core::fmt;
 
 /// Union type to hold either an ID or a weight.
 #[derive(Clone, Copy)]
@@ -113,7 +115,7 @@ impl Key {
     /// Returns an empty slice if no string is set.
     pub fn as_bytes(&self) -> &[u8] {
         if let Some(ptr) = self.ptr {
-            unsafe { std::slice::from_raw_parts(ptr, self.length as usize) }
+            unsafe { core::slice::from_raw_parts(ptr, self.length as usize) }
         } else {
             &[]
         }
@@ -125,7 +127,41 @@ impl Key {
     ///
     /// Panics if the key contains invalid UTF-8.
     pub fn as_str(&self) -> &str {
-        std::str::from_utf8(self.as_bytes()).expect("Invalid UTF-8 in key")
+        core::str::from_utf8(self.as_bytes()).expect("Invalid UTF-8 in key")
+    }
+
+    /// Returns the key string, or an error if it is not valid UTF-8.
+    ///
+    /// Rust-specific: non-panicking counterpart of [`as_str`](Self::as_str).
+    pub fn try_as_str(&self) -> Result<&str, core::str::Utf8Error> {
+        core::str::from_utf8(self.as_bytes())
+    }
+
+    /// Returns the key string, replacing invalid UTF-8 sequences with
+    /// U+FFFD.
+    ///
+    /// Rust-specific. Borrows when the key is valid UTF-8.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_bytes(b"caf\xC3\xA9", 1.0).unwrap();
+    /// keyset.push_back_bytes(b"bin\xFF", 1.0).unwrap();
+    ///
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_bytes(b"bin\xFF");
+    /// assert!(trie.lookup(&mut agent));
+    /// assert_eq!(agent.key().as_str_lossy(), "bin\u{FFFD}");
+    /// assert!(agent.key().try_as_str().is_err());
+    /// ```
+    pub fn as_str_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(self.as_bytes())
     }
 
     /// Returns a pointer to the key data.
@@ -165,7 +201,7 @@ impl Key {
 
     /// Swaps with another key.
     pub fn swap(&mut self, other: &mut Key) {
-        std::mem::swap(self, other);
+        core::mem::swap(self, other);
     }
 }
 
@@ -317,4 +353,21 @@ mod tests {
 
     // Note: Cannot safely test set_str with length > u32::MAX
     // as creating such a string would require invalid operations.
+
+    #[test]
+    fn test_key_invalid_utf8_helpers() {
+        // Rust-specific: UTF-8 helpers for binary keys
+        let mut key = Key::new();
+        key.set_bytes(b"ok\xFE\xFFend");
+        assert!(key.try_as_str().is_err());
+        assert_eq!(key.as_str_lossy(), "ok\u{FFFD}\u{FFFD}end");
+
+        key.set_str("日本語");
+        assert_eq!(key.try_as_str(), Ok("日本語"));
+        assert!(matches!(key.as_str_lossy(), Cow::Borrowed("日本語")));
+
+        let empty = Key::new();
+        assert_eq!(empty.try_as_str(), Ok(""));
+        assert_eq!(empty.as_str_lossy(), "");
+    }
 }
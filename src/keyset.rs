@@ -4,10 +4,14 @@
 //! - include/marisa/keyset.h
 //! - lib/marisa/keyset.cc
 
+use crate::grimoire::io;
 use crate::key::Key;
-use std::io;
+use alloc::boxed::Box;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
 
-/// Block sizes for memory allocation.
+/// Default block sizes for memory allocation (see [`Keyset::with_block_sizes`]).
 const BASE_BLOCK_SIZE: usize = 4096;
 const EXTRA_BLOCK_SIZE: usize = 1024;
 const KEY_BLOCK_SIZE: usize = 256;
@@ -16,13 +20,26 @@ const KEY_BLOCK_SIZE: usize = 256;
 ///
 /// Keys are stored in blocks to minimize allocations and provide
 /// stable addresses for borrowed string data.
-pub struct Keyset {
-    /// Blocks of base size for normal string storage.
-    base_blocks: Vec<Box<[u8; BASE_BLOCK_SIZE]>>,
-    /// Blocks for strings larger than EXTRA_BLOCK_SIZE.
+///
+/// Rust-specific: the lifetime `'a` bounds keys added with
+/// [`push_back_borrowed`](Self::push_back_borrowed), which point into the
+/// caller's buffer instead of being copied. A keyset holding only copied
+/// keys can have any lifetime.
+pub struct Keyset<'a> {
+    /// Blocks of `base_block_size` bytes for normal string storage.
+    base_blocks: Vec<Box<[u8]>>,
+    /// Pre-allocated base blocks not yet in use (see `reserve`).
+    spare_base_blocks: Vec<Box<[u8]>>,
+    /// Blocks for strings larger than `extra_block_size`.
     extra_blocks: Vec<Vec<u8>>,
-    /// Blocks of Key objects.
-    key_blocks: Vec<Box<[Key; KEY_BLOCK_SIZE]>>,
+    /// Blocks of `key_block_size` Key objects.
+    key_blocks: Vec<Box<[Key]>>,
+    /// Size in bytes of each base block.
+    base_block_size: usize,
+    /// Strings longer than this get their own extra block.
+    extra_block_size: usize,
+    /// Number of keys per key block.
+    key_block_size: usize,
     /// Current write position in the current base block.
     ptr_offset: usize,
     /// Available space remaining in current base block.
@@ -31,46 +48,96 @@ pub struct Keyset {
     size: usize,
     /// Total length of all key strings.
     total_length: usize,
+    /// Ties the keyset to the buffers of borrowed keys.
+    borrowed: PhantomData<&'a [u8]>,
 }
 
-impl Default for Keyset {
+impl Default for Keyset<'_> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl Keyset {
+impl<'a> Keyset<'a> {
     /// Creates a new empty keyset.
     pub fn new() -> Self {
+        Keyset::with_block_sizes(BASE_BLOCK_SIZE, EXTRA_BLOCK_SIZE, KEY_BLOCK_SIZE)
+    }
+
+    /// Creates a new empty keyset with custom allocation granularity.
+    ///
+    /// Key bytes are packed into base blocks of `base` bytes, except that
+    /// keys longer than `extra` bytes get a block of their own. Key
+    /// objects are allocated `key` at a time. [`new`](Self::new) uses
+    /// 4096, 1024 and 256. Larger base blocks suit very long keys such as
+    /// URLs or genomic reads; smaller key blocks suit small keysets.
+    ///
+    /// # Arguments
+    ///
+    /// * `base` - Size in bytes of each base block
+    /// * `extra` - Largest key, in bytes, stored in a base block
+    /// * `key` - Number of keys per key block
+    ///
+    /// # Panics
+    ///
+    /// Panics if any size is zero or if `extra` is larger than `base`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::Keyset;
+    ///
+    /// let mut keyset = Keyset::with_block_sizes(1 << 16, 1 << 12, 64);
+    /// keyset.push_back_str("https://example.com/a/long/path").unwrap();
+    /// assert_eq!(keyset.size(), 1);
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn with_block_sizes(base: usize, extra: usize, key: usize) -> Self {
+        assert!(
+            base > 0 && extra > 0 && key > 0,
+            "Block sizes must be non-zero"
+        );
+        assert!(
+            extra <= base,
+            "Extra block threshold {} exceeds base block size {}",
+            extra,
+            base
+        );
         Keyset {
             base_blocks: Vec::new(),
+            spare_base_blocks: Vec::new(),
             extra_blocks: Vec::new(),
             key_blocks: Vec::new(),
+            base_block_size: base,
+            extra_block_size: extra,
+            key_block_size: key,
             ptr_offset: 0,
             avail: 0,
             size: 0,
             total_length: 0,
+            borrowed: PhantomData,
         }
     }
 
     /// Adds a key to the keyset.
     pub fn push_back_key(&mut self, key: &Key) {
         let key_bytes = key.as_bytes();
-        let key_ptr = self.reserve(key_bytes.len());
+        let key_ptr = self.reserve_string(key_bytes.len());
 
         // Copy string data
         unsafe {
-            std::ptr::copy_nonoverlapping(key_bytes.as_ptr(), key_ptr, key_bytes.len());
+            core::ptr::copy_nonoverlapping(key_bytes.as_ptr(), key_ptr, key_bytes.len());
         }
 
         // Create new Key in key block
-        let key_block_idx = self.size / KEY_BLOCK_SIZE;
-        let key_idx = self.size % KEY_BLOCK_SIZE;
+        let key_block_idx = self.size / self.key_block_size;
+        let key_idx = self.size % self.key_block_size;
         let new_key = &mut self.key_blocks[key_block_idx][key_idx];
 
         // Set string from our stable storage
         let stored_slice =
-            unsafe { std::slice::from_raw_parts(key_ptr as *const u8, key_bytes.len()) };
+            unsafe { core::slice::from_raw_parts(key_ptr as *const u8, key_bytes.len()) };
         new_key.set_bytes(stored_slice);
         new_key.set_id(key.id());
 
@@ -79,29 +146,36 @@ impl Keyset {
     }
 
     /// Adds a key with an end marker character.
+    ///
+    /// As in C++, the marker is stored right after the key bytes but is not
+    /// part of the key. The key length, `total_length` and trie searches
+    /// all ignore it, and a NUL marker does not switch the trie to
+    /// `TailMode::BinaryTail`. Use
+    /// [`push_back_bytes_with_marker`](Self::push_back_bytes_with_marker)
+    /// to make the marker part of the searchable key.
     pub fn push_back_key_with_marker(&mut self, key: &Key, end_marker: u8) {
-        if self.size / KEY_BLOCK_SIZE == self.key_blocks.len() {
+        if self.size / self.key_block_size == self.key_blocks.len() {
             self.append_key_block();
         }
 
         let key_bytes = key.as_bytes();
         let total_len = key_bytes.len() + 1;
-        let key_ptr = self.reserve(total_len);
+        let key_ptr = self.reserve_string(total_len);
 
         // Copy string data and add marker
         unsafe {
-            std::ptr::copy_nonoverlapping(key_bytes.as_ptr(), key_ptr, key_bytes.len());
+            core::ptr::copy_nonoverlapping(key_bytes.as_ptr(), key_ptr, key_bytes.len());
             *key_ptr.add(key_bytes.len()) = end_marker;
         }
 
         // Create new Key in key block
-        let key_block_idx = self.size / KEY_BLOCK_SIZE;
-        let key_idx = self.size % KEY_BLOCK_SIZE;
+        let key_block_idx = self.size / self.key_block_size;
+        let key_idx = self.size % self.key_block_size;
         let new_key = &mut self.key_blocks[key_block_idx][key_idx];
 
         // Set string from our stable storage (without end marker)
         let stored_slice =
-            unsafe { std::slice::from_raw_parts(key_ptr as *const u8, key_bytes.len()) };
+            unsafe { core::slice::from_raw_parts(key_ptr as *const u8, key_bytes.len()) };
         new_key.set_bytes(stored_slice);
         new_key.set_id(key.id());
 
@@ -110,37 +184,222 @@ impl Keyset {
     }
 
     /// Adds a string to the keyset with default weight of 1.0.
-    pub fn push_back_str(&mut self, s: &str) -> io::Result<()> {
+    ///
+    /// Returns the index of the new key, as with
+    /// [`push_back_bytes`](Self::push_back_bytes).
+    pub fn push_back_str(&mut self, s: &str) -> io::Result<usize> {
         self.push_back_bytes(s.as_bytes(), 1.0)
     }
 
     /// Adds bytes to the keyset with specified weight.
-    pub fn push_back_bytes(&mut self, bytes: &[u8], weight: f32) -> io::Result<()> {
+    ///
+    /// Returns the index of the new key in the keyset. `Trie::build`
+    /// assigns each key's trie ID to the key at that index, so
+    /// `keyset.get(index).id()` maps an input to its key ID after building.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// let banana = keyset.push_back_bytes(b"banana", 1.0).unwrap();
+    /// let apple = keyset.push_back_bytes(b"apple", 1.0).unwrap();
+    /// assert_eq!((banana, apple), (0, 1));
+    ///
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    /// let apple_id = keyset.get(apple).id();
+    /// assert!(apple_id < trie.num_keys());
+    /// ```
+    pub fn push_back_bytes(&mut self, bytes: &[u8], weight: f32) -> io::Result<usize> {
+        self.push_back_bytes_and_marker(bytes, None, weight)
+    }
+
+    /// Adds bytes followed by an end marker, with the marker as the last
+    /// byte of the key.
+    ///
+    /// Unlike [`push_back_key_with_marker`](Self::push_back_key_with_marker),
+    /// the marker is searchable: the key is found by looking up `bytes`
+    /// plus the marker, and a predictive search for that form matches only
+    /// keys that continue past the marker. This suits delimiter-joined keys
+    /// such as `reading\tsurface`, where `reading\t` must not match a
+    /// longer reading. A NUL marker puts NUL bytes in the keys, so a tail
+    /// that stores one switches to `TailMode::BinaryTail`.
+    ///
+    /// Returns the index of the new key, as with
+    /// [`push_back_bytes`](Self::push_back_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` if the key with its marker is longer than
+    /// `u32::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_bytes_with_marker("かな".as_bytes(), b'\t', 1.0).unwrap();
+    /// keyset.push_back_bytes_with_marker("かなり".as_bytes(), b'\t', 1.0).unwrap();
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("かな\t");
+    /// assert!(trie.lookup(&mut agent));
+    /// agent.set_query_str("かな");
+    /// assert!(!trie.lookup(&mut agent));
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn push_back_bytes_with_marker(
+        &mut self,
+        bytes: &[u8],
+        end_marker: u8,
+        weight: f32,
+    ) -> io::Result<usize> {
+        self.push_back_bytes_and_marker(bytes, Some(end_marker), weight)
+    }
+
+    /// Adds a key that points into `bytes` instead of copying it.
+    ///
+    /// The other `push_back_*` methods copy each key into the keyset's
+    /// blocks. When the keys already sit in a buffer that outlives the
+    /// keyset, such as a memory-mapped file, that copy is pure overhead;
+    /// this stores the slice as is, and the keyset's lifetime keeps it
+    /// from outliving `bytes`. Borrowed and copied keys can be mixed, and
+    /// the trie built from them does not borrow either.
+    ///
+    /// Returns the index of the new key, as with
+    /// [`push_back_bytes`](Self::push_back_bytes).
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidInput` if `bytes` is longer than `u32::MAX` bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::{Agent, Keyset, Trie};
+    ///
+    /// let text = String::from("apple\nbanana\ncherry");
+    /// let mut keyset = Keyset::new();
+    /// for line in text.lines() {
+    ///     keyset.push_back_borrowed(line.as_bytes(), 1.0).unwrap();
+    /// }
+    /// let mut trie = Trie::new();
+    /// trie.build(&mut keyset, 0);
+    /// drop(keyset);
+    /// drop(text);
+    ///
+    /// let mut agent = Agent::new();
+    /// agent.set_query_str("banana");
+    /// assert!(trie.lookup(&mut agent));
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn push_back_borrowed(&mut self, bytes: &'a [u8], weight: f32) -> io::Result<usize> {
         if bytes.len() > u32::MAX as usize {
             return Err(io::Error::new(io::ErrorKind::InvalidInput, "Key too long"));
         }
+        if self.size / self.key_block_size == self.key_blocks.len() {
+            self.append_key_block();
+        }
 
-        let key_ptr = self.reserve(bytes.len());
+        let key =
+            &mut self.key_blocks[self.size / self.key_block_size][self.size % self.key_block_size];
+        key.set_bytes(bytes);
+        key.set_weight(weight);
+
+        let index = self.size;
+        self.size += 1;
+        self.total_length += bytes.len();
+
+        Ok(index)
+    }
+
+    /// Stores `bytes` plus an optional trailing marker as one key.
+    fn push_back_bytes_and_marker(
+        &mut self,
+        bytes: &[u8],
+        end_marker: Option<u8>,
+        weight: f32,
+    ) -> io::Result<usize> {
+        let length = bytes.len() + usize::from(end_marker.is_some());
+        if length > u32::MAX as usize {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "Key too long"));
+        }
+
+        let key_ptr = self.reserve_string(length);
 
         // Copy string data
         unsafe {
-            std::ptr::copy_nonoverlapping(bytes.as_ptr(), key_ptr, bytes.len());
+            core::ptr::copy_nonoverlapping(bytes.as_ptr(), key_ptr, bytes.len());
+            if let Some(marker) = end_marker {
+                *key_ptr.add(bytes.len()) = marker;
+            }
         }
 
         // Create new Key in key block
-        let key_block_idx = self.size / KEY_BLOCK_SIZE;
-        let key_idx = self.size % KEY_BLOCK_SIZE;
+        let key_block_idx = self.size / self.key_block_size;
+        let key_idx = self.size % self.key_block_size;
         let key = &mut self.key_blocks[key_block_idx][key_idx];
 
         // Set string from our stable storage
-        let stored_slice = unsafe { std::slice::from_raw_parts(key_ptr as *const u8, bytes.len()) };
+        let stored_slice = unsafe { core::slice::from_raw_parts(key_ptr as *const u8, length) };
         key.set_bytes(stored_slice);
         key.set_weight(weight);
 
+        let index = self.size;
         self.size += 1;
-        self.total_length += bytes.len();
+        self.total_length += length;
 
-        Ok(())
+        Ok(index)
+    }
+
+    /// Pre-allocates storage for `num_keys` more keys totalling
+    /// `total_bytes` bytes.
+    ///
+    /// Rust-specific: C++ marisa grows the keyset one block at a time.
+    /// Reserving up front avoids repeated block allocations when the final
+    /// size is known. Keys longer than the extra-block threshold (1024
+    /// bytes by default) are stored separately and are not covered by the
+    /// reservation.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::Keyset;
+    ///
+    /// let words = ["apple", "banana", "cherry"];
+    /// let total_bytes = words.iter().map(|w| w.len()).sum();
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.reserve(words.len(), total_bytes);
+    /// for word in words {
+    ///     keyset.push_back_str(word).unwrap();
+    /// }
+    /// assert_eq!(keyset.size(), 3);
+    /// ```
+    pub fn reserve(&mut self, num_keys: usize, total_bytes: usize) {
+        let num_key_blocks = (self.size + num_keys + self.key_block_size - 1) / self.key_block_size;
+        if num_key_blocks > self.key_blocks.len() {
+            self.key_blocks
+                .reserve(num_key_blocks - self.key_blocks.len());
+            while self.key_blocks.len() < num_key_blocks {
+                self.append_key_block();
+            }
+        }
+
+        let new_bytes = total_bytes.saturating_sub(self.avail);
+        let num_base_blocks = (new_bytes + self.base_block_size - 1) / self.base_block_size;
+        self.base_blocks.reserve(num_base_blocks);
+        while self.spare_base_blocks.len() < num_base_blocks {
+            self.spare_base_blocks
+                .push(vec![0u8; self.base_block_size].into_boxed_slice());
+        }
     }
 
     /// Returns a reference to the key at the specified index.
@@ -150,7 +409,7 @@ impl Keyset {
     /// Panics if index is out of bounds.
     pub fn get(&self, i: usize) -> &Key {
         assert!(i < self.size, "Index out of bounds");
-        &self.key_blocks[i / KEY_BLOCK_SIZE][i % KEY_BLOCK_SIZE]
+        &self.key_blocks[i / self.key_block_size][i % self.key_block_size]
     }
 
     /// Returns a mutable reference to the key at the specified index.
@@ -160,7 +419,30 @@ impl Keyset {
     /// Panics if index is out of bounds.
     pub fn get_mut(&mut self, i: usize) -> &mut Key {
         assert!(i < self.size, "Index out of bounds");
-        &mut self.key_blocks[i / KEY_BLOCK_SIZE][i % KEY_BLOCK_SIZE]
+        &mut self.key_blocks[i / self.key_block_size][i % self.key_block_size]
+    }
+
+    /// Returns an iterator over the keys in insertion order.
+    ///
+    /// Rust-specific: C++ marisa only offers indexed access.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::Keyset;
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_str("apple").unwrap();
+    /// keyset.push_back_str("banana").unwrap();
+    ///
+    /// let keys: Vec<&str> = keyset.iter().map(|key| key.as_str()).collect();
+    /// assert_eq!(keys, ["apple", "banana"]);
+    /// ```
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            keyset: self,
+            index: 0,
+        }
     }
 
     /// Returns the number of keys in the keyset.
@@ -183,6 +465,86 @@ impl Keyset {
         self.total_length
     }
 
+    /// Returns the heap bytes held by the keyset's blocks.
+    ///
+    /// Counts every base block (including spare ones from
+    /// [`reserve`](Self::reserve)), the capacity of every extra block and
+    /// every key block, so it is usually larger than
+    /// [`total_length`](Self::total_length). Useful for estimating memory
+    /// before building a trie.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::Keyset;
+    ///
+    /// let mut keyset = Keyset::new();
+    /// assert_eq!(keyset.allocated_bytes(), 0);
+    /// keyset.push_back_str("apple").unwrap();
+    /// assert!(keyset.allocated_bytes() >= keyset.total_length());
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn allocated_bytes(&self) -> usize {
+        let base_blocks = self.base_blocks.len() + self.spare_base_blocks.len();
+        let extra_bytes: usize = self.extra_blocks.iter().map(Vec::capacity).sum();
+        base_blocks * self.base_block_size
+            + extra_bytes
+            + self.key_blocks.len() * self.key_block_size * core::mem::size_of::<Key>()
+    }
+
+    /// Sorts the keys and removes exact duplicates.
+    ///
+    /// Keys are left in byte order, and each duplicate group keeps the
+    /// largest weight among its copies. Only the `Key` objects are
+    /// reordered; the key bytes stay where they are, so the space used by
+    /// removed copies is not reclaimed until [`reset`](Self::reset) or
+    /// [`clear`](Self::clear). Indices returned by `push_back_*` no longer
+    /// apply afterwards.
+    ///
+    /// `build` already merges duplicate keys, and their weights add up
+    /// when nodes are ordered by weight. Call this to know the final key
+    /// count before building, or to count each key's maximum weight once.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use rsmarisa::Keyset;
+    ///
+    /// let mut keyset = Keyset::new();
+    /// keyset.push_back_bytes(b"banana", 1.0).unwrap();
+    /// keyset.push_back_bytes(b"apple", 2.0).unwrap();
+    /// keyset.push_back_bytes(b"banana", 5.0).unwrap();
+    /// keyset.dedup();
+    ///
+    /// let keys: Vec<(&str, f32)> = keyset.iter().map(|k| (k.as_str(), k.weight())).collect();
+    /// assert_eq!(keys, [("apple", 2.0), ("banana", 5.0)]);
+    /// ```
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn dedup(&mut self) {
+        let mut keys: Vec<Key> = self.iter().cloned().collect();
+        keys.sort_by(|a, b| a.as_bytes().cmp(b.as_bytes()));
+
+        let mut unique: Vec<Key> = Vec::with_capacity(keys.len());
+        for key in keys {
+            match unique.last_mut() {
+                Some(last) if last.as_bytes() == key.as_bytes() => {
+                    if key.weight() > last.weight() {
+                        last.set_weight(key.weight());
+                    }
+                }
+                _ => unique.push(key),
+            }
+        }
+
+        self.size = unique.len();
+        self.total_length = unique.iter().map(Key::length).sum();
+        for (i, key) in unique.into_iter().enumerate() {
+            self.key_blocks[i / self.key_block_size][i % self.key_block_size] = key;
+        }
+    }
+
     /// Resets the keyset to reuse allocated memory.
     pub fn reset(&mut self) {
         self.ptr_offset = 0;
@@ -193,32 +555,40 @@ impl Keyset {
     }
 
     /// Clears all data and frees memory.
+    ///
+    /// Block sizes set by [`with_block_sizes`](Self::with_block_sizes) are
+    /// kept.
     pub fn clear(&mut self) {
-        *self = Keyset::new();
+        *self = Keyset::with_block_sizes(
+            self.base_block_size,
+            self.extra_block_size,
+            self.key_block_size,
+        );
     }
 
     /// Swaps with another keyset.
-    pub fn swap(&mut self, other: &mut Keyset) {
-        std::mem::swap(self, other);
+    pub fn swap(&mut self, other: &mut Keyset<'a>) {
+        core::mem::swap(self, other);
     }
 
     /// Reserves space for a string of the given size.
     ///
     /// Returns a mutable pointer to the reserved space.
-    fn reserve(&mut self, size: usize) -> *mut u8 {
+    fn reserve_string(&mut self, size: usize) -> *mut u8 {
         // Ensure we have a key block for the new key
-        if self.size / KEY_BLOCK_SIZE == self.key_blocks.len() {
+        if self.size / self.key_block_size == self.key_blocks.len() {
             self.append_key_block();
         }
 
         // For large strings, use an extra block
-        if size > EXTRA_BLOCK_SIZE {
+        if size > self.extra_block_size {
             self.append_extra_block(size);
             return self.extra_blocks.last_mut().unwrap().as_mut_ptr();
         }
 
-        // Need a new base block?
-        if size > self.avail {
+        // Need a new base block? An empty first key still needs one to
+        // point into.
+        if size > self.avail || self.base_blocks.is_empty() {
             self.append_base_block();
         }
 
@@ -238,9 +608,13 @@ impl Keyset {
 
     /// Appends a new base block for string storage.
     fn append_base_block(&mut self) {
-        self.base_blocks.push(Box::new([0u8; BASE_BLOCK_SIZE]));
+        let block = self
+            .spare_base_blocks
+            .pop()
+            .unwrap_or_else(|| vec![0u8; self.base_block_size].into_boxed_slice());
+        self.base_blocks.push(block);
         self.ptr_offset = 0;
-        self.avail = BASE_BLOCK_SIZE;
+        self.avail = self.base_block_size;
     }
 
     /// Appends a new extra block for large string storage.
@@ -251,11 +625,48 @@ impl Keyset {
     /// Appends a new key block.
     fn append_key_block(&mut self) {
         // Create a new block with default Keys
-        let block = Box::new([(); KEY_BLOCK_SIZE].map(|_| Key::new()));
+        let block = (0..self.key_block_size).map(|_| Key::new()).collect();
         self.key_blocks.push(block);
     }
 }
 
+impl<'k> IntoIterator for &'k Keyset<'_> {
+    type Item = &'k Key;
+    type IntoIter = Iter<'k>;
+
+    fn into_iter(self) -> Iter<'k> {
+        self.iter()
+    }
+}
+
+/// Iterator over the keys of a [`Keyset`], created by [`Keyset::iter`].
+pub struct Iter<'a> {
+    /// Keyset being iterated.
+    keyset: &'a Keyset<'a>,
+    /// Index of the next key.
+    index: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Key;
+
+    fn next(&mut self) -> Option<&'a Key> {
+        if self.index >= self.keyset.size {
+            return None;
+        }
+        let key = self.keyset.get(self.index);
+        self.index += 1;
+        Some(key)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.keyset.size - self.index;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -298,6 +709,66 @@ mod tests {
         assert!((keyset.get(0).weight() - 2.5).abs() < 0.001);
     }
 
+    #[test]
+    fn test_keyset_empty_first_key() {
+        // Rust-specific: an empty key pushed before any storage block
+        // exists used to underflow the block index
+        let mut keyset = Keyset::new();
+        keyset.push_back_str("").unwrap();
+        keyset.push_back_str("a").unwrap();
+
+        assert_eq!(keyset.size(), 2);
+        assert_eq!(keyset.total_length(), 1);
+        assert_eq!(keyset.get(0).as_bytes(), b"");
+        assert_eq!(keyset.get(1).as_str(), "a");
+    }
+
+    #[test]
+    fn test_keyset_dedup() {
+        // Rust-specific: duplicates collapse to one key with the largest
+        // weight, and new keys can still be pushed afterwards
+        let mut keyset = Keyset::with_block_sizes(64, 16, 2);
+        let input: &[(&[u8], f32)] = &[
+            (b"pear", 1.0),
+            (b"apple", 3.0),
+            (b"pear", 7.5),
+            (b"", 0.5),
+            (b"apple", 2.0),
+            (b"fig", 1.0),
+            (b"pear", 4.0),
+            (b"", 2.5),
+            (b"a long key stored in its own block", 1.0),
+            (b"a long key stored in its own block", 9.0),
+        ];
+        for &(bytes, weight) in input {
+            keyset.push_back_bytes(bytes, weight).unwrap();
+        }
+
+        keyset.dedup();
+        let keys: Vec<(&[u8], f32)> = keyset.iter().map(|k| (k.as_bytes(), k.weight())).collect();
+        assert_eq!(
+            keys,
+            [
+                (&b""[..], 2.5),
+                (&b"a long key stored in its own block"[..], 9.0),
+                (&b"apple"[..], 3.0),
+                (&b"fig"[..], 1.0),
+                (&b"pear"[..], 7.5),
+            ]
+        );
+        assert_eq!(keyset.size(), 5);
+        assert_eq!(keyset.total_length(), 34 + 5 + 3 + 4);
+
+        keyset.push_back_str("kiwi").unwrap();
+        assert_eq!(keyset.size(), 6);
+        assert_eq!(keyset.get(5).as_str(), "kiwi");
+        assert_eq!(keyset.total_length(), 50);
+
+        let mut trie = crate::Trie::new();
+        trie.build(&mut keyset, 0);
+        assert_eq!(trie.num_keys(), 6);
+    }
+
     #[test]
     fn test_keyset_push_back_key() {
         let mut keyset = Keyset::new();
@@ -330,6 +801,70 @@ mod tests {
         // End marker is not included in the key length
     }
 
+    #[test]
+    fn test_keyset_marker_searchable_vs_hidden() {
+        // Rust-specific: tab-joined entries, with the marker part of the key
+        // or stored outside it
+        use crate::base::TailMode;
+        use crate::{Agent, Trie};
+
+        let entries = [("かな", "仮名"), ("かなり", "可也"), ("かな", "カナ")];
+        let mut keyset = Keyset::new();
+        for (reading, surface) in entries {
+            let joined = format!("{}\t{}", reading, surface);
+            keyset.push_back_str(&joined).unwrap();
+            keyset
+                .push_back_bytes_with_marker(reading.as_bytes(), b'\t', 1.0)
+                .unwrap();
+        }
+        assert_eq!(keyset.get(1).as_bytes(), "かな\t".as_bytes());
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+        assert_eq!(trie.tail_mode(), TailMode::TextTail);
+
+        let mut agent = Agent::new();
+        agent.set_query_str("かな\t仮名");
+        assert!(trie.lookup(&mut agent));
+        agent.set_query_str("かな\t");
+        assert!(trie.lookup(&mut agent));
+        agent.set_query_str("かな");
+        assert!(!trie.lookup(&mut agent));
+
+        // "かな\t" completes to its own surfaces only, never to "かなり".
+        let found: Vec<Vec<u8>> = trie
+            .predictive_search_n("かな\t", usize::MAX)
+            .into_iter()
+            .map(|(_, key)| key)
+            .collect();
+        assert_eq!(found.len(), 3);
+        assert!(found.iter().all(|k| k.starts_with("かな\t".as_bytes())));
+
+        // A hidden marker is not searchable and does not force BinaryTail.
+        let mut hidden = Keyset::new();
+        let mut key = Key::new();
+        key.set_str("かな");
+        hidden.push_back_key_with_marker(&key, b'\0');
+        let mut trie = Trie::new();
+        trie.build(&mut hidden, 0);
+        assert_eq!(trie.tail_mode(), TailMode::TextTail);
+        agent.set_query_str("かな");
+        assert!(trie.lookup(&mut agent));
+
+        // A searchable NUL marker puts NUL in the keys, so a tail holding
+        // it must be binary.
+        let mut visible = Keyset::new();
+        for key in [&b"abcdef"[..], b"xyzxyz"] {
+            visible
+                .push_back_bytes_with_marker(key, b'\0', 1.0)
+                .unwrap();
+        }
+        let mut trie = Trie::new();
+        trie.build(&mut visible, 1);
+        assert_eq!(trie.tail_mode(), TailMode::BinaryTail);
+        assert!(trie.lookup_bytes(b"abcdef\0").is_some());
+        assert_eq!(trie.lookup_bytes(b"abcdef"), None);
+    }
+
     #[test]
     fn test_keyset_get_mut() {
         let mut keyset = Keyset::new();
@@ -429,4 +964,220 @@ mod tests {
         keyset.push_back_str("test").unwrap();
         assert!(!keyset.empty());
     }
+
+    #[test]
+    fn test_keyset_iter() {
+        // Rust-specific: iteration spans multiple key blocks in insertion order
+        let mut keyset = Keyset::new();
+        let num_keys = KEY_BLOCK_SIZE * 2 + 17;
+        for i in 0..num_keys {
+            keyset.push_back_str(&format!("key{}", i)).unwrap();
+        }
+
+        let iter = keyset.iter();
+        assert_eq!(iter.len(), num_keys);
+        for (i, key) in iter.enumerate() {
+            assert_eq!(key.as_str(), format!("key{}", i));
+        }
+
+        let mut count = 0;
+        for key in &keyset {
+            assert_eq!(key.as_bytes(), keyset.get(count).as_bytes());
+            count += 1;
+        }
+        assert_eq!(count, num_keys);
+
+        // reset() keeps the blocks but must not expose stale keys.
+        keyset.reset();
+        keyset.push_back_str("fresh").unwrap();
+        let keys: Vec<&str> = keyset.iter().map(|key| key.as_str()).collect();
+        assert_eq!(keys, ["fresh"]);
+    }
+
+    #[test]
+    fn test_keyset_allocated_bytes() {
+        // Rust-specific: allocation grows block by block as keys are added
+        let key_block_bytes = KEY_BLOCK_SIZE * std::mem::size_of::<Key>();
+        let mut keyset = Keyset::new();
+        assert_eq!(keyset.allocated_bytes(), 0);
+
+        keyset.push_back_str("a").unwrap();
+        assert_eq!(keyset.allocated_bytes(), BASE_BLOCK_SIZE + key_block_bytes);
+
+        // Fill the first key block; the next key starts a second one
+        for i in 1..KEY_BLOCK_SIZE {
+            keyset.push_back_str(&format!("{:03}", i)).unwrap();
+        }
+        let one_key_block = keyset.allocated_bytes();
+        assert_eq!(one_key_block, BASE_BLOCK_SIZE + key_block_bytes);
+        keyset.push_back_str("x").unwrap();
+        assert_eq!(keyset.allocated_bytes(), one_key_block + key_block_bytes);
+
+        // Overflowing the base block allocates another
+        let before = keyset.allocated_bytes();
+        for byte in b"bcde" {
+            keyset.push_back_bytes(&[*byte; 900], 1.0).unwrap();
+        }
+        assert_eq!(keyset.allocated_bytes(), before + BASE_BLOCK_SIZE);
+
+        // A key longer than EXTRA_BLOCK_SIZE gets an extra block
+        let before = keyset.allocated_bytes();
+        keyset.push_back_bytes(&[b'e'; 5000], 1.0).unwrap();
+        assert!(keyset.allocated_bytes() >= before + 5000);
+        assert!(keyset.allocated_bytes() >= keyset.total_length());
+
+        keyset.clear();
+        assert_eq!(keyset.allocated_bytes(), 0);
+    }
+
+    #[test]
+    fn test_keyset_reserve() {
+        // Rust-specific: reserve() pre-allocates key and base blocks
+        let num_keys = KEY_BLOCK_SIZE * 3;
+        let keys: Vec<String> = (0..num_keys).map(|i| format!("word{:04}", i)).collect();
+        let total_bytes: usize = keys.iter().map(|key| key.len()).sum();
+
+        let mut keyset = Keyset::new();
+        keyset.reserve(num_keys, total_bytes);
+        assert_eq!(keyset.key_blocks.len(), 3);
+        let num_spare = keyset.spare_base_blocks.len();
+        assert_eq!(
+            num_spare,
+            (total_bytes + BASE_BLOCK_SIZE - 1) / BASE_BLOCK_SIZE
+        );
+        let first_block = keyset.spare_base_blocks.last().unwrap().as_ptr();
+
+        for key in &keys {
+            keyset.push_back_str(key).unwrap();
+        }
+
+        // No key blocks beyond the reservation; spare base blocks were used.
+        assert_eq!(keyset.key_blocks.len(), 3);
+        assert_eq!(keyset.base_blocks[0].as_ptr(), first_block);
+        assert!(keyset.base_blocks.len() <= num_spare + 1);
+        for (i, key) in keys.iter().enumerate() {
+            assert_eq!(keyset.get(i).as_str(), key);
+        }
+    }
+
+    #[test]
+    fn test_keyset_push_back_returns_index() {
+        // Rust-specific: push_back_* return the index used to look up the built ID
+        use crate::agent::Agent;
+        use crate::trie::Trie;
+
+        let words = ["delta", "alpha", "charlie", "bravo"];
+        let mut keyset = Keyset::new();
+        let indices: Vec<usize> = words
+            .iter()
+            .map(|word| keyset.push_back_str(word).unwrap())
+            .collect();
+        assert_eq!(indices, [0, 1, 2, 3]);
+        assert_eq!(keyset.push_back_bytes(b"echo", 1.0).unwrap(), 4);
+
+        let mut trie = Trie::new();
+        trie.build(&mut keyset, 0);
+
+        let mut agent = Agent::new();
+        for (word, index) in words.iter().zip(indices) {
+            agent.set_query_str(word);
+            assert!(trie.lookup(&mut agent));
+            assert_eq!(agent.key().id(), keyset.get(index).id());
+        }
+    }
+
+    #[test]
+    fn test_keyset_with_block_sizes() {
+        // Rust-specific: a tiny extra-block threshold sends modest strings
+        // to extra blocks, and reserve and clear honor the custom sizes
+        let mut keyset = Keyset::with_block_sizes(64, 8, 4);
+        keyset.reserve(10, 40);
+        assert_eq!(keyset.key_blocks.len(), 3);
+        assert_eq!(keyset.key_blocks[0].len(), 4);
+        assert_eq!(keyset.spare_base_blocks.len(), 1);
+        assert_eq!(keyset.spare_base_blocks[0].len(), 64);
+
+        let words = [
+            "short",
+            "exactly8",
+            "ninechars",
+            "tiny",
+            "a much longer key",
+            "mid-size!",
+            "",
+            "z",
+            "twelve chars",
+            "eightchr",
+        ];
+        for word in words {
+            keyset.push_back_str(word).unwrap();
+        }
+        let long_words = words.iter().filter(|word| word.len() > 8).count();
+        assert_eq!(keyset.extra_blocks.len(), long_words);
+        assert_eq!(keyset.base_blocks.len(), 1);
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(keyset.get(i).as_str(), *word);
+        }
+
+        let mut trie = crate::trie::Trie::new();
+        trie.build(&mut keyset, 0);
+        for (i, word) in words.iter().enumerate() {
+            assert_eq!(trie.lookup_bytes(word.as_bytes()), Some(keyset.get(i).id()));
+        }
+
+        keyset.clear();
+        keyset.push_back_str("ninechars").unwrap();
+        assert_eq!(keyset.extra_blocks.len(), 1);
+        assert_eq!(keyset.key_blocks[0].len(), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "exceeds base block size")]
+    fn test_keyset_with_block_sizes_rejects_large_extra() {
+        // Rust-specific: strings up to the extra threshold must fit a base block
+        Keyset::with_block_sizes(16, 32, 4);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_keyset_push_back_borrowed_matches_copy() {
+        // Rust-specific: borrowed keys, alone or mixed with copied ones,
+        // build the same trie and report the same IDs as copied keys
+        use crate::grimoire::io::Writer;
+        use crate::trie::Trie;
+
+        let text: Vec<u8> = (0..2000)
+            .map(|i| format!("k{}-{}\n", i % 700, "x".repeat(i % 2500 / 100)))
+            .collect::<String>()
+            .into_bytes();
+        let lines: Vec<&[u8]> = text.split(|&b| b == b'\n').collect();
+        let build = |keyset: &mut Keyset<'_>| {
+            let mut trie = Trie::new();
+            trie.build(keyset, 0);
+            let ids: Vec<usize> = keyset.iter().map(Key::id).collect();
+            let mut writer = Writer::from_vec(Vec::new());
+            trie.write(&mut writer).unwrap();
+            (ids, writer.into_inner().unwrap())
+        };
+
+        let mut copied = Keyset::new();
+        let mut borrowed = Keyset::new();
+        let mut mixed = Keyset::new();
+        for (i, line) in lines.iter().enumerate() {
+            let weight = (i % 5) as f32;
+            assert_eq!(copied.push_back_bytes(line, weight).unwrap(), i);
+            assert_eq!(borrowed.push_back_borrowed(line, weight).unwrap(), i);
+            if i % 2 == 0 {
+                mixed.push_back_borrowed(line, weight).unwrap();
+            } else {
+                mixed.push_back_bytes(line, weight).unwrap();
+            }
+        }
+        assert_eq!(borrowed.get(3).as_bytes().as_ptr(), lines[3].as_ptr());
+        assert_eq!(borrowed.total_length(), copied.total_length());
+
+        let expected = build(&mut copied);
+        assert_eq!(build(&mut borrowed), expected);
+        assert_eq!(build(&mut mixed), expected);
+    }
 }
@@ -27,12 +27,26 @@
 //! - Original version: 0.3.1
 //! - Baseline commit: 4ef33cc5a2b6b4f5e147e4564a5236e163d67982
 //! - Original license: BSD-2-Clause OR LGPL-2.1-or-later
+//!
+//! ## `no_std` support
+//!
+//! Rust-specific: with the default `std` feature disabled the crate is
+//! `no_std` and only needs `alloc`. Building, lookups, searches and
+//! [`Trie::map`] over a byte slice all remain available; `Reader`, `Writer`
+//! and the file and stream based `load`/`save`/`read`/`write` methods
+//! require `std`. I/O errors are then reported with the minimal
+//! [`grimoire::io::Error`] instead of `std::io::Error`.
 
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 #![warn(missing_docs)]
 #![warn(rust_2018_idioms)]
 
+extern crate alloc;
+
 pub mod agent;
 pub mod base;
+pub mod bound_agent;
+pub mod cursor;
 pub mod grimoire;
 pub mod key;
 pub mod keyset;
@@ -42,7 +56,10 @@ pub mod trie;
 // Re-export main types at the crate root
 // These correspond to the public API in include/marisa/*.h
 pub use agent::Agent;
+pub use base::{format_version, version, BuildPhase};
+pub use bound_agent::BoundAgent;
+pub use cursor::{FeedResult, TrieCursor};
 pub use key::Key;
 pub use keyset::Keyset;
 pub use query::Query;
-pub use trie::Trie;
+pub use trie::{SearchKind, Trie};
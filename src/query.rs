@@ -2,29 +2,32 @@
 //!
 //! Ported from: include/marisa/query.h
 
-use std::fmt;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
 
 /// Query represents a search query with a string and optional ID.
 ///
 /// A query can contain either:
-/// - A string to search for (ptr + length)
+/// - A string to search for
 /// - An ID to reverse lookup
+///
+/// Rust-specific: C++ marisa borrows the query string. This query copies
+/// it into its own buffer, so the caller's data does not need to outlive
+/// the query. The buffer is reused across queries.
 #[derive(Clone)]
 pub struct Query {
-    /// Pointer to query string data (borrowed).
-    ptr: Option<*const u8>,
-    /// Length of query string.
-    length: usize,
+    /// Owned copy of the query string.
+    bytes: Vec<u8>,
     /// Query ID for reverse lookup.
     id: usize,
 }
 
-// Manual Debug implementation since raw pointers don't implement Debug
 impl fmt::Debug for Query {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("Query")
-            .field("ptr", &self.ptr.map(|_| "..."))
-            .field("length", &self.length)
+            .field("bytes", &String::from_utf8_lossy(&self.bytes))
+            .field("length", &self.bytes.len())
             .field("id", &self.id)
             .finish()
     }
@@ -40,41 +43,65 @@ impl Query {
     /// Creates a new empty query.
     pub fn new() -> Self {
         Query {
-            ptr: None,
-            length: 0,
+            bytes: Vec::new(),
+            id: 0,
+        }
+    }
+
+    /// Creates a query holding a copy of `bytes`.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        Query {
+            bytes: bytes.to_vec(),
             id: 0,
         }
     }
 
+    /// Creates a query holding a copy of `s`.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    // Infallible, so `FromStr`'s `Result` would only add an unwrap.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &str) -> Self {
+        Query::from_bytes(s.as_bytes())
+    }
+
     /// Returns the character at the specified index.
     ///
     /// # Panics
     ///
     /// Panics if index is out of bounds.
     pub fn get(&self, i: usize) -> u8 {
-        assert!(i < self.length, "Index out of bounds");
-        if let Some(ptr) = self.ptr {
-            unsafe { *ptr.add(i) }
-        } else {
-            panic!("Query has no string data");
-        }
+        assert!(i < self.bytes.len(), "Index out of bounds");
+        self.bytes[i]
     }
 
-    /// Sets the query from a string slice.
+    /// Sets the query from a string slice, copying it.
     pub fn set_str(&mut self, s: &str) {
-        self.ptr = Some(s.as_ptr());
-        self.length = s.len();
+        self.set_bytes(s.as_bytes());
     }
 
-    /// Sets the query from a byte slice.
+    /// Sets the query from a byte slice, copying it.
+    ///
+    /// The bytes need not be valid UTF-8.
     pub fn set_bytes(&mut self, bytes: &[u8]) {
-        if bytes.is_empty() {
-            self.ptr = None;
-            self.length = 0;
-        } else {
-            self.ptr = Some(bytes.as_ptr());
-            self.length = bytes.len();
-        }
+        self.bytes.clear();
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Appends one byte to the query string.
+    ///
+    /// Rust-specific: used by `TrieCursor` to grow its query in place.
+    pub(crate) fn push(&mut self, byte: u8) {
+        self.bytes.push(byte);
+    }
+
+    /// Shortens the query string to its first `len` bytes.
+    ///
+    /// Rust-specific: used by case-insensitive lookup to backtrack.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.bytes.truncate(len);
     }
 
     /// Sets the query ID.
@@ -86,11 +113,7 @@ impl Query {
     ///
     /// Returns an empty slice if no string is set.
     pub fn as_bytes(&self) -> &[u8] {
-        if let Some(ptr) = self.ptr {
-            unsafe { std::slice::from_raw_parts(ptr, self.length) }
-        } else {
-            &[]
-        }
+        &self.bytes
     }
 
     /// Returns the query string as a str reference.
@@ -99,17 +122,35 @@ impl Query {
     ///
     /// Panics if the query contains invalid UTF-8.
     pub fn as_str(&self) -> &str {
-        std::str::from_utf8(self.as_bytes()).expect("Invalid UTF-8 in query")
+        core::str::from_utf8(self.as_bytes()).expect("Invalid UTF-8 in query")
     }
 
-    /// Returns a pointer to the query data.
+    /// Returns a pointer to the query data, or `None` if it is empty.
     pub fn ptr(&self) -> Option<*const u8> {
-        self.ptr
+        if self.bytes.is_empty() {
+            None
+        } else {
+            Some(self.bytes.as_ptr())
+        }
     }
 
     /// Returns the length of the query string.
     pub fn length(&self) -> usize {
-        self.length
+        self.bytes.len()
+    }
+
+    /// Returns the length of the query string (same as `length`).
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Returns true if the query string is empty.
+    ///
+    /// Rust-specific: Not present in C++ version.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
     }
 
     /// Returns the query ID.
@@ -119,20 +160,16 @@ impl Query {
 
     /// Clears the query to empty state.
     pub fn clear(&mut self) {
-        *self = Query::new();
+        self.bytes.clear();
+        self.id = 0;
     }
 
     /// Swaps with another query.
     pub fn swap(&mut self, other: &mut Query) {
-        std::mem::swap(self, other);
+        core::mem::swap(self, other);
     }
 }
 
-// Safety: Query only holds a pointer that must remain valid for its lifetime.
-// The user is responsible for ensuring the borrowed data outlives the Query.
-unsafe impl Send for Query {}
-unsafe impl Sync for Query {}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -244,6 +281,64 @@ mod tests {
         assert_eq!(q2.id(), 1);
     }
 
+    #[test]
+    fn test_query_owns_bytes() {
+        // Rust-specific: the query keeps its own copy of the data
+        let mut query = Query::new();
+        {
+            let temp = vec![b'a', 0xFF, b'b'];
+            query.set_bytes(&temp);
+        }
+        assert_eq!(query.as_bytes(), &[b'a', 0xFF, b'b']);
+        assert_eq!(query.get(1), 0xFF);
+
+        let cloned = query.clone();
+        query.set_str("x");
+        assert_eq!(cloned.as_bytes(), &[b'a', 0xFF, b'b']);
+        assert_eq!(query.as_bytes(), b"x");
+    }
+
+    #[test]
+    fn test_query_constructors() {
+        // Rust-specific: from_bytes/from_str copy the data, and len and
+        // is_empty agree with length
+        let query = Query::from_str("apple");
+        assert_eq!(query.as_bytes(), b"apple");
+        assert_eq!(query.len(), 5);
+        assert_eq!(query.len(), query.length());
+        assert!(!query.is_empty());
+        assert_eq!(query.id(), 0);
+
+        let bytes = [0u8, 0xFF, b'x'];
+        let query = Query::from_bytes(&bytes);
+        assert_eq!(query.as_bytes(), &bytes);
+        assert_eq!(query.len(), 3);
+
+        let query = Query::from_bytes(&[]);
+        assert!(query.is_empty());
+        assert_eq!(query.len(), 0);
+        assert!(Query::new().is_empty());
+    }
+
+    #[test]
+    fn test_query_read_back_through_agent() {
+        // Rust-specific: what Agent::set_query_* stores is visible through
+        // the public Query accessors
+        use crate::Agent;
+
+        let mut agent = Agent::new();
+        agent.set_query_str("banana");
+        assert_eq!(agent.query().as_bytes(), b"banana");
+        assert_eq!(agent.query().len(), 6);
+
+        agent.set_query_bytes(b"\0a");
+        assert_eq!(agent.query().as_bytes(), b"\0a");
+        assert_eq!(agent.query().len(), 2);
+
+        agent.set_query_bytes(b"");
+        assert!(agent.query().is_empty());
+    }
+
     #[test]
     fn test_query_with_unicode() {
         let s = "こんにちは";
@@ -3,6 +3,8 @@
 //! This test verifies that Rust-generated binary files are identical to
 //! C++-generated files when built from the same keyset.
 
+#![cfg(feature = "std")]
+
 use rsmarisa::{Keyset, Trie};
 use std::fs;
 use std::path::PathBuf;
@@ -0,0 +1,119 @@
+//! Randomized rank/select property test for `BitVector`.
+//!
+//! Rust-specific: generates bit patterns from a fixed-seed xorshift
+//! generator so failures are reproducible, and checks that rank and select
+//! invert each other at every position. Patterns mix uniform densities with
+//! runs whose edges fall on 64-bit word and 512-bit rank block boundaries,
+//! where the select index samples and the rank blocks meet.
+
+use rsmarisa::grimoire::vector::bit_vector::BitVector;
+
+/// Number of random bit patterns to check.
+const NUM_CASES: usize = 60;
+
+/// Longest pattern, in bits.
+const MAX_BITS: usize = 100_000;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Returns a value in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Generates one random bit pattern.
+fn random_bits(rng: &mut XorShift) -> Vec<bool> {
+    let len = match rng.below(4) {
+        0 => rng.below(1100),
+        1 => 512 * (1 + rng.below(MAX_BITS / 512)),
+        _ => rng.below(MAX_BITS + 1),
+    };
+    match rng.below(5) {
+        // Uniform with a density from nearly empty to nearly full
+        0 | 1 => {
+            let per_mille = [1, 10, 100, 500, 900, 990, 999][rng.below(7)];
+            (0..len).map(|_| rng.below(1000) < per_mille).collect()
+        }
+        // Alternating runs with lengths around word and block sizes
+        2 | 3 => {
+            let mut bits = Vec::with_capacity(len);
+            let mut bit = rng.below(2) == 0;
+            while bits.len() < len {
+                let run = match rng.below(3) {
+                    0 => 1 + rng.below(8),
+                    1 => 64 * (1 + rng.below(8)) - 1 + rng.below(3),
+                    _ => 512 * (1 + rng.below(4)) - 1 + rng.below(3),
+                };
+                bits.extend(std::iter::repeat(bit).take(run.min(len - bits.len())));
+                bit = !bit;
+            }
+            bits
+        }
+        // Constant
+        _ => vec![rng.below(2) == 0; len],
+    }
+}
+
+fn check_bits(case: usize, bits: &[bool], bv: &BitVector) {
+    let num_1s = bits.iter().filter(|&&bit| bit).count();
+    assert_eq!(bv.size(), bits.len(), "case {}", case);
+    assert_eq!(bv.num_1s(), num_1s, "case {}", case);
+    assert_eq!(bv.num_0s(), bits.len() - num_1s, "case {}", case);
+
+    for i in 0..num_1s {
+        let pos = bv.select1(i);
+        assert!(bits[pos], "case {} select1({}) = {}", case, i, pos);
+        assert_eq!(bv.rank1(pos), i, "case {} select1({})", case, i);
+    }
+    for i in 0..bits.len() - num_1s {
+        let pos = bv.select0(i);
+        assert!(!bits[pos], "case {} select0({}) = {}", case, i, pos);
+        assert_eq!(bv.rank0(pos), i, "case {} select0({})", case, i);
+    }
+
+    // select(rank(p) - 1) is the last bit before p, select(rank(p)) the
+    // first at or after it
+    for p in 0..=bits.len() {
+        let rank1 = bv.rank1(p);
+        let rank0 = bv.rank0(p);
+        assert_eq!(rank1 + rank0, p, "case {} rank at {}", case, p);
+        if rank1 > 0 {
+            assert!(bv.select1(rank1 - 1) < p, "case {} p {}", case, p);
+        }
+        if rank1 < num_1s {
+            assert!(bv.select1(rank1) >= p, "case {} p {}", case, p);
+        }
+        if rank0 > 0 {
+            assert!(bv.select0(rank0 - 1) < p, "case {} p {}", case, p);
+        }
+        if rank0 < bits.len() - num_1s {
+            assert!(bv.select0(rank0) >= p, "case {} p {}", case, p);
+        }
+    }
+}
+
+#[test]
+fn test_bit_vector_rank_select_invert() {
+    let mut rng = XorShift(0x2545_F491_4F6C_DD1D);
+    for case in 0..NUM_CASES {
+        let bits = random_bits(&mut rng);
+        let mut bv = BitVector::from_bits(&bits);
+        if case % 2 == 0 {
+            bv.build(true, true);
+        } else {
+            bv.build_lazy(true, true);
+        }
+        check_bits(case, &bits, &bv);
+    }
+}
@@ -3,6 +3,8 @@
 //! Verifies that rsmarisa-build and rsmarisa-lookup produce
 //! results identical to C++ marisa-trie tools.
 
+#![cfg(feature = "cli")]
+
 use std::fs;
 use std::io::Write;
 use std::process::Command;
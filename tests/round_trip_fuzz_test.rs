@@ -0,0 +1,174 @@
+//! Randomized round-trip test: build -> write -> read -> lookup.
+//!
+//! Rust-specific: generates keysets from a fixed-seed xorshift generator so
+//! failures are reproducible, covering empty keys, NUL bytes, long keys and
+//! duplicates across every tail mode, node order and trie count.
+
+#![cfg(feature = "std")]
+
+use rsmarisa::base::{NodeOrder, TailMode};
+use rsmarisa::grimoire::io::{Reader, Writer};
+use rsmarisa::{Agent, Keyset, Trie};
+use std::collections::HashSet;
+
+/// Number of random keysets to check.
+const NUM_CASES: usize = 300;
+
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    /// Returns a value in `0..n`.
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+/// Generates one random key, biased towards a small alphabet so keys share
+/// prefixes and suffixes (exercising links and tail merging).
+fn random_key(rng: &mut XorShift) -> Vec<u8> {
+    let len = match rng.below(10) {
+        0 => 0,
+        1 => 64 + rng.below(400),
+        _ => 1 + rng.below(12),
+    };
+    let alphabet = rng.below(4);
+    (0..len)
+        .map(|_| match alphabet {
+            0 => b"ab\0"[rng.below(3)],
+            1 => b"abcde"[rng.below(5)],
+            2 => rng.below(256) as u8,
+            _ => b'a' + rng.below(26) as u8,
+        })
+        .collect()
+}
+
+fn random_flags(rng: &mut XorShift) -> i32 {
+    let num_tries = 1 + rng.below(4) as i32;
+    let tail_mode = if rng.below(2) == 0 {
+        TailMode::TextTail
+    } else {
+        TailMode::BinaryTail
+    };
+    let node_order = if rng.below(2) == 0 {
+        NodeOrder::Label
+    } else {
+        NodeOrder::Weight
+    };
+    num_tries | tail_mode as i32 | node_order as i32
+}
+
+/// Checks that a common prefix search reports every key that is a prefix
+/// of `query` exactly once, shortest first, with distinct IDs.
+fn check_common_prefixes(trie: &Trie, keys: &HashSet<&[u8]>, query: &[u8], case: usize) {
+    let mut agent = Agent::new();
+    agent.set_query_bytes(query);
+    let mut found: Vec<(usize, Vec<u8>)> = Vec::new();
+    while trie.common_prefix_search(&mut agent) {
+        found.push((agent.key().id(), agent.key().as_bytes().to_vec()));
+    }
+
+    let expected: Vec<&[u8]> = (0..=query.len())
+        .map(|len| &query[..len])
+        .filter(|prefix| keys.contains(prefix))
+        .collect();
+    let found_keys: Vec<&[u8]> = found.iter().map(|(_, key)| key.as_slice()).collect();
+    assert_eq!(found_keys, expected, "case {} query {:?}", case, query);
+
+    let ids: HashSet<usize> = found.iter().map(|&(id, _)| id).collect();
+    assert_eq!(ids.len(), found.len(), "case {} duplicate IDs", case);
+}
+
+fn check_case(case: usize, rng: &mut XorShift) {
+    let num_keys = rng.below(200);
+    let mut keys: Vec<Vec<u8>> = (0..num_keys).map(|_| random_key(rng)).collect();
+    // Duplicates of earlier keys
+    for _ in 0..rng.below(20) {
+        if !keys.is_empty() {
+            let dup = keys[rng.below(keys.len())].clone();
+            keys.push(dup);
+        }
+    }
+    let flags = random_flags(rng);
+
+    let mut keyset = Keyset::new();
+    for key in &keys {
+        keyset.push_back_bytes(key, 1.0).unwrap();
+    }
+    let mut trie = Trie::new();
+    trie.build(&mut keyset, flags);
+
+    let mut writer = Writer::from_vec(Vec::new());
+    trie.write(&mut writer).unwrap();
+    let data = writer.into_inner().unwrap();
+    assert_eq!(
+        data.len(),
+        trie.io_size(),
+        "case {} flags {:#x}",
+        case,
+        flags
+    );
+
+    let mut restored = Trie::new();
+    restored
+        .read(&mut Reader::from_bytes(&data))
+        .unwrap_or_else(|e| panic!("case {} flags {:#x}: {}", case, flags, e));
+
+    let unique: HashSet<&[u8]> = keys.iter().map(Vec::as_slice).collect();
+    assert_eq!(restored.num_keys(), unique.len(), "case {}", case);
+
+    let mut agent = Agent::new();
+    for key in &keys {
+        agent.set_query_bytes(key);
+        assert!(trie.lookup(&mut agent), "case {} key {:?}", case, key);
+        let id = agent.key().id();
+
+        agent.set_query_bytes(key);
+        assert!(
+            restored.lookup(&mut agent),
+            "case {} flags {:#x} key {:?}",
+            case,
+            flags,
+            key
+        );
+        assert_eq!(agent.key().id(), id, "case {} key {:?}", case, key);
+
+        agent.set_query_id(id);
+        restored.reverse_lookup(&mut agent);
+        assert_eq!(agent.key().as_bytes(), key.as_slice(), "case {}", case);
+
+        check_common_prefixes(&restored, &unique, key, case);
+    }
+
+    for _ in 0..50 {
+        let probe = random_key(rng);
+        if unique.contains(probe.as_slice()) {
+            continue;
+        }
+        check_common_prefixes(&restored, &unique, &probe, case);
+        agent.set_query_bytes(&probe);
+        assert!(
+            !restored.lookup(&mut agent),
+            "case {} flags {:#x} non-key {:?}",
+            case,
+            flags,
+            probe
+        );
+    }
+}
+
+#[test]
+fn test_round_trip_random_keysets() {
+    let mut rng = XorShift(0x9E37_79B9_7F4A_7C15);
+    for case in 0..NUM_CASES {
+        check_case(case, &mut rng);
+    }
+}